@@ -0,0 +1,695 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use quillborn_core::analysis;
+use quillborn_core::export;
+use quillborn_core::manuscript::error::WithPath;
+use quillborn_core::manuscript::ids::ChapterId;
+use quillborn_core::manuscript::import::{self, SplitMethod};
+use quillborn_core::manuscript::project::{Project, ProjectError};
+use quillborn_core::planning::timeline::Timeline;
+use quillborn_core::planning::wiki::LinkIndex;
+use quillborn_core::plugins::PluginHost;
+use quillborn_core::rpc::{RpcConfig, RpcServer};
+use quillborn_core::search;
+
+/// Work on Quillborn `.qb` projects without the GUI.
+#[derive(Parser)]
+#[command(name = "quillborn-cli", version)]
+struct Cli {
+    /// Print JSON instead of human-readable text
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a new project inside DIR
+    New {
+        dir: PathBuf,
+        title: String,
+        #[arg(long, default_value = "")]
+        author: String,
+    },
+    /// Append an empty chapter
+    AddChapter {
+        project: PathBuf,
+        title: String,
+        /// Id of the part or chapter to nest the new chapter under
+        #[arg(long)]
+        parent: Option<String>,
+    },
+    /// Show word counts and progress
+    Stats { project: PathBuf },
+    /// Find text in the manuscript
+    Search {
+        project: PathBuf,
+        query: String,
+        #[command(flatten)]
+        flags: SearchFlags,
+        /// Also search notes, character sheets, ghost notes and the timeline
+        #[arg(long)]
+        everywhere: bool,
+    },
+    /// Search chapters and notes through the full-text index, best match
+    /// first. Supports `"phrases"`, `word*`, AND/OR/NOT, `a NEAR/3 b` and
+    /// filters such as `pov:Mara`, `status:draft`, `part:"Part Two"`,
+    /// `category:places` and `in:trash`.
+    Query {
+        project: PathBuf,
+        query: String,
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// List the spellings of a word used in the manuscript, including
+    /// near misses like "Kaelith" for "Caelith", and where they occur
+    Spellings { project: PathBuf, word: String },
+    /// List the project's smart collections, or with NAME (or id) the
+    /// nodes currently in that collection
+    Collection { project: PathBuf, name: Option<String> },
+    /// Replace text across the manuscript. Only lists the changes unless
+    /// --apply is given.
+    Replace {
+        project: PathBuf,
+        query: String,
+        /// With --regex, `$1` or `${name}` insert capture groups
+        replacement: String,
+        #[command(flatten)]
+        flags: SearchFlags,
+        /// Write every listed change, after taking a snapshot
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Export the manuscript
+    Export {
+        project: PathBuf,
+        #[arg(long, value_enum)]
+        format: Format,
+        /// Output file; relative paths land in the project's exports/ folder,
+        /// absolute ones in an existing folder outside the project
+        #[arg(short, long)]
+        output: String,
+        /// Export only the chapters in this smart collection, by name or id
+        #[arg(long)]
+        collection: Option<String>,
+    },
+    /// Save a snapshot of the manuscript structure
+    Snapshot {
+        project: PathBuf,
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Put back the chapter text kept in a snapshot, such as the one
+    /// `replace --apply` takes
+    Restore {
+        project: PathBuf,
+        /// Snapshot file name, as printed when it was taken
+        snapshot: String,
+    },
+    /// Report broken links, timeline contradictions and missing files.
+    /// Exits with status 1 when anything is found.
+    Check {
+        project: PathBuf,
+        /// Also run the checks registered by the plugins in this directory
+        #[arg(long)]
+        plugins: Option<PathBuf>,
+    },
+    /// Flag adverbs, filter words, passive voice, clichés and other style
+    /// issues, following the project's style.json
+    Lint {
+        project: PathBuf,
+        /// Only this chapter, by id
+        #[arg(long)]
+        chapter: Option<String>,
+    },
+    /// List the words the manuscript uses far more than English in general
+    Overused {
+        project: PathBuf,
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// List the plugins installed in DIR and what they provide
+    Plugins { dir: PathBuf },
+    /// Serve the JSON-RPC automation API on 127.0.0.1 until interrupted
+    Serve {
+        #[arg(long, default_value_t = 7417)]
+        port: u16,
+        /// Bearer token clients must send; a random one is printed if omitted
+        #[arg(long)]
+        token: Option<String>,
+        /// Refuse methods that change projects or write files
+        #[arg(long)]
+        read_only: bool,
+    },
+    /// Import a markdown or text file as new chapters
+    Import {
+        project: PathBuf,
+        file: PathBuf,
+        #[arg(long, value_enum, default_value = "heading")]
+        split: Split,
+        #[arg(long)]
+        parent: Option<String>,
+    },
+}
+
+#[derive(Args)]
+struct SearchFlags {
+    #[arg(long)]
+    case_sensitive: bool,
+    /// Treat QUERY as a regular expression
+    #[arg(long)]
+    regex: bool,
+    /// Treat QUERY as a single word and also match near spellings
+    #[arg(long, conflicts_with = "regex")]
+    fuzzy: bool,
+    /// Only match whole words
+    #[arg(long)]
+    whole_word: bool,
+    /// Match letters regardless of accents
+    #[arg(long)]
+    ignore_accents: bool,
+}
+
+impl SearchFlags {
+    fn options(&self) -> search::SearchOptions {
+        search::SearchOptions {
+            case_sensitive: self.case_sensitive,
+            mode: if self.regex {
+                search::SearchMode::Regex
+            } else if self.fuzzy {
+                search::SearchMode::Fuzzy
+            } else {
+                search::SearchMode::Literal
+            },
+            whole_word: self.whole_word,
+            accent_insensitive: self.ignore_accents,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Epub,
+    Html,
+    Latex,
+    Md,
+    Txt,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Split {
+    Heading,
+    Break,
+    Single,
+}
+
+impl From<Split> for SplitMethod {
+    fn from(split: Split) -> Self {
+        match split {
+            Split::Heading => SplitMethod::Heading,
+            Split::Break => SplitMethod::Break,
+            Split::Single => SplitMethod::Single,
+        }
+    }
+}
+
+/// The result of a command, ready to print either way.
+struct Output {
+    json: serde_json::Value,
+    text: String,
+    /// Whether the command should exit with a failure status.
+    failed: bool,
+    /// Whether the command already printed everything it had to say.
+    quiet: bool,
+}
+
+impl Output {
+    fn new<T: Serialize>(value: &T, text: String) -> Result<Self, ProjectError> {
+        Ok(Output {
+            json: serde_json::to_value(value)?,
+            text,
+            failed: false,
+            quiet: false,
+        })
+    }
+
+    fn quiet() -> Self {
+        Output {
+            json: serde_json::Value::Null,
+            text: String::new(),
+            failed: false,
+            quiet: true,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChapterStats {
+    id: String,
+    title: String,
+    status: String,
+    word_count: u64,
+}
+
+#[derive(Serialize)]
+struct Stats {
+    title: String,
+    author: String,
+    total_word_count: u64,
+    word_count_target: Option<u64>,
+    chapters: Vec<ChapterStats>,
+}
+
+#[derive(Serialize)]
+struct CheckIssue {
+    kind: String,
+    message: String,
+}
+
+fn parse_parent(parent: Option<&str>) -> Result<Option<ChapterId>, ProjectError> {
+    parent.map(ChapterId::parse).transpose()
+}
+
+fn snippet(snippet: &search::Snippet) -> String {
+    format!("{}{}{}", snippet.before, snippet.matched, snippet.after)
+}
+
+/// A smart collection by id, or failing that by name ignoring case.
+fn find_collection<'c>(
+    collections: &'c search::SmartCollections,
+    name: &str,
+) -> Result<&'c search::SmartCollection, ProjectError> {
+    collections
+        .get(name)
+        .or_else(|_| {
+            collections
+                .collections
+                .iter()
+                .find(|c| c.name.to_lowercase() == name.to_lowercase())
+                .ok_or_else(|| ProjectError::CollectionNotFound(name.to_string()))
+        })
+}
+
+fn stats(project: &Project) -> Result<Output, ProjectError> {
+    let chapters: Vec<ChapterStats> = project
+        .chapter_order()
+        .iter()
+        .filter_map(|id| project.structure.nodes.get(id.as_str()))
+        .map(|node| ChapterStats {
+            id: node.id.clone(),
+            title: node.title.clone(),
+            status: node.status.as_str().to_string(),
+            word_count: node.word_count,
+        })
+        .collect();
+    let stats = Stats {
+        title: project.metadata.title.clone(),
+        author: project.metadata.author.clone(),
+        total_word_count: project.total_word_count(),
+        word_count_target: project.metadata.word_count_target,
+        chapters,
+    };
+
+    let mut text = stats.title.clone();
+    if !stats.author.is_empty() {
+        text.push_str(&format!(" by {}", stats.author));
+    }
+    text.push_str(&format!("\n{} words in {} chapters", stats.total_word_count, stats.chapters.len()));
+    if let Some(target) = stats.word_count_target.filter(|t| *t > 0) {
+        let percent = stats.total_word_count as f64 / target as f64 * 100.0;
+        text.push_str(&format!(" ({:.0}% of {})", percent, target));
+    }
+    text.push('\n');
+    for chapter in &stats.chapters {
+        text.push_str(&format!(
+            "\n  {:>7}  {:<8} {}",
+            chapter.word_count, chapter.status, chapter.title
+        ));
+    }
+    Output::new(&stats, text)
+}
+
+fn check(project: &Project, plugins: Option<&PluginHost>) -> Result<Output, ProjectError> {
+    let mut issues = Vec::new();
+
+    for id in project.chapter_order() {
+        if !project.chapter_path(&id).exists() {
+            let title = &project.structure.nodes[id.as_str()].title;
+            issues.push(CheckIssue {
+                kind: "missing_chapter_file".to_string(),
+                message: format!("\"{}\" has no chapter file", title),
+            });
+        }
+    }
+
+    for link in LinkIndex::build(project)?.broken_links() {
+        issues.push(CheckIssue {
+            kind: "broken_link".to_string(),
+            message: format!(
+                "[[{}]] in \"{}\" (line {}) doesn't match any chapter, note or character",
+                link.target, link.source_title, link.line_number
+            ),
+        });
+    }
+
+    for issue in Timeline::load(project)?.check(project)? {
+        issues.push(CheckIssue {
+            kind: serde_json::to_value(&issue.kind)?
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            message: issue.message,
+        });
+    }
+
+    if let Some(host) = plugins {
+        let results = host.run_checks(project);
+        for issue in results.issues {
+            issues.push(CheckIssue {
+                kind: format!("{}/{}", issue.plugin_id, issue.check_id),
+                message: issue.message,
+            });
+        }
+        for failed in results.errors {
+            issues.push(CheckIssue {
+                kind: format!("{}/{}", failed.plugin_id, failed.check_id),
+                message: format!("check failed: {}", failed.error),
+            });
+        }
+    }
+
+    let text = if issues.is_empty() {
+        "No problems found".to_string()
+    } else {
+        issues
+            .iter()
+            .map(|i| format!("{}: {}", i.kind, i.message))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let mut output = Output::new(&issues, text)?;
+    output.failed = !issues.is_empty();
+    Ok(output)
+}
+
+fn plugins(host: &PluginHost) -> Result<Output, ProjectError> {
+    let mut lines = Vec::new();
+    for plugin in &host.plugins {
+        let manifest = &plugin.manifest;
+        lines.push(format!("{} {} ({})", manifest.id, manifest.version, manifest.name));
+        let contributions = &plugin.contributions;
+        for format in &contributions.exports {
+            lines.push(format!("  export  {} (.{})", format.id, format.extension));
+        }
+        for check in &contributions.checks {
+            lines.push(format!("  check   {}", check.id));
+        }
+        for command in &contributions.commands {
+            lines.push(format!("  command {}", command.id));
+        }
+    }
+    for failure in &host.errors {
+        lines.push(format!("failed to load {}: {}", failure.path, failure.error));
+    }
+    if lines.is_empty() {
+        lines.push("No plugins installed".to_string());
+    }
+    let mut output = Output::new(host, lines.join("\n"))?;
+    output.failed = !host.errors.is_empty();
+    Ok(output)
+}
+
+fn serve(config: RpcConfig, json: bool) -> Result<Output, ProjectError> {
+    let read_only = config.read_only;
+    let token = config.token.clone();
+    let server = RpcServer::start(config)?;
+    let url = format!("http://{}/", server.addr());
+    if json {
+        println!("{}", serde_json::json!({ "url": url, "token": token, "read_only": read_only }));
+    } else {
+        println!("Listening on {}{}", url, if read_only { " (read-only)" } else { "" });
+        println!("Token: {}", token);
+    }
+    server.wait();
+    Ok(Output::quiet())
+}
+
+fn run(command: Command, json: bool) -> Result<Output, ProjectError> {
+    match command {
+        Command::New { dir, title, author } => {
+            let project = Project::create(&dir, &title, &author)?;
+            let path = project.path.display().to_string();
+            Output::new(&serde_json::json!({ "path": path }), format!("Created {}", path))
+        }
+        Command::AddChapter { project, title, parent } => {
+            let mut project = Project::open(&project)?;
+            let parent = parse_parent(parent.as_deref())?;
+            let chapter = project.add_chapter(&title, parent.as_ref())?;
+            let text = format!("Added \"{}\" ({})", chapter.title, chapter.id);
+            Output::new(&chapter, text)
+        }
+        Command::Stats { project } => stats(&Project::open(&project)?),
+        Command::Search { project, query, flags, everywhere: true } => {
+            let groups = search::search_project(&Project::open(&project)?, &query, &flags.options())?;
+            let mut text = String::new();
+            let mut count = 0;
+            for group in &groups {
+                text.push_str(&format!("[{}]\n", group.kind.as_str()));
+                for result in &group.results {
+                    let title = match &result.field {
+                        Some(field) => format!("{} ({})", result.title, field),
+                        None => result.title.clone(),
+                    };
+                    for m in &result.matches {
+                        text.push_str(&format!("{}:{}: {}\n", title, m.matched.line_number, snippet(&m.matched.snippet)));
+                        count += 1;
+                    }
+                }
+            }
+            text.push_str(&format!("{} matches", count));
+            Output::new(&groups, text)
+        }
+        Command::Search { project, query, flags, everywhere: false } => {
+            let project = Project::open(&project)?;
+            let results = search::search_manuscript(&project, &query, &flags.options())?;
+            let mut text = String::new();
+            for result in &results {
+                for m in &result.matches {
+                    text.push_str(&format!(
+                        "{}:{}: {}\n",
+                        result.chapter_title, m.line_number, snippet(&m.snippet)
+                    ));
+                }
+            }
+            let count: usize = results.iter().map(|r| r.matches.len()).sum();
+            text.push_str(&format!("{} matches", count));
+            Output::new(&results, text)
+        }
+        Command::Query { project, query, limit } => {
+            let hits = search::search_index(&Project::open(&project)?, &query, limit)?;
+            let mut text = String::new();
+            for hit in &hits {
+                let kind = hit.category.as_deref().unwrap_or("chapter");
+                text.push_str(&format!("{:>7.2}  {} ({}, {} matches)\n", hit.score, hit.title, kind, hit.matches));
+            }
+            text.push_str(&format!("{} results", hits.len()));
+            Output::new(&hits, text)
+        }
+        Command::Spellings { project, word } => {
+            let found = search::fuzzy_search(&Project::open(&project)?, &word)?;
+            let mut text = String::new();
+            for term in &found.terms {
+                let sound = if term.sounds_alike { ", sounds alike" } else { "" };
+                text.push_str(&format!("{:>6}  {} ({} edits{})\n", term.count, term.term, term.edits, sound));
+            }
+            text.push('\n');
+            for result in &found.results {
+                for m in &result.matches {
+                    text.push_str(&format!(
+                        "{}:{}: {}\n",
+                        result.chapter_title, m.line_number, snippet(&m.snippet)
+                    ));
+                }
+            }
+            text.push_str(&format!("{} spellings", found.terms.len()));
+            Output::new(&found, text)
+        }
+        Command::Collection { project, name } => {
+            let project = Project::open(&project)?;
+            let collections = search::SmartCollections::load(&project)?;
+            let Some(name) = name else {
+                let text = collections
+                    .collections
+                    .iter()
+                    .map(|c| format!("{}  {}  ({})", c.id, c.name, c.query))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                return Output::new(&collections.collections, text);
+            };
+            let members = find_collection(&collections, &name)?.members(&project)?;
+            let text = members
+                .iter()
+                .map(|node| format!("{}  {} ({} words)", node.id, node.title, node.word_count))
+                .chain(std::iter::once(format!("{} nodes", members.len())))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Output::new(&members, text)
+        }
+        Command::Replace { project, query, replacement, flags, apply } => {
+            let mut project = Project::open(&project)?;
+            let options = flags.options();
+            let proposed = search::preview_replace(&project, &query, &replacement, &options)?;
+            let mut text = String::new();
+            for change in &proposed {
+                text.push_str(&format!(
+                    "{}:{}: {} -> {}\n",
+                    change.chapter_title, change.line_number, change.matched, change.replacement
+                ));
+            }
+            if !apply {
+                text.push_str(&format!("{} changes (dry run; pass --apply to write them)", proposed.len()));
+                return Output::new(&proposed, text);
+            }
+            let ids: Vec<String> = proposed.into_iter().map(|change| change.id).collect();
+            let outcome = search::apply_replace(&mut project, &query, &replacement, &options, &ids)?;
+            text.push_str(&format!("Replaced {} matches", outcome.replaced));
+            if let Some(snapshot) = &outcome.snapshot {
+                text.push_str(&format!(" (snapshot {})", snapshot));
+            }
+            Output::new(&outcome, text)
+        }
+        Command::Export { project, format, output, collection } => {
+            let project = Project::open(&project)?;
+            // The folder of an absolute -o was named on the command line, so
+            // it needs no further permission.
+            let requested = Path::new(&output);
+            let allow_dirs: Vec<PathBuf> =
+                requested.parent().filter(|_| requested.is_absolute()).map(Path::to_path_buf).into_iter().collect();
+            let collection = match collection {
+                Some(name) => {
+                    let collections = search::SmartCollections::load(&project)?;
+                    Some(find_collection(&collections, &name)?.id.clone())
+                }
+                None => None,
+            };
+            let project_path = project.path.display().to_string();
+            let written = match format {
+                Format::Epub => export::export_epub(project_path, output, collection, &allow_dirs)?,
+                Format::Html => export::export_html(project_path, output, collection, &allow_dirs)?,
+                Format::Latex => export::export_latex(project_path, output, collection, &allow_dirs)?,
+                Format::Md => export::export_markdown(project_path, output, collection, &allow_dirs)?,
+                Format::Txt => export::export_plain_text(project_path, output, collection, &allow_dirs)?,
+            };
+            Output::new(&serde_json::json!({ "path": written }), format!("Exported to {}", written))
+        }
+        Command::Snapshot { project, name } => {
+            let filename = Project::open(&project)?.create_snapshot(name.as_deref())?;
+            Output::new(
+                &serde_json::json!({ "snapshot": filename }),
+                format!("Saved snapshot {}", filename),
+            )
+        }
+        Command::Restore { project, snapshot } => {
+            let restored = Project::open(&project)?.restore_snapshot(&snapshot)?;
+            Output::new(
+                &serde_json::json!({ "restored": restored }),
+                format!("Restored {} chapters from {}", restored.len(), snapshot),
+            )
+        }
+        Command::Check { project, plugins } => {
+            let host = plugins.as_deref().map(PluginHost::load).transpose()?;
+            check(&Project::open(&project)?, host.as_ref())
+        }
+        Command::Lint { project, chapter } => {
+            let project = Project::open(&project)?;
+            let chapters = match chapter {
+                Some(id) => vec![analysis::lint_chapter(&project, &ChapterId::parse(&id)?)?],
+                None => analysis::lint_manuscript(&project)?,
+            };
+            let mut text = String::new();
+            let mut total = 0;
+            for chapter in &chapters {
+                for d in &chapter.diagnostics {
+                    text.push_str(&format!(
+                        "{}:{}:{} [{}] {}: {}\n",
+                        chapter.chapter_title,
+                        d.line_number,
+                        d.span.start_char + 1,
+                        d.severity.as_str(),
+                        d.rule.as_str(),
+                        d.message
+                    ));
+                }
+                total += chapter.diagnostics.len();
+            }
+            text.push_str(&format!("{} diagnostics", total));
+            Output::new(&chapters, text)
+        }
+        Command::Overused { project, limit } => {
+            let words = analysis::overused_words(&Project::open(&project)?, limit)?;
+            let text = words
+                .iter()
+                .map(|w| format!("{:<16} {:>5} uses  {:>6.1}x", w.lemma, w.count, w.ratio))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Output::new(&words, text)
+        }
+        Command::Plugins { dir } => plugins(&PluginHost::load(&dir)?),
+        Command::Serve { port, token, read_only } => serve(
+            RpcConfig {
+                port,
+                token: token.unwrap_or_else(RpcConfig::generate_token),
+                read_only,
+            },
+            json,
+        ),
+        Command::Import { project, file, split, parent } => {
+            let mut project = Project::open(&project)?;
+            let parent = parse_parent(parent.as_deref())?;
+            let text = fs::read_to_string(&file).with_path(&file)?;
+            let chapters = import::import_text(&mut project, &text, split.into(), parent.as_ref())?;
+            let summary = chapters
+                .iter()
+                .map(|c| format!("  {} ({} words)", c.title, c.word_count))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Output::new(
+                &chapters,
+                format!("Imported {} chapters\n{}", chapters.len(), summary),
+            )
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command, cli.json) {
+        Ok(output) => {
+            if !output.quiet {
+                if cli.json {
+                    println!("{}", output.json);
+                } else {
+                    println!("{}", output.text);
+                }
+            }
+            if output.failed {
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(error) => {
+            if cli.json {
+                eprintln!("{}", serde_json::to_string(&error).unwrap_or_default());
+            } else {
+                eprintln!("error: {}", error);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
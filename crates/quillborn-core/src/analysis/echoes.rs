@@ -0,0 +1,388 @@
+//! Words a manuscript repeats: echoes within a few words of each other,
+//! found by the linter, and pet words used more across the book than
+//! English in general uses them.
+//!
+//! Words are grouped by lemma, so "glance", "glanced" and "glancing"
+//! count as one word. Stop words, names and contractions are left out.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use super::style::{names, normalize, prose_lines, NOT_PARTICIPLES};
+use crate::manuscript::project::{Project, ProjectError};
+use crate::search::MatchSpan;
+
+/// Words used fewer times than this are never reported as overused.
+const MIN_USES: usize = 5;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct OverusedWord {
+    pub lemma: String,
+    pub count: usize,
+    /// Uses per million words of the manuscript.
+    pub per_million: f64,
+    /// Uses per million words in the bundled baseline.
+    pub baseline_per_million: f64,
+    /// How many times more often the manuscript uses the word.
+    pub ratio: f64,
+    /// Every use, in reading order.
+    pub locations: Vec<WordLocation>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WordLocation {
+    pub chapter_id: String,
+    pub line_number: usize,
+    #[serde(flatten)]
+    pub span: MatchSpan,
+    /// The form used, such as "glanced".
+    pub text: String,
+}
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "the", "and", "or", "but", "nor", "so", "yet", "for", "of", "in", "on", "at", "to",
+    "from", "by", "with", "without", "within", "into", "onto", "upon", "about", "above", "below",
+    "over", "under", "after", "before", "between", "through", "during", "against", "among",
+    "around", "across", "along", "behind", "beyond", "beside", "besides", "near", "toward",
+    "towards", "off", "out", "up", "down", "away", "than", "then", "as", "if", "because", "while",
+    "though", "although", "unless", "until", "since", "whether", "where", "when", "why", "how",
+    "what", "which", "who", "whom", "whose", "that", "this", "these", "those", "there", "here", "i",
+    "me", "my", "mine", "myself", "you", "your", "yours", "yourself", "yourselves", "he", "him",
+    "his", "himself", "she", "her", "hers", "herself", "it", "its", "itself", "we", "us", "our",
+    "ours", "ourselves", "they", "them", "their", "theirs", "themselves", "be", "am", "is", "are",
+    "was", "were", "been", "being", "have", "has", "had", "having", "do", "does", "did", "done",
+    "doing", "will", "would", "shall", "should", "can", "could", "may", "might", "must", "not",
+    "no", "yes", "all", "any", "both", "each", "every", "either", "neither", "few", "many", "more",
+    "most", "much", "other", "others", "some", "such", "same", "own", "only", "just", "too",
+    "also", "again", "ever", "never", "always", "still", "even", "once", "now", "well", "one",
+    "ones", "two", "three", "nothing", "something", "anything", "everything", "nobody", "somebody",
+    "anybody", "everybody", "someone", "anyone", "everyone", "none", "oh", "ok", "okay", "yeah",
+    // Dialogue tags readers skip over.
+    "said",
+];
+
+fn stop_words() -> &'static HashSet<&'static str> {
+    static STOP: OnceLock<HashSet<&str>> = OnceLock::new();
+    STOP.get_or_init(|| STOP_WORDS.iter().copied().collect())
+}
+
+/// Whether a [normalized](normalize) word is worth counting: not a stop
+/// word, a contraction like "didn't", or a number.
+pub(super) fn is_content_word(word: &str) -> bool {
+    if word.chars().count() < 3 || !word.chars().any(char::is_alphabetic) {
+        return false;
+    }
+    let base = match word.split_once('\'') {
+        // Possessive, unless it's "it's" or "that's".
+        Some((base, "s" | "")) => base,
+        Some(_) => return false,
+        None => word,
+    };
+    !stop_words().contains(base)
+}
+
+const IRREGULAR: &[(&str, &str)] = &[
+    ("went", "go"), ("gone", "go"), ("goes", "go"), ("saw", "see"), ("seen", "see"),
+    ("took", "take"), ("taken", "take"), ("came", "come"), ("ran", "run"), ("thought", "think"),
+    ("knew", "know"), ("known", "know"), ("felt", "feel"), ("says", "say"), ("made", "make"),
+    ("got", "get"), ("gotten", "get"), ("gave", "give"), ("given", "give"), ("told", "tell"),
+    ("found", "find"), ("held", "hold"), ("stood", "stand"), ("sat", "sit"),
+    ("began", "begin"), ("begun", "begin"), ("brought", "bring"), ("kept", "keep"),
+    ("spoke", "speak"), ("spoken", "speak"), ("wrote", "write"), ("written", "write"),
+    ("fell", "fall"), ("fallen", "fall"), ("grew", "grow"), ("grown", "grow"), ("drew", "draw"),
+    ("drawn", "draw"), ("men", "man"), ("women", "woman"), ("children", "child"),
+    ("feet", "foot"), ("teeth", "tooth"), ("mice", "mouse"), ("lay", "lie"), ("lain", "lie"),
+    ("lying", "lie"), ("lies", "lie"), ("died", "die"), ("dying", "die"), ("tied", "tie"),
+    ("tying", "tie"), ("heard", "hear"), ("met", "meet"), ("led", "lead"), ("lost", "lose"),
+    ("paid", "pay"), ("sent", "send"), ("built", "build"), ("caught", "catch"),
+    ("taught", "teach"), ("bought", "buy"), ("fought", "fight"), ("wore", "wear"),
+    ("worn", "wear"), ("threw", "throw"), ("thrown", "throw"), ("broke", "break"),
+    ("broken", "break"), ("chose", "choose"), ("chosen", "choose"), ("drove", "drive"),
+    ("driven", "drive"), ("ate", "eat"), ("eaten", "eat"),
+    ("swam", "swim"), ("sang", "sing"), ("sung", "sing"), ("drank", "drink"), ("drunk", "drink"),
+    ("hid", "hide"), ("hidden", "hide"), ("slept", "sleep"), ("wept", "weep"),
+    ("swept", "sweep"), ("meant", "mean"), ("understood", "understand"), ("struck", "strike"),
+    ("stuck", "stick"), ("shook", "shake"), ("shaken", "shake"), ("woke", "wake"),
+    ("woken", "wake"), ("flew", "fly"), ("flown", "fly"), ("froze", "freeze"),
+    ("frozen", "freeze"), ("forgot", "forget"), ("forgotten", "forget"), ("bitten", "bite"),
+    ("blew", "blow"), ("blown", "blow"), ("knelt", "kneel"),
+    ("leapt", "leap"), ("lit", "light"), ("slid", "slide"), ("spun", "spin"), ("tore", "tear"),
+    ("torn", "tear"), ("won", "win"), ("sank", "sink"), ("sunk", "sink"), ("rang", "ring"),
+    ("rung", "ring"), ("shot", "shoot"), ("sold", "sell"), ("spent", "spend"), ("stole", "steal"),
+    ("stolen", "steal"), ("swore", "swear"), ("sworn", "swear"), ("hung", "hang"),
+    ("fed", "feed"), ("fled", "flee"), ("bled", "bleed"), ("dug", "dig"), ("clung", "cling"),
+    ("crept", "creep"),
+];
+
+fn irregular() -> &'static HashMap<&'static str, &'static str> {
+    static IRREGULAR_MAP: OnceLock<HashMap<&str, &str>> = OnceLock::new();
+    IRREGULAR_MAP.get_or_init(|| IRREGULAR.iter().copied().collect())
+}
+
+/// Words ending in -ing that aren't a verb form.
+const NOT_GERUNDS: &[&str] = &[
+    "morning", "evening", "ceiling", "wedding", "pudding", "darling", "sibling", "lightning",
+    "awning", "herring", "farthing", "shilling",
+];
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+/// Undo the spelling changes made when adding -ed or -ing: "stopp" to
+/// "stop", "smil" to "smile", "glanc" to "glance".
+fn restore(stem: &str) -> String {
+    let chars: Vec<char> = stem.chars().collect();
+    let n = chars.len();
+    let last = chars[n - 1];
+    if n >= 2 && last == chars[n - 2] && !is_vowel(last) && !"lsfz".contains(last) {
+        return chars[..n - 1].iter().collect();
+    }
+    const TAKE_E: &[&str] = &["v", "nc", "rc", "dg", "rg", "tl", "bl", "pl", "dl", "gl", "kl", "zl", "uir"];
+    if TAKE_E.iter().any(|end| stem.ends_with(end)) {
+        return format!("{}e", stem);
+    }
+    // One syllable ending consonant, vowel, consonant: "hop" for "hoping".
+    let syllables = (0..n).filter(|&i| is_vowel(chars[i]) && (i == 0 || !is_vowel(chars[i - 1]))).count();
+    let cvc = n >= 3 && !is_vowel(chars[n - 3]) && is_vowel(chars[n - 2]) && !"aeiouwxy".contains(last);
+    if cvc && syllables == 1 {
+        return format!("{}e", stem);
+    }
+    stem.to_string()
+}
+
+/// The dictionary form of a [normalized](normalize) word, by a handful of
+/// English suffix rules and a list of irregular forms. Not a dictionary
+/// lemmatizer, but it groups "glass" with "glasses" and "ran" with "run".
+pub(super) fn lemma(word: &str) -> String {
+    let word = word
+        .strip_suffix("'s")
+        .or_else(|| word.strip_suffix('\''))
+        .unwrap_or(word);
+    if let Some(base) = irregular().get(word) {
+        return base.to_string();
+    }
+    let len = word.chars().count();
+    if len > 4 {
+        if let Some(stem) = word.strip_suffix("ies").or_else(|| word.strip_suffix("ied")) {
+            return format!("{}y", stem);
+        }
+    }
+    if ["sses", "ches", "shes", "xes", "zzes"].iter().any(|end| word.ends_with(end)) {
+        return word[..word.len() - 2].to_string();
+    }
+    if let Some(stem) = word.strip_suffix('s') {
+        let plural = len > 3 && !["ss", "us", "is"].iter().any(|end| word.ends_with(end));
+        return if plural { stem } else { word }.to_string();
+    }
+    if NOT_PARTICIPLES.contains(&word) || NOT_GERUNDS.contains(&word) {
+        return word.to_string();
+    }
+    if word.ends_with("eed") || word.ends_with("ued") {
+        return word[..word.len() - 1].to_string();
+    }
+    let stem = word
+        .strip_suffix("ed")
+        .filter(|_| len > 4)
+        .or_else(|| word.strip_suffix("ing").filter(|_| len > 5));
+    match stem {
+        Some(stem) if stem.chars().any(|c| is_vowel(c) || c == 'y') => restore(stem),
+        _ => word.to_string(),
+    }
+}
+
+/// Uses per million words of general English fiction, by lemma, from the
+/// bundled `baseline.txt`, and the lowest rate listed.
+fn baseline() -> &'static (HashMap<&'static str, f64>, f64) {
+    static BASELINE: OnceLock<(HashMap<&str, f64>, f64)> = OnceLock::new();
+    BASELINE.get_or_init(|| {
+        let rates: HashMap<&str, f64> = include_str!("baseline.txt")
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (word, rate) = line.split_once(char::is_whitespace)?;
+                Some((word, rate.trim().parse().ok()?))
+            })
+            .collect();
+        let floor = rates.values().copied().fold(f64::INFINITY, f64::min);
+        (rates, floor)
+    })
+}
+
+/// The words the manuscript uses most out of proportion to the baseline,
+/// most overused first, at most `limit` of them. Words missing from the
+/// baseline are taken to be as rare as its rarest word.
+pub fn overused_words(project: &Project, limit: usize) -> Result<Vec<OverusedWord>, ProjectError> {
+    let mut total = 0;
+    let mut book_names: HashSet<String> = HashSet::new();
+    let mut uses: HashMap<String, Vec<WordLocation>> = HashMap::new();
+    for chapter_id in project.chapter_order() {
+        if !project.chapter_path(&chapter_id).exists() {
+            continue;
+        }
+        let chapter = project.load_chapter(&chapter_id)?;
+        let lines = prose_lines(&chapter.content);
+        book_names.extend(names(&lines).into_iter().map(str::to_string));
+        for line in lines.iter().flatten() {
+            for word in line.words() {
+                total += 1;
+                if !is_content_word(&word.lower) {
+                    continue;
+                }
+                uses.entry(lemma(&word.lower)).or_default().push(WordLocation {
+                    chapter_id: chapter_id.to_string(),
+                    line_number: line.number,
+                    span: MatchSpan::new(line.text, word.start, word.end),
+                    text: word.text.to_string(),
+                });
+            }
+        }
+    }
+
+    let (rates, floor) = baseline();
+    let mut overused: Vec<OverusedWord> = uses
+        .into_iter()
+        .filter_map(|(lemma, mut locations)| {
+            locations.retain(|location| !book_names.contains(&normalize(&location.text)));
+            if locations.len() < MIN_USES {
+                return None;
+            }
+            let per_million = locations.len() as f64 * 1_000_000.0 / total as f64;
+            let baseline_per_million = rates.get(lemma.as_str()).copied().unwrap_or(*floor);
+            Some(OverusedWord {
+                count: locations.len(),
+                per_million,
+                baseline_per_million,
+                ratio: per_million / baseline_per_million,
+                lemma,
+                locations,
+            })
+        })
+        .collect();
+    overused.sort_by(|a, b| {
+        b.ratio
+            .total_cmp(&a.ratio)
+            .then(b.count.cmp(&a.count))
+            .then(a.lemma.cmp(&b.lemma))
+    });
+    overused.truncate(limit);
+    Ok(overused)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::style::Linter;
+    use crate::analysis::{Diagnostic, RuleId, StyleConfig};
+    use crate::manuscript::ids::ChapterId;
+
+    #[test]
+    fn forms_of_a_word_share_a_lemma() {
+        for (word, expected) in [
+            ("glass", "glass"),
+            ("glasses", "glass"),
+            ("ran", "run"),
+            ("run", "run"),
+            ("hoping", "hope"),
+            ("hopping", "hop"),
+            ("hop", "hop"),
+            ("glanced", "glance"),
+            ("glancing", "glance"),
+            ("glance", "glance"),
+            ("smiled", "smile"),
+            ("stopped", "stop"),
+            ("carried", "carry"),
+            ("cries", "cry"),
+            ("agreed", "agree"),
+            ("mara's", "mara"),
+            ("bus", "bus"),
+            ("hundred", "hundred"),
+            ("morning", "morning"),
+        ] {
+            assert_eq!(lemma(word), expected, "lemma of {}", word);
+        }
+    }
+
+    #[test]
+    fn stop_words_contractions_and_numbers_are_not_content() {
+        for word in ["the", "she", "said", "didn't", "it's", "1984", "ox"] {
+            assert!(!is_content_word(word), "{}", word);
+        }
+        for word in ["lantern", "mara's", "glanced"] {
+            assert!(is_content_word(word), "{}", word);
+        }
+    }
+
+    fn echoes(text: &str, window: usize) -> Vec<Diagnostic> {
+        let config = StyleConfig {
+            echo_window: window,
+            ..StyleConfig::default()
+        };
+        Linter::new(&config).lint(text).into_iter().filter(|d| d.rule == RuleId::Echo).collect()
+    }
+
+    #[test]
+    fn echoes_are_flagged_up_to_the_window() {
+        // "lantern" is the 2nd and 7th word, 5 apart.
+        let text = "The lantern hung low, then the lantern fell.";
+        assert_eq!(echoes(text, 5).len(), 1);
+        assert!(echoes(text, 4).is_empty());
+        // Other forms of the word count too.
+        assert_eq!(echoes("She glanced back, glancing twice.", 40)[0].text, "glancing");
+    }
+
+    #[test]
+    fn an_echo_points_at_the_earlier_use() {
+        let found = echoes("Mara lit the lantern.\nThe lanterns swung.", 40);
+        assert_eq!(found.len(), 1);
+        let echo = &found[0];
+        assert_eq!((echo.line_number, echo.text.as_str()), (2, "lanterns"));
+        assert_eq!(echo.message, "\u{201c}lanterns\u{201d} echoes \u{201c}lantern\u{201d} 2 words earlier");
+        let related = echo.related.as_ref().unwrap();
+        assert_eq!((related.line_number, related.span.start, related.span.end), (1, 13, 20));
+    }
+
+    #[test]
+    fn breaks_names_and_allowed_lemmas_stop_echoes() {
+        assert!(echoes("The lantern.\n\nThe lantern.", 40).is_empty());
+        assert!(echoes("Then Hope smiled. Hope left.", 40).is_empty());
+        let config = StyleConfig {
+            allow: [(RuleId::Echo, vec!["glance".to_string()])].into(),
+            ..StyleConfig::default()
+        };
+        assert!(Linter::new(&config).lint("She glanced back, glancing twice.").is_empty());
+    }
+
+    #[test]
+    fn overused_words_skip_stop_words_and_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "Echoes", "Author").unwrap();
+        let chapter = project.add_chapter("One", None).unwrap();
+        let chapter_id = ChapterId::parse(&chapter.id).unwrap();
+        let lines = [
+            "Then Mara glanced at the lantern.",
+            "Then Mara was glancing at the lantern.",
+            "Then Mara glances at the lantern.",
+            "Then Mara will glance at the lantern.",
+            "Then Mara glanced at the lantern again.",
+            "Then Mara glanced at the door.",
+        ];
+        project.update_chapter_content(&chapter_id, &lines.join("\n")).unwrap();
+
+        let overused = overused_words(&project, 10).unwrap();
+        let lemmas: Vec<&str> = overused.iter().map(|w| w.lemma.as_str()).collect();
+        assert!(lemmas.contains(&"glance") && lemmas.contains(&"lantern"), "{:?}", lemmas);
+        assert!(!lemmas.iter().any(|l| ["mara", "then", "the", "at"].contains(l)), "{:?}", lemmas);
+
+        let glance = overused.iter().find(|w| w.lemma == "glance").unwrap();
+        assert_eq!(glance.count, 6);
+        let texts: Vec<&str> = glance.locations.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, ["glanced", "glancing", "glances", "glance", "glanced", "glanced"]);
+        assert_eq!(glance.locations[1].line_number, 2);
+        assert_eq!(glance.locations[1].chapter_id, chapter_id.to_string());
+
+        assert_eq!(overused_words(&project, 1).unwrap().len(), 1);
+    }
+}
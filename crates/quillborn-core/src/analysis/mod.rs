@@ -0,0 +1,295 @@
+//! Analysis of the manuscript's prose, reported as diagnostics with a rule,
+//! a severity and the range of text they concern, plus a book-wide report
+//! of [overused words](overused_words).
+//!
+//! Results are cached per chapter in `index/style/`, next to the search
+//! index, and only recomputed when the chapter file or the project's
+//! [`StyleConfig`] changed. Saving a chapter through
+//! `Project::update_chapter_content` relints it straight away. Read-only
+//! projects are linted without touching the cache.
+
+mod echoes;
+mod style;
+
+pub use echoes::{overused_words, OverusedWord, WordLocation};
+pub use style::{RuleId, StyleConfig};
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::manuscript::chapter::Chapter;
+use crate::manuscript::error::WithPath;
+use crate::manuscript::ids::ChapterId;
+use crate::manuscript::project::{Project, ProjectError};
+use crate::search::{stamp, MatchSpan, Stamp};
+use style::Linter;
+
+/// Name of the Tauri event emitted with a [`ChapterDiagnostics`] payload
+/// after a chapter is saved.
+pub const DIAGNOSTICS_EVENT: &str = "style-diagnostics";
+
+/// Bump when the rules change, so cached results are recomputed.
+const CACHE_VERSION: u32 = 2;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Diagnostic {
+    pub rule: RuleId,
+    pub severity: Severity,
+    pub line_number: usize,
+    #[serde(flatten)]
+    pub span: MatchSpan,
+    /// The text flagged.
+    pub text: String,
+    pub message: String,
+    /// Another range the diagnostic points at, such as the earlier word an
+    /// echo repeats.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub related: Option<Location>,
+}
+
+/// A range of text on one line of a chapter.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Location {
+    pub line_number: usize,
+    #[serde(flatten)]
+    pub span: MatchSpan,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ChapterDiagnostics {
+    pub chapter_id: String,
+    pub chapter_title: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A chapter's diagnostics as of the chapter file with `stamp`, linted
+/// with `config`.
+#[derive(Serialize, Deserialize)]
+struct Cached {
+    version: u32,
+    stamp: Stamp,
+    config: StyleConfig,
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn cache_dir(project: &Project) -> PathBuf {
+    project.path.join("index").join("style")
+}
+
+fn cache_path(project: &Project, chapter_id: &ChapterId) -> PathBuf {
+    cache_dir(project).join(format!("{}.json", chapter_id))
+}
+
+fn store(project: &Project, chapter_id: &ChapterId, cached: &Cached) -> Result<(), ProjectError> {
+    let dir = cache_dir(project);
+    fs::create_dir_all(&dir).with_path(&dir)?;
+    let path = cache_path(project, chapter_id);
+    fs::write(&path, serde_json::to_vec(cached)?).with_path(&path)?;
+    Ok(())
+}
+
+/// Diagnostics for one chapter, from the cache if still current. `None`
+/// when the chapter has no file.
+fn chapter_diagnostics(
+    project: &Project,
+    config: &StyleConfig,
+    linter: &Linter,
+    chapter_id: &ChapterId,
+) -> Result<Option<Vec<Diagnostic>>, ProjectError> {
+    let Some(stamp) = stamp(&project.chapter_path(chapter_id)) else {
+        return Ok(None);
+    };
+    let cached = fs::read(cache_path(project, chapter_id))
+        .ok()
+        .and_then(|raw| serde_json::from_slice::<Cached>(&raw).ok())
+        .filter(|cached| cached.version == CACHE_VERSION && cached.stamp == stamp && cached.config == *config);
+    if let Some(cached) = cached {
+        return Ok(Some(cached.diagnostics));
+    }
+
+    let chapter = project.load_chapter(chapter_id)?;
+    let cached = Cached {
+        version: CACHE_VERSION,
+        stamp,
+        config: config.clone(),
+        diagnostics: linter.lint(&chapter.content),
+    };
+    if !project.read_only {
+        store(project, chapter_id, &cached)?;
+    }
+    Ok(Some(cached.diagnostics))
+}
+
+fn title_of(project: &Project, chapter_id: &ChapterId) -> String {
+    project
+        .structure
+        .nodes
+        .get(chapter_id.as_str())
+        .map(|node| node.title.clone())
+        .unwrap_or_default()
+}
+
+/// Style diagnostics for every chapter, in reading order. Chapters with
+/// nothing to report are included with an empty list.
+pub fn lint_manuscript(project: &Project) -> Result<Vec<ChapterDiagnostics>, ProjectError> {
+    let config = StyleConfig::load(project)?;
+    let linter = Linter::new(&config);
+    let order = project.chapter_order();
+    let mut results = Vec::new();
+    for chapter_id in &order {
+        if let Some(diagnostics) = chapter_diagnostics(project, &config, &linter, chapter_id)? {
+            results.push(ChapterDiagnostics {
+                chapter_id: chapter_id.to_string(),
+                chapter_title: title_of(project, chapter_id),
+                diagnostics,
+            });
+        }
+    }
+
+    if project.read_only {
+        return Ok(results);
+    }
+    // Forget chapters that have since been deleted.
+    let live: HashSet<String> = order.iter().map(|id| format!("{}.json", id)).collect();
+    if let Ok(entries) = fs::read_dir(cache_dir(project)) {
+        for entry in entries.flatten() {
+            if !entry.file_name().to_str().is_some_and(|name| live.contains(name)) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+    Ok(results)
+}
+
+pub fn lint_chapter(project: &Project, chapter_id: &ChapterId) -> Result<ChapterDiagnostics, ProjectError> {
+    let config = StyleConfig::load(project)?;
+    let diagnostics = chapter_diagnostics(project, &config, &Linter::new(&config), chapter_id)?
+        .ok_or_else(|| ProjectError::ChapterNotFound(chapter_id.to_string()))?;
+    Ok(ChapterDiagnostics {
+        chapter_id: chapter_id.to_string(),
+        chapter_title: title_of(project, chapter_id),
+        diagnostics,
+    })
+}
+
+/// Relint a chapter straight after it was saved. A broken `style.json`
+/// is reported when diagnostics are asked for, not here, so it never
+/// stands in the way of saving.
+pub(crate) fn update_chapter(project: &Project, chapter: &Chapter) -> Result<(), ProjectError> {
+    let Ok(config) = StyleConfig::load(project) else {
+        return Ok(());
+    };
+    let Ok(chapter_id) = ChapterId::parse(&chapter.id) else {
+        return Ok(());
+    };
+    let Some(stamp) = stamp(&project.chapter_path(&chapter_id)) else {
+        return Ok(());
+    };
+    let diagnostics = Linter::new(&config).lint(&chapter.content);
+    store(
+        project,
+        &chapter_id,
+        &Cached {
+            version: CACHE_VERSION,
+            stamp,
+            config,
+            diagnostics,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project_with(text: &str) -> (tempfile::TempDir, Project, ChapterId) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "Style", "Author").unwrap();
+        let chapter = project.add_chapter("One", None).unwrap();
+        let chapter_id = ChapterId::parse(&chapter.id).unwrap();
+        project.update_chapter_content(&chapter_id, text).unwrap();
+        (dir, project, chapter_id)
+    }
+
+    /// Rewrite the cached message of every diagnostic, leaving the stamp
+    /// and config alone, so a later lint shows whether the cache was used.
+    fn mark_cache(project: &Project, chapter_id: &ChapterId) {
+        let raw = fs::read(cache_path(project, chapter_id)).unwrap();
+        let mut cached: Cached = serde_json::from_slice(&raw).unwrap();
+        for diagnostic in &mut cached.diagnostics {
+            diagnostic.message = "from the cache".to_string();
+        }
+        store(project, chapter_id, &cached).unwrap();
+    }
+
+    fn messages(project: &Project, chapter_id: &ChapterId) -> Vec<String> {
+        lint_chapter(project, chapter_id).unwrap().diagnostics.into_iter().map(|d| d.message).collect()
+    }
+
+    #[test]
+    fn the_cache_is_reused_while_chapter_and_config_are_unchanged() {
+        let (_dir, project, chapter_id) = project_with("She ran quickly.");
+        mark_cache(&project, &chapter_id);
+        assert_eq!(messages(&project, &chapter_id), ["from the cache"]);
+        assert_eq!(lint_manuscript(&project).unwrap()[0].diagnostics[0].message, "from the cache");
+    }
+
+    #[test]
+    fn a_new_config_relints() {
+        let (_dir, project, chapter_id) = project_with("She ran quickly.");
+        mark_cache(&project, &chapter_id);
+        let config = StyleConfig {
+            severity: [(RuleId::Adverb, Severity::Warning)].into(),
+            ..StyleConfig::default()
+        };
+        config.save(&project).unwrap();
+
+        let diagnostics = lint_chapter(&project, &chapter_id).unwrap().diagnostics;
+        assert_eq!(diagnostics.len(), 1);
+        assert_ne!(diagnostics[0].message, "from the cache");
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn a_changed_chapter_file_relints() {
+        let (_dir, project, chapter_id) = project_with("She ran quickly.");
+        mark_cache(&project, &chapter_id);
+        // Edited outside the app, so the cache isn't refreshed on save.
+        let path = project.chapter_path(&chapter_id);
+        let raw = fs::read_to_string(&path).unwrap();
+        fs::write(&path, raw.replace("ran quickly", "ran very quickly")).unwrap();
+
+        let texts: Vec<String> =
+            lint_chapter(&project, &chapter_id).unwrap().diagnostics.into_iter().map(|d| d.text).collect();
+        assert_eq!(texts, ["very", "quickly"]);
+    }
+
+    #[test]
+    fn caches_of_deleted_chapters_are_dropped() {
+        let (_dir, mut project, chapter_id) = project_with("She ran quickly.");
+        assert!(cache_path(&project, &chapter_id).exists());
+        project.delete_chapter(&chapter_id).unwrap();
+        lint_manuscript(&project).unwrap();
+        assert!(!cache_path(&project, &chapter_id).exists());
+    }
+}
@@ -0,0 +1,656 @@
+//! Prose style rules and their per-project configuration, kept in
+//! `style.json`.
+//!
+//! Text is checked sentence by sentence within each line, so nothing is
+//! flagged across a paragraph break. Words are compared in lower case.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{echoes, Diagnostic, Location, Severity};
+use crate::manuscript::error::{read_json, WithPath};
+use crate::manuscript::project::{Project, ProjectError};
+use crate::search::MatchSpan;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleId {
+    /// Words ending in -ly that modify a verb or adjective.
+    Adverb,
+    /// "She saw", "he felt": perception reported rather than shown.
+    FilterWord,
+    /// A form of "to be" followed by a past participle.
+    PassiveVoice,
+    /// Hedges and intensifiers such as "very", "quite" or "sort of".
+    WeaselWord,
+    /// Several sentences in a row starting with the same word.
+    RepeatedOpener,
+    LongSentence,
+    /// Phrases from the bundled `cliches.txt`.
+    Cliche,
+    /// A word, or another form of it, used again within a few words.
+    Echo,
+}
+
+impl RuleId {
+    pub const ALL: [RuleId; 8] = [
+        RuleId::Adverb,
+        RuleId::FilterWord,
+        RuleId::PassiveVoice,
+        RuleId::WeaselWord,
+        RuleId::RepeatedOpener,
+        RuleId::LongSentence,
+        RuleId::Cliche,
+        RuleId::Echo,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleId::Adverb => "adverb",
+            RuleId::FilterWord => "filter_word",
+            RuleId::PassiveVoice => "passive_voice",
+            RuleId::WeaselWord => "weasel_word",
+            RuleId::RepeatedOpener => "repeated_opener",
+            RuleId::LongSentence => "long_sentence",
+            RuleId::Cliche => "cliche",
+            RuleId::Echo => "echo",
+        }
+    }
+
+    /// Severity unless the project sets another.
+    fn default_severity(&self) -> Severity {
+        match self {
+            RuleId::RepeatedOpener | RuleId::LongSentence | RuleId::Cliche => Severity::Warning,
+            _ => Severity::Info,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StyleConfig {
+    /// Rules switched off for this project.
+    pub disabled: BTreeSet<RuleId>,
+    /// Text each rule should let through, such as `"Lily"` for `adverb` or
+    /// `"I"` for `repeated_opener`. Compared ignoring case.
+    pub allow: BTreeMap<RuleId, Vec<String>>,
+    pub severity: BTreeMap<RuleId, Severity>,
+    /// Sentences with more words than this are flagged as long.
+    pub max_sentence_words: usize,
+    /// How many sentences in a row may start with the same word.
+    pub max_repeated_openers: usize,
+    /// How many words apart two uses of a word count as an echo.
+    pub echo_window: usize,
+}
+
+impl Default for StyleConfig {
+    fn default() -> Self {
+        StyleConfig {
+            disabled: BTreeSet::new(),
+            allow: BTreeMap::new(),
+            severity: BTreeMap::new(),
+            max_sentence_words: 40,
+            max_repeated_openers: 2,
+            echo_window: 40,
+        }
+    }
+}
+
+fn config_path(project: &Project) -> PathBuf {
+    project.path.join("style.json")
+}
+
+fn invalid(field: &str, message: &str) -> ProjectError {
+    ProjectError::InvalidSettings {
+        field: format!("style.{}", field),
+        message: message.to_string(),
+    }
+}
+
+impl StyleConfig {
+    /// The project's configuration, or the defaults if it has none.
+    pub fn load(project: &Project) -> Result<Self, ProjectError> {
+        let path = config_path(project);
+        if !path.exists() {
+            return Ok(StyleConfig::default());
+        }
+        let config: StyleConfig = read_json(&path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn save(&self, project: &Project) -> Result<(), ProjectError> {
+        self.validate()?;
+        let path = config_path(project);
+        fs::write(&path, serde_json::to_string_pretty(self)?).with_path(&path)?;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), ProjectError> {
+        if !(5..=500).contains(&self.max_sentence_words) {
+            return Err(invalid("max_sentence_words", "must be between 5 and 500"));
+        }
+        if !(1..=20).contains(&self.max_repeated_openers) {
+            return Err(invalid("max_repeated_openers", "must be between 1 and 20"));
+        }
+        if !(2..=1000).contains(&self.echo_window) {
+            return Err(invalid("echo_window", "must be between 2 and 1000"));
+        }
+        Ok(())
+    }
+
+    pub fn severity_of(&self, rule: RuleId) -> Severity {
+        self.severity.get(&rule).copied().unwrap_or_else(|| rule.default_severity())
+    }
+}
+
+/// Lower case with curly apostrophes straightened and runs of whitespace
+/// collapsed, so text compares the same however it was typed.
+pub(super) fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('\u{2019}', "'")
+        .to_lowercase()
+}
+
+/// A word of a line, with its byte range in the line.
+pub(super) struct Word<'l> {
+    pub start: usize,
+    pub end: usize,
+    pub text: &'l str,
+    /// The word [normalized](normalize).
+    pub lower: String,
+}
+
+/// A line of prose and the words of each of its sentences.
+pub(super) struct ProseLine<'t> {
+    pub number: usize,
+    pub text: &'t str,
+    pub sentences: Vec<Vec<Word<'t>>>,
+}
+
+impl<'t> ProseLine<'t> {
+    pub fn words(&self) -> impl Iterator<Item = &Word<'t>> {
+        self.sentences.iter().flatten()
+    }
+}
+
+/// The lines of `text`, with `None` for headings and scene breaks, which
+/// aren't prose and end a run of openers or echoes.
+pub(super) fn prose_lines(text: &str) -> Vec<Option<ProseLine<'_>>> {
+    text.split('\n')
+        .enumerate()
+        .map(|(index, raw)| {
+            let line = raw.strip_suffix('\r').unwrap_or(raw);
+            if line.trim_start().starts_with('#') || !line.contains(char::is_alphanumeric) {
+                return None;
+            }
+            let sentences = line
+                .split_sentence_bound_indices()
+                .map(|(offset, sentence)| {
+                    sentence
+                        .unicode_word_indices()
+                        .map(|(start, text)| Word {
+                            start: offset + start,
+                            end: offset + start + text.len(),
+                            text,
+                            lower: normalize(text),
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .filter(|words| !words.is_empty())
+                .collect();
+            Some(ProseLine { number: index + 1, text: line, sentences })
+        })
+        .collect()
+}
+
+/// Words capitalised mid-sentence anywhere in `lines`. They are names,
+/// like Emily, even where they start a sentence.
+pub(super) fn names<'a>(lines: &'a [Option<ProseLine>]) -> HashSet<&'a str> {
+    lines
+        .iter()
+        .flatten()
+        .flat_map(|line| &line.sentences)
+        .flat_map(|words| words.iter().skip(1))
+        .filter(|word| word.text.starts_with(char::is_uppercase))
+        .map(|word| word.lower.as_str())
+        .collect()
+}
+
+/// Multi-word phrases, by first word, longest first.
+struct Phrases(HashMap<String, Vec<Vec<String>>>);
+
+impl Phrases {
+    fn new<'a>(phrases: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut by_first: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+        for phrase in phrases {
+            let words: Vec<String> = phrase.unicode_words().map(normalize).collect();
+            if let Some(first) = words.first() {
+                by_first.entry(first.clone()).or_default().push(words);
+            }
+        }
+        for candidates in by_first.values_mut() {
+            candidates.sort_by_key(|words| std::cmp::Reverse(words.len()));
+        }
+        Phrases(by_first)
+    }
+
+    /// Length in words of the longest phrase starting at `words[0]`.
+    fn longest_at(&self, words: &[Word]) -> Option<usize> {
+        self.0.get(&words.first()?.lower)?.iter().find_map(|phrase| {
+            let matches = phrase.len() <= words.len() && phrase.iter().zip(words).all(|(p, w)| *p == w.lower);
+            matches.then_some(phrase.len())
+        })
+    }
+}
+
+fn cliches() -> &'static Phrases {
+    static CLICHES: OnceLock<Phrases> = OnceLock::new();
+    CLICHES.get_or_init(|| {
+        Phrases::new(
+            include_str!("cliches.txt")
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#')),
+        )
+    })
+}
+
+fn weasel_words() -> &'static Phrases {
+    static WEASELS: OnceLock<Phrases> = OnceLock::new();
+    WEASELS.get_or_init(|| Phrases::new(WEASEL_WORDS.iter().copied()))
+}
+
+const WEASEL_WORDS: &[&str] = &[
+    "very", "really", "quite", "rather", "somewhat", "fairly", "extremely", "just", "basically",
+    "actually", "literally", "totally", "completely", "simply", "various", "several", "numerous",
+    "arguably", "seemingly", "relatively", "probably", "perhaps", "a bit", "a little", "sort of",
+    "kind of", "a lot",
+];
+
+/// Words ending in -ly that aren't adverbs, or are too plain to flag.
+const NOT_ADVERBS: &[&str] = &[
+    "only", "family", "early", "daily", "weekly", "monthly", "yearly", "hourly", "nightly", "holy",
+    "ugly", "belly", "bully", "jelly", "lily", "holly", "rally", "reply", "supply", "apply", "comply",
+    "rely", "imply", "multiply", "ally", "july", "italy", "assembly", "anomaly", "monopoly", "homily",
+    "melancholy", "butterfly", "dragonfly", "firefly", "friendly", "lonely", "lovely", "lively",
+    "likely", "unlikely", "lowly", "costly", "deadly", "elderly", "ghastly", "ghostly", "heavenly",
+    "homely", "leisurely", "manly", "motherly", "fatherly", "brotherly", "sisterly", "orderly",
+    "scholarly", "sickly", "silly", "surly", "timely", "wobbly", "woolly", "wily", "worldly",
+    "cowardly", "curly", "chilly", "hilly", "jolly", "smelly", "shapely", "stately", "portly",
+    "burly", "beastly", "courtly", "comely", "gangly", "grisly", "measly", "prickly", "saintly",
+    "seemly", "unseemly", "sprightly", "earthly", "unearthly", "bubbly", "cuddly", "bristly",
+];
+
+const SUBJECTS: &[&str] = &["i", "he", "she", "they", "we", "you"];
+
+/// Words allowed between a subject and a filter verb, as in "she could see".
+const MODALS: &[&str] = &["could", "can", "would", "did"];
+
+const FILTER_VERBS: &[&str] = &[
+    "saw", "see", "sees", "heard", "hear", "hears", "felt", "feel", "feels", "noticed", "notice",
+    "notices", "watched", "watch", "watches", "realized", "realised", "realize", "realise",
+    "wondered", "wonder", "thought", "think", "knew", "know", "smelled", "smelt", "smell", "tasted",
+    "taste", "seemed", "seem", "decided", "observed", "sensed",
+];
+
+const BE: &[&str] = &["am", "is", "are", "was", "were", "be", "been", "being"];
+
+const IRREGULAR_PARTICIPLES: &[&str] = &[
+    "beaten", "bitten", "blown", "borne", "bound", "brought", "built", "bought", "caught", "chosen",
+    "done", "drawn", "driven", "eaten", "fallen", "fed", "felt", "forbidden", "forgiven",
+    "forgotten", "fought", "found", "frozen", "given", "grown", "heard", "held", "hidden", "hung",
+    "hurt", "kept", "known", "laid", "led", "left", "lit", "lost", "made", "meant", "met", "paid",
+    "ridden", "said", "seen", "sent", "set", "shaken", "shot", "shown", "shut", "slain", "sold",
+    "spent", "spoken", "stolen", "struck", "stuck", "sung", "sunk", "sworn", "swept", "taken",
+    "taught", "thrown", "told", "torn", "understood", "woken", "won", "worn", "woven", "written",
+];
+
+/// Words ending in -ed that aren't past participles.
+pub(super) const NOT_PARTICIPLES: &[&str] = &[
+    "need", "bed", "red", "shed", "seed", "feed", "speed", "bleed", "breed", "greed", "deed", "weed",
+    "reed", "indeed", "hundred", "sacred", "naked", "wicked", "rugged", "ragged", "jagged",
+    "crooked", "kindred", "wretched", "sled", "wed",
+];
+
+fn is_participle(word: &str) -> bool {
+    IRREGULAR_PARTICIPLES.contains(&word)
+        || (word.ends_with("ed") && word.chars().count() > 3 && !NOT_PARTICIPLES.contains(&word))
+}
+
+fn is_adverb(word: &str) -> bool {
+    word.ends_with("ly") && word.chars().count() > 4 && !NOT_ADVERBS.contains(&word)
+}
+
+/// Runs the enabled rules over text.
+pub(super) struct Linter<'c> {
+    config: &'c StyleConfig,
+    allow: HashMap<RuleId, HashSet<String>>,
+}
+
+impl<'c> Linter<'c> {
+    pub fn new(config: &'c StyleConfig) -> Self {
+        let allow = config
+            .allow
+            .iter()
+            .map(|(rule, texts)| (*rule, texts.iter().map(|text| normalize(text)).collect()))
+            .collect();
+        Linter { config, allow }
+    }
+
+    fn on(&self, rule: RuleId) -> bool {
+        !self.config.disabled.contains(&rule)
+    }
+
+    fn flag<'f>(
+        &self,
+        found: &'f mut Vec<Diagnostic>,
+        at: (usize, &str),
+        rule: RuleId,
+        range: (usize, usize),
+        message: String,
+    ) -> Option<&'f mut Diagnostic> {
+        let (line_number, line) = at;
+        let text = &line[range.0..range.1];
+        if self.allowed(rule, &normalize(text)) {
+            return None;
+        }
+        found.push(Diagnostic {
+            rule,
+            severity: self.config.severity_of(rule),
+            line_number,
+            span: MatchSpan::new(line, range.0, range.1),
+            text: text.to_string(),
+            message,
+            related: None,
+        });
+        found.last_mut()
+    }
+
+    fn allowed(&self, rule: RuleId, text: &str) -> bool {
+        self.allow.get(&rule).is_some_and(|allowed| allowed.contains(text))
+    }
+
+    /// Every diagnostic for `text`, in order.
+    pub fn lint(&self, text: &str) -> Vec<Diagnostic> {
+        let lines = prose_lines(text);
+        let names = names(&lines);
+        let mut found = Vec::new();
+        // The word the last sentences started with, and how many did.
+        let mut opener: Option<(String, usize)> = None;
+        for line in &lines {
+            let Some(line) = line else {
+                opener = None;
+                continue;
+            };
+            for words in &line.sentences {
+                self.sentence(&mut found, (line.number, line.text), words, &names, &mut opener);
+            }
+        }
+        if self.on(RuleId::Echo) {
+            self.echoes(&mut found, &lines, &names);
+        }
+        found.sort_by_key(|d| (d.line_number, d.span.start));
+        found
+    }
+
+    fn sentence(
+        &self,
+        found: &mut Vec<Diagnostic>,
+        at: (usize, &str),
+        words: &[Word],
+        names: &HashSet<&str>,
+        opener: &mut Option<(String, usize)>,
+    ) {
+        if self.on(RuleId::Adverb) {
+            for word in words {
+                if is_adverb(&word.lower) && !names.contains(word.lower.as_str()) {
+                    let message =
+                        format!("\u{201c}{}\u{201d} is an adverb; a stronger verb may not need it", word.text);
+                    self.flag(found, at, RuleId::Adverb, (word.start, word.end), message);
+                }
+            }
+        }
+
+        if self.on(RuleId::FilterWord) {
+            for (i, word) in words.iter().enumerate() {
+                if !SUBJECTS.contains(&word.lower.as_str()) {
+                    continue;
+                }
+                let mut verb = i + 1;
+                if words.get(verb).is_some_and(|w| MODALS.contains(&w.lower.as_str())) {
+                    verb += 1;
+                }
+                if let Some(verb) = words.get(verb).filter(|w| FILTER_VERBS.contains(&w.lower.as_str())) {
+                    let message = format!(
+                        "\u{201c}{}\u{201d} filters the scene; show what is perceived instead",
+                        &at.1[word.start..verb.end]
+                    );
+                    self.flag(found, at, RuleId::FilterWord, (word.start, verb.end), message);
+                }
+            }
+        }
+
+        if self.on(RuleId::PassiveVoice) {
+            for (i, word) in words.iter().enumerate() {
+                if !BE.contains(&word.lower.as_str()) {
+                    continue;
+                }
+                let mut next = i + 1;
+                if words.get(next).is_some_and(|w| is_adverb(&w.lower)) {
+                    next += 1;
+                }
+                if let Some(participle) = words.get(next).filter(|w| is_participle(&w.lower)) {
+                    let passive = &at.1[word.start..participle.end];
+                    let message = format!("Passive voice: \u{201c}{}\u{201d}", passive);
+                    self.flag(found, at, RuleId::PassiveVoice, (word.start, participle.end), message);
+                }
+            }
+        }
+
+        if self.on(RuleId::WeaselWord) {
+            self.phrases(found, at, words, RuleId::WeaselWord, weasel_words(), |text| {
+                format!("\u{201c}{}\u{201d} hedges or pads; cut it or be specific", text)
+            });
+        }
+
+        if self.on(RuleId::Cliche) {
+            self.phrases(found, at, words, RuleId::Cliche, cliches(), |text| {
+                format!("\u{201c}{}\u{201d} is a clich\u{e9}", text)
+            });
+        }
+
+        let (first, last) = (&words[0], &words[words.len() - 1]);
+        if self.on(RuleId::LongSentence) && words.len() > self.config.max_sentence_words {
+            let message = format!("Sentence of {} words; consider splitting it", words.len());
+            self.flag(found, at, RuleId::LongSentence, (first.start, last.end), message);
+        }
+
+        let streak = match opener.take() {
+            Some((word, count)) if word == first.lower => count + 1,
+            _ => 1,
+        };
+        if self.on(RuleId::RepeatedOpener) && streak > self.config.max_repeated_openers {
+            let message = format!("{} sentences in a row start with \u{201c}{}\u{201d}", streak, first.text);
+            self.flag(found, at, RuleId::RepeatedOpener, (first.start, first.end), message);
+        }
+        *opener = Some((first.lower.clone(), streak));
+    }
+
+    /// Flag each content word whose lemma was used within the last
+    /// `echo_window` words, pointing back at the earlier use. The
+    /// allow-list takes either the word or its lemma.
+    fn echoes(&self, found: &mut Vec<Diagnostic>, lines: &[Option<ProseLine>], names: &HashSet<&str>) {
+        // Where each lemma was last used: word position, line and range.
+        let mut seen: HashMap<String, (usize, &ProseLine, &Word)> = HashMap::new();
+        let mut position = 0;
+        for line in lines {
+            let Some(line) = line else {
+                seen.clear();
+                continue;
+            };
+            for word in line.words() {
+                position += 1;
+                if !echoes::is_content_word(&word.lower) || names.contains(word.lower.as_str()) {
+                    continue;
+                }
+                let lemma = echoes::lemma(&word.lower);
+                if let Some((before, earlier, first)) = seen.get(&lemma) {
+                    let distance = position - before;
+                    if distance <= self.config.echo_window && !self.allowed(RuleId::Echo, &lemma) {
+                        let message = format!(
+                            "\u{201c}{}\u{201d} echoes \u{201c}{}\u{201d} {} word{} earlier",
+                            word.text,
+                            first.text,
+                            distance,
+                            if distance == 1 { "" } else { "s" }
+                        );
+                        let at = (line.number, line.text);
+                        let range = (word.start, word.end);
+                        if let Some(diagnostic) = self.flag(found, at, RuleId::Echo, range, message) {
+                            diagnostic.related = Some(Location {
+                                line_number: earlier.number,
+                                span: MatchSpan::new(earlier.text, first.start, first.end),
+                            });
+                        }
+                    }
+                }
+                seen.insert(lemma, (position, line, word));
+            }
+        }
+    }
+
+    /// Flag each phrase from `phrases` in `words`, without overlaps.
+    fn phrases(
+        &self,
+        found: &mut Vec<Diagnostic>,
+        at: (usize, &str),
+        words: &[Word],
+        rule: RuleId,
+        phrases: &Phrases,
+        message: impl Fn(&str) -> String,
+    ) {
+        let mut i = 0;
+        while i < words.len() {
+            match phrases.longest_at(&words[i..]) {
+                Some(len) => {
+                    let (start, end) = (words[i].start, words[i + len - 1].end);
+                    self.flag(found, at, rule, (start, end), message(&at.1[start..end]));
+                    i += len;
+                }
+                None => i += 1,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text `rule` flags in `text` under `config`.
+    fn flagged(config: &StyleConfig, text: &str, rule: RuleId) -> Vec<String> {
+        Linter::new(config)
+            .lint(text)
+            .into_iter()
+            .filter(|d| d.rule == rule)
+            .map(|d| d.text)
+            .collect()
+    }
+
+    fn defaults(text: &str, rule: RuleId) -> Vec<String> {
+        flagged(&StyleConfig::default(), text, rule)
+    }
+
+    #[test]
+    fn each_rule_flags_its_text() {
+        assert_eq!(defaults("She ran quickly home. It was early.", RuleId::Adverb), ["quickly"]);
+        let filtered = defaults("She could see the tower. He felt cold.", RuleId::FilterWord);
+        assert_eq!(filtered, ["She could see", "He felt"]);
+        let passive = defaults("The door was slowly opened. The gate was built.", RuleId::PassiveVoice);
+        assert_eq!(passive, ["was slowly opened", "was built"]);
+        assert_eq!(defaults("It was sort of cold and very dark.", RuleId::WeaselWord), ["sort of", "very"]);
+        assert_eq!(defaults("It was a blessing in disguise.", RuleId::Cliche), ["a blessing in disguise"]);
+        assert_eq!(defaults("He ran. He hid. He waited.\n\nHe slept.", RuleId::RepeatedOpener), ["He"]);
+        assert_eq!(defaults("The lantern swung. A lantern fell.", RuleId::Echo), ["lantern"]);
+
+        let config = StyleConfig {
+            max_sentence_words: 5,
+            ..StyleConfig::default()
+        };
+        let long = flagged(&config, "One two three four five six. Short one.", RuleId::LongSentence);
+        assert_eq!(long, ["One two three four five six"]);
+    }
+
+    #[test]
+    fn headings_and_scene_breaks_are_not_prose() {
+        assert!(defaults("# Very quickly\n\n***", RuleId::Adverb).is_empty());
+        // A heading ends a run of openers.
+        assert!(defaults("He ran. He hid.\n# Two\nHe waited.", RuleId::RepeatedOpener).is_empty());
+    }
+
+    #[test]
+    fn names_are_not_adverbs() {
+        let text = "Emily smiled. I waved at Emily. She left hastily.";
+        assert_eq!(defaults(text, RuleId::Adverb), ["hastily"]);
+    }
+
+    #[test]
+    fn allowed_text_is_let_through_ignoring_case() {
+        let config = StyleConfig {
+            allow: BTreeMap::from([
+                (RuleId::Adverb, vec!["Quickly".to_string()]),
+                (RuleId::RepeatedOpener, vec!["he".to_string()]),
+                (RuleId::Cliche, vec!["A  Blessing in Disguise".to_string()]),
+            ]),
+            ..StyleConfig::default()
+        };
+        let text = "He ran quickly. He hid slowly. He waited. It was a blessing in disguise.";
+        assert_eq!(flagged(&config, text, RuleId::Adverb), ["slowly"]);
+        assert!(flagged(&config, text, RuleId::RepeatedOpener).is_empty());
+        assert!(flagged(&config, text, RuleId::Cliche).is_empty());
+    }
+
+    #[test]
+    fn disabled_rules_report_nothing() {
+        let config = StyleConfig {
+            disabled: RuleId::ALL.into_iter().collect(),
+            max_sentence_words: 5,
+            ..StyleConfig::default()
+        };
+        let text = "She saw it was very quickly done, a blessing in disguise. She saw it. She saw it again and again.";
+        assert!(Linter::new(&config).lint(text).is_empty());
+        assert!(!Linter::new(&StyleConfig::default()).lint(text).is_empty());
+    }
+
+    #[test]
+    fn severities_default_per_rule_and_can_be_overridden() {
+        let config = StyleConfig {
+            severity: BTreeMap::from([(RuleId::Adverb, Severity::Error)]),
+            ..StyleConfig::default()
+        };
+        let diagnostics = Linter::new(&config).lint("She ran quickly, a blessing in disguise. It was very odd.");
+        let severity = |rule| diagnostics.iter().find(|d| d.rule == rule).unwrap().severity;
+        assert_eq!(severity(RuleId::Adverb), Severity::Error);
+        assert_eq!(severity(RuleId::Cliche), Severity::Warning);
+        assert_eq!(severity(RuleId::WeaselWord), Severity::Info);
+    }
+
+    #[test]
+    fn out_of_range_settings_are_refused() {
+        assert!(StyleConfig::default().validate().is_ok());
+        for config in [
+            StyleConfig { max_sentence_words: 4, ..StyleConfig::default() },
+            StyleConfig { max_repeated_openers: 0, ..StyleConfig::default() },
+            StyleConfig { echo_window: 1, ..StyleConfig::default() },
+        ] {
+            assert!(matches!(config.validate(), Err(ProjectError::InvalidSettings { .. })));
+        }
+    }
+}
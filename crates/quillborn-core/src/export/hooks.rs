@@ -0,0 +1,407 @@
+//! Export hooks: small [Rhai](https://rhai.rs) scripts kept in the project
+//! under `scripts/export/` that adjust text on its way out.
+//!
+//! - `pre.rhai` runs once per chapter, before the chapter is converted to
+//!   the output format. It sees `text` (the chapter's markdown), `title`,
+//!   `number` (1-based), `chapter_id`, plus the common variables below, and
+//!   may change `text` and `title`, or evaluate to the new text.
+//! - `post.rhai` runs on the finished document (for EPUB, on each chapter
+//!   page) with the document in `text`, and may change it the same way.
+//!
+//! Both also see `format`, `book_title` and `author`, and can call
+//! `characters()`, `character(name_or_id)`, `note(title)`, `number_words(n)`,
+//! `roman(n)` and `regex_replace(text, pattern, replacement_or_fn)`.
+//! Scripts cannot import modules or touch the filesystem, and are stopped
+//! when they run too long or build strings, arrays or maps past a cap.
+
+use regex::Regex;
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FnPtr, Map, NativeCallContext, Position, Scope, AST};
+use std::cell::Cell;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::manuscript::chapter::{count_words, Chapter};
+use crate::manuscript::error::WithPath;
+use crate::manuscript::project::{Project, ProjectError};
+use crate::planning::{characters, notes};
+
+pub const SCRIPTS_DIR: &str = "scripts/export";
+pub const PRE_SCRIPT: &str = "pre.rhai";
+pub const POST_SCRIPT: &str = "post.rhai";
+
+/// How long one run of a hook may take before it is stopped.
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Caps on what a hook may build, so a script can't exhaust memory well
+/// within its time limit. Strings leave room for a whole book.
+const MAX_STRING_BYTES: usize = 64 * 1024 * 1024;
+const MAX_ARRAY_LEN: usize = 100_000;
+const MAX_MAP_LEN: usize = 10_000;
+const MAX_CALL_LEVELS: usize = 64;
+/// Expression nesting at the top level and inside functions.
+const MAX_EXPR_DEPTHS: (usize, usize) = (64, 32);
+
+struct Script {
+    /// Project-relative path, used in error messages.
+    name: String,
+    ast: AST,
+}
+
+pub struct ExportHooks {
+    engine: Engine,
+    pre: Option<Script>,
+    post: Option<Script>,
+    format: String,
+    book_title: String,
+    author: String,
+    deadline: Rc<Cell<Instant>>,
+    timed_out: Rc<Cell<bool>>,
+}
+
+impl ExportHooks {
+    /// Compile whichever hook scripts the project has. `format` is passed to
+    /// the scripts so one script can serve several exporters.
+    pub fn load(project: &Project, format: &str) -> Result<Self, ProjectError> {
+        let deadline = Rc::new(Cell::new(Instant::now()));
+        let timed_out = Rc::new(Cell::new(false));
+        let mut hooks = ExportHooks {
+            engine: Engine::new(),
+            pre: None,
+            post: None,
+            format: format.to_string(),
+            book_title: project.metadata.title.clone(),
+            author: project.metadata.author.clone(),
+            deadline,
+            timed_out,
+        };
+
+        let dir = project.path.join(SCRIPTS_DIR);
+        if !dir.join(PRE_SCRIPT).is_file() && !dir.join(POST_SCRIPT).is_file() {
+            return Ok(hooks);
+        }
+
+        hooks.engine = build_engine(project, hooks.deadline.clone(), hooks.timed_out.clone())?;
+        hooks.pre = hooks.compile(&dir, PRE_SCRIPT)?;
+        hooks.post = hooks.compile(&dir, POST_SCRIPT)?;
+        Ok(hooks)
+    }
+
+    /// Run `pre.rhai` over each chapter's markdown and title.
+    pub fn apply_to_chapters(&self, mut chapters: Vec<Chapter>) -> Result<Vec<Chapter>, ProjectError> {
+        let Some(script) = &self.pre else {
+            return Ok(chapters);
+        };
+
+        for (i, chapter) in chapters.iter_mut().enumerate() {
+            let mut scope = self.scope(&chapter.content);
+            scope.push("title", chapter.title.clone());
+            scope.push("number", (i + 1) as i64);
+            scope.push("chapter_id", chapter.id.clone());
+
+            chapter.content = self.run(script, &mut scope)?;
+            if let Some(title) = scope.get_value::<String>("title") {
+                chapter.title = title;
+            }
+            chapter.word_count = count_words(&chapter.content) as u64;
+        }
+        Ok(chapters)
+    }
+
+    /// Run `post.rhai` over a finished document.
+    pub fn apply_to_document(&self, document: String) -> Result<String, ProjectError> {
+        match &self.post {
+            Some(script) => self.run(script, &mut self.scope(&document)),
+            None => Ok(document),
+        }
+    }
+
+    fn compile(&self, dir: &Path, file: &str) -> Result<Option<Script>, ProjectError> {
+        let path = dir.join(file);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let name = format!("{}/{}", SCRIPTS_DIR, file);
+        let source = fs::read_to_string(&path).with_path(&path)?;
+        let ast = self
+            .engine
+            .compile(&source)
+            .map_err(|e| script_error(&name, e.1, e.0.to_string()))?;
+        Ok(Some(Script { name, ast }))
+    }
+
+    fn scope(&self, text: &str) -> Scope<'static> {
+        let mut scope = Scope::new();
+        scope.push("text", text.to_string());
+        scope.push_constant("format", self.format.clone());
+        scope.push_constant("book_title", self.book_title.clone());
+        scope.push_constant("author", self.author.clone());
+        scope
+    }
+
+    /// Evaluate a script and take its result as the new text if it is a
+    /// string, otherwise whatever the script left in `text`.
+    fn run(&self, script: &Script, scope: &mut Scope) -> Result<String, ProjectError> {
+        self.deadline.set(Instant::now() + SCRIPT_TIMEOUT);
+        self.timed_out.set(false);
+
+        match self.engine.eval_ast_with_scope::<Dynamic>(scope, &script.ast) {
+            Ok(result) if result.is_string() => Ok(result.cast::<String>()),
+            Ok(_) => scope.get_value::<String>("text").ok_or_else(|| {
+                script_error(&script.name, Position::NONE, "`text` is no longer a string".to_string())
+            }),
+            Err(_) if self.timed_out.get() => Err(ProjectError::ExportScriptTimeout(script.name.clone())),
+            Err(mut e) => {
+                let position = e.take_position();
+                Err(script_error(&script.name, position, e.to_string()))
+            }
+        }
+    }
+}
+
+fn script_error(script: &str, position: Position, message: String) -> ProjectError {
+    ProjectError::ExportScript {
+        script: script.to_string(),
+        line: position.line(),
+        column: position.position(),
+        message,
+    }
+}
+
+/// An engine with the hook helpers registered and no way to reach the
+/// filesystem. Project data the helpers need is read once up front.
+fn build_engine(
+    project: &Project,
+    deadline: Rc<Cell<Instant>>,
+    timed_out: Rc<Cell<bool>>,
+) -> Result<Engine, ProjectError> {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine
+        .set_max_string_size(MAX_STRING_BYTES)
+        .set_max_array_size(MAX_ARRAY_LEN)
+        .set_max_map_size(MAX_MAP_LEN)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_expr_depths(MAX_EXPR_DEPTHS.0, MAX_EXPR_DEPTHS.1);
+    engine.on_print(|message| eprintln!("[export script] {}", message));
+    engine.on_debug(|message, _, position| eprintln!("[export script] {} {}", position, message));
+    engine.on_progress(move |operations| {
+        if operations % 1024 == 0 && Instant::now() > deadline.get() {
+            timed_out.set(true);
+            return Some(Dynamic::UNIT);
+        }
+        None
+    });
+
+    let sheets = characters::list_characters(project)?;
+    let all_characters: Array = sheets
+        .iter()
+        .map(|c| {
+            let mut map = Map::new();
+            map.insert("id".into(), c.id.clone().into());
+            map.insert("name".into(), c.name.clone().into());
+            map.insert("aliases".into(), c.aliases.iter().cloned().map(Dynamic::from).collect::<Array>().into());
+            map.insert("role".into(), c.role.clone().into());
+            Dynamic::from_map(map)
+        })
+        .collect();
+    engine.register_fn("characters", move || all_characters.clone());
+
+    // `character("mara")` matches an id, a full name, a first name or an
+    // alias, ignoring case, and gives back the character's full name.
+    engine.register_fn("character", move |key: &str| -> Dynamic {
+        let key = key.trim().to_lowercase();
+        sheets
+            .iter()
+            .find(|c| {
+                c.id == key
+                    || c.name.to_lowercase() == key
+                    || c.name.split_whitespace().next().map(str::to_lowercase) == Some(key.clone())
+                    || c.aliases.iter().any(|a| a.to_lowercase() == key)
+            })
+            .map(|c| Dynamic::from(c.name.clone()))
+            .unwrap_or(Dynamic::UNIT)
+    });
+
+    let all_notes = notes::list_notes(project)?;
+    engine.register_fn("note", move |title: &str| -> Dynamic {
+        all_notes
+            .iter()
+            .find(|n| n.title.eq_ignore_ascii_case(title))
+            .map(|n| Dynamic::from(n.content.clone()))
+            .unwrap_or(Dynamic::UNIT)
+    });
+
+    engine.register_fn("number_words", |n: i64| number_words(n));
+    engine.register_fn("roman", |n: i64| roman(n));
+    engine.register_fn(
+        "regex_replace",
+        |text: &str, pattern: &str, replacement: &str| -> Result<String, Box<EvalAltResult>> {
+            Ok(compile_regex(pattern)?.replace_all(text, replacement).into_owned())
+        },
+    );
+    engine.register_fn(
+        "regex_replace",
+        |context: NativeCallContext, text: &str, pattern: &str, callback: FnPtr| -> Result<String, Box<EvalAltResult>> {
+            let regex = compile_regex(pattern)?;
+            let mut output = String::with_capacity(text.len());
+            let mut last = 0;
+            for captures in regex.captures_iter(text) {
+                let whole = captures.get(0).expect("capture 0 is always present");
+                let groups: Array = captures
+                    .iter()
+                    .map(|m| m.map_or(Dynamic::UNIT, |m| m.as_str().into()))
+                    .collect();
+                let replacement: Dynamic = callback.call_within_context(&context, (groups,))?;
+                output.push_str(&text[last..whole.start()]);
+                output.push_str(&replacement.to_string());
+                last = whole.end();
+            }
+            output.push_str(&text[last..]);
+            Ok(output)
+        },
+    );
+
+    Ok(engine)
+}
+
+fn compile_regex(pattern: &str) -> Result<Regex, Box<EvalAltResult>> {
+    Regex::new(pattern).map_err(|e| e.to_string().into())
+}
+
+/// Spell out a number in title case, e.g. `21` as "Twenty-One".
+fn number_words(n: i64) -> String {
+    const ONES: [&str; 20] = [
+        "Zero", "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine", "Ten",
+        "Eleven", "Twelve", "Thirteen", "Fourteen", "Fifteen", "Sixteen", "Seventeen", "Eighteen",
+        "Nineteen",
+    ];
+    const TENS: [&str; 10] = [
+        "", "", "Twenty", "Thirty", "Forty", "Fifty", "Sixty", "Seventy", "Eighty", "Ninety",
+    ];
+
+    if n < 0 {
+        return format!("Minus {}", number_words(n.saturating_neg()));
+    }
+    let n = n as u64;
+    match n {
+        0..=19 => ONES[n as usize].to_string(),
+        20..=99 => match n % 10 {
+            0 => TENS[(n / 10) as usize].to_string(),
+            ones => format!("{}-{}", TENS[(n / 10) as usize], ONES[ones as usize]),
+        },
+        _ => {
+            let (unit, size) = match n {
+                100..=999 => ("Hundred", 100),
+                1_000..=999_999 => ("Thousand", 1_000),
+                1_000_000..=999_999_999 => ("Million", 1_000_000),
+                _ => ("Billion", 1_000_000_000),
+            };
+            let head = format!("{} {}", number_words((n / size) as i64), unit);
+            match n % size {
+                0 => head,
+                rest => format!("{} {}", head, number_words(rest as i64)),
+            }
+        }
+    }
+}
+
+/// Roman numerals for chapter numbers; anything outside 1..=3999 is left
+/// as digits.
+fn roman(n: i64) -> String {
+    const NUMERALS: [(i64, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    if !(1..=3999).contains(&n) {
+        return n.to_string();
+    }
+    let mut rest = n;
+    let mut output = String::new();
+    for (value, numeral) in NUMERALS {
+        while rest >= value {
+            output.push_str(numeral);
+            rest -= value;
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project_with_pre(script: &str) -> (tempfile::TempDir, Project) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "Hooks", "Author").unwrap();
+        project.add_chapter("One", None).unwrap();
+        let scripts = project.path.join(SCRIPTS_DIR);
+        fs::create_dir_all(&scripts).unwrap();
+        fs::write(scripts.join(PRE_SCRIPT), script).unwrap();
+        (dir, project)
+    }
+
+    fn run_pre(script: &str) -> Result<Vec<Chapter>, ProjectError> {
+        let (_dir, project) = project_with_pre(script);
+        let hooks = ExportHooks::load(&project, "markdown")?;
+        hooks.apply_to_chapters(vec![Chapter::new("One")])
+    }
+
+    #[test]
+    fn scripts_rewrite_text_and_titles() {
+        let chapters = run_pre("title = \"Chapter \" + roman(number); text + \"!\"").unwrap();
+        assert_eq!(chapters[0].title, "Chapter I");
+        assert!(chapters[0].content.ends_with('!'));
+    }
+
+    #[test]
+    fn endless_scripts_time_out() {
+        let error = run_pre("loop { }").unwrap_err();
+        assert!(matches!(error, ProjectError::ExportScriptTimeout(ref name) if name == "scripts/export/pre.rhai"));
+    }
+
+    #[test]
+    fn scripts_cannot_build_huge_values() {
+        for script in [
+            "let s = \"x\"; loop { s += s; }",
+            "let a = [1]; loop { a += a; }",
+            "fn f(n) { f(n + 1) } f(0)",
+        ] {
+            let error = run_pre(script).unwrap_err();
+            assert!(matches!(error, ProjectError::ExportScript { .. }), "{}: {:?}", script, error);
+        }
+    }
+
+    #[test]
+    fn script_errors_carry_the_line() {
+        let error = run_pre("let a = 1;\nlet b = a +;\n").unwrap_err();
+        let ProjectError::ExportScript { line, .. } = error else {
+            panic!("expected a script error, got {:?}", error);
+        };
+        assert_eq!(line, Some(2));
+
+        let error = run_pre("let a = 1;\n\nno_such_function(a)").unwrap_err();
+        assert!(matches!(error, ProjectError::ExportScript { line: Some(3), .. }), "{:?}", error);
+    }
+
+    #[test]
+    fn numbers_are_spelled_out() {
+        assert_eq!(number_words(21), "Twenty-One");
+        assert_eq!(number_words(1_005), "One Thousand Five");
+        assert_eq!(roman(1994), "MCMXCIV");
+        assert_eq!(roman(0), "0");
+    }
+}
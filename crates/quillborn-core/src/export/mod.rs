@@ -1,3 +1,5 @@
+pub mod hooks;
+
 use std::fs;
 use std::io::{Cursor, Write};
 use std::path::PathBuf;
@@ -6,46 +8,46 @@ use zip::write::SimpleFileOptions;
 use zip::ZipWriter;
 
 use crate::manuscript::chapter::Chapter;
-use crate::manuscript::project::{NodeType, Project, ProjectError};
+use crate::manuscript::error::WithPath;
+use crate::manuscript::paths;
+use crate::manuscript::project::{Project, ProjectError};
+use crate::search::SmartCollections;
+use hooks::ExportHooks;
 
-fn collect_chapters_in_order(project: &Project) -> Result<Vec<Chapter>, ProjectError> {
+pub(crate) fn collect_chapters_in_order(project: &Project) -> Result<Vec<Chapter>, ProjectError> {
     let mut chapters = Vec::new();
-
-    fn walk_node(
-        project: &Project,
-        node_id: &str,
-        chapters: &mut Vec<Chapter>,
-    ) -> Result<(), ProjectError> {
-        if let Some(node) = project.structure.nodes.get(node_id) {
-            if node.node_type == NodeType::Chapter {
-                let chapter_path = project
-                    .path
-                    .join("chapters")
-                    .join(format!("{}.md", node_id));
-                if chapter_path.exists() {
-                    let chapter = Chapter::from_file(&chapter_path).map_err(|e| {
-                        ProjectError::Io(std::io::Error::new(
-                            std::io::ErrorKind::Other,
-                            e.to_string(),
-                        ))
-                    })?;
-                    chapters.push(chapter);
-                }
-            }
-            for child_id in &node.children {
-                walk_node(project, child_id, chapters)?;
-            }
+    for chapter_id in project.chapter_order() {
+        if project.chapter_path(&chapter_id).exists() {
+            chapters.push(project.load_chapter(&chapter_id)?);
         }
-        Ok(())
     }
+    Ok(chapters)
+}
 
-    walk_node(project, &project.structure.root.clone(), &mut chapters)?;
+/// The whole manuscript, or with a smart collection id only the chapters
+/// that collection covers.
+fn chapters_to_export(project: &Project, collection: Option<&str>) -> Result<Vec<Chapter>, ProjectError> {
+    let Some(collection_id) = collection else {
+        return collect_chapters_in_order(project);
+    };
+    let mut chapters = Vec::new();
+    for chapter_id in SmartCollections::load(project)?.get(collection_id)?.chapters(project)? {
+        if project.chapter_path(&chapter_id).exists() {
+            chapters.push(project.load_chapter(&chapter_id)?);
+        }
+    }
     Ok(chapters)
 }
 
-pub fn export_markdown(project_path: String, output_path: String) -> Result<String, ProjectError> {
+pub fn export_markdown(
+    project_path: String,
+    output_path: String,
+    collection: Option<String>,
+    allowed_dirs: &[PathBuf],
+) -> Result<String, ProjectError> {
     let project = Project::open(&PathBuf::from(&project_path))?;
-    let chapters = collect_chapters_in_order(&project)?;
+    let hooks = ExportHooks::load(&project, "markdown")?;
+    let chapters = hooks.apply_to_chapters(chapters_to_export(&project, collection.as_deref())?)?;
 
     let mut output = String::new();
     output.push_str(&format!("# {}\n\n", project.metadata.title));
@@ -60,8 +62,9 @@ pub fn export_markdown(project_path: String, output_path: String) -> Result<Stri
         output.push_str("\n\n---\n\n");
     }
 
-    let out_path = PathBuf::from(&output_path);
-    fs::write(&out_path, &output)?;
+    let output = hooks.apply_to_document(output)?;
+    let out_path = paths::resolve_export_path(&project.path, &output_path, paths::MARKDOWN_EXTENSIONS, allowed_dirs)?;
+    fs::write(&out_path, &output).with_path(&out_path)?;
 
     Ok(out_path.display().to_string())
 }
@@ -69,13 +72,16 @@ pub fn export_markdown(project_path: String, output_path: String) -> Result<Stri
 pub fn export_plain_text(
     project_path: String,
     output_path: String,
+    collection: Option<String>,
+    allowed_dirs: &[PathBuf],
 ) -> Result<String, ProjectError> {
     let project = Project::open(&PathBuf::from(&project_path))?;
-    let chapters = collect_chapters_in_order(&project)?;
+    let hooks = ExportHooks::load(&project, "text")?;
+    let chapters = hooks.apply_to_chapters(chapters_to_export(&project, collection.as_deref())?)?;
 
     let mut output = String::new();
     output.push_str(&project.metadata.title.to_uppercase());
-    output.push_str("\n");
+    output.push('\n');
     if !project.metadata.author.is_empty() {
         output.push_str(&format!("by {}", project.metadata.author));
     }
@@ -90,8 +96,9 @@ pub fn export_plain_text(
         output.push_str("\n\n");
     }
 
-    let out_path = PathBuf::from(&output_path);
-    fs::write(&out_path, &output)?;
+    let output = hooks.apply_to_document(output)?;
+    let out_path = paths::resolve_export_path(&project.path, &output_path, paths::TEXT_EXTENSIONS, allowed_dirs)?;
+    fs::write(&out_path, &output).with_path(&out_path)?;
 
     Ok(out_path.display().to_string())
 }
@@ -108,7 +115,7 @@ fn strip_markdown(text: &str) -> String {
         };
         // Remove bold/italic markers
         let line = line.replace("**", "").replace("__", "");
-        let line = line.replace('*', "").replace('_', "");
+        let line = line.replace(['*', '_'], "");
         result.push_str(&line);
         result.push('\n');
     }
@@ -284,8 +291,7 @@ fn inline_markdown_to_html(text: &str) -> String {
     let text = replace_delimited(&text, "*", "<em>", "</em>");
     let text = replace_delimited(&text, "_", "<em>", "</em>");
     // Inline code: `text`
-    let text = replace_delimited(&text, "`", "<code>", "</code>");
-    text
+    replace_delimited(&text, "`", "<code>", "</code>")
 }
 
 fn html_escape(text: &str) -> String {
@@ -314,9 +320,15 @@ fn replace_delimited(text: &str, delimiter: &str, open: &str, close: &str) -> St
     result
 }
 
-pub fn export_html(project_path: String, output_path: String) -> Result<String, ProjectError> {
+pub fn export_html(
+    project_path: String,
+    output_path: String,
+    collection: Option<String>,
+    allowed_dirs: &[PathBuf],
+) -> Result<String, ProjectError> {
     let project = Project::open(&PathBuf::from(&project_path))?;
-    let chapters = collect_chapters_in_order(&project)?;
+    let hooks = ExportHooks::load(&project, "html")?;
+    let chapters = hooks.apply_to_chapters(chapters_to_export(&project, collection.as_deref())?)?;
 
     let title = html_escape(&project.metadata.title);
     let author = html_escape(&project.metadata.author);
@@ -502,8 +514,9 @@ pub fn export_html(project_path: String, output_path: String) -> Result<String,
         body = body,
     );
 
-    let out_path = PathBuf::from(&output_path);
-    fs::write(&out_path, &html)?;
+    let html = hooks.apply_to_document(html)?;
+    let out_path = paths::resolve_export_path(&project.path, &output_path, paths::HTML_EXTENSIONS, allowed_dirs)?;
+    fs::write(&out_path, &html).with_path(&out_path)?;
 
     Ok(out_path.display().to_string())
 }
@@ -630,13 +643,18 @@ fn inline_markdown_to_latex(text: &str) -> String {
     let text = replace_delimited(&text, "*", "\\emph{", "}");
     let text = replace_delimited(&text, "\\_", "\\emph{", "}");
     // Inline code: `text`
-    let text = replace_delimited(&text, "`", "\\texttt{", "}");
-    text
+    replace_delimited(&text, "`", "\\texttt{", "}")
 }
 
-pub fn export_latex(project_path: String, output_path: String) -> Result<String, ProjectError> {
+pub fn export_latex(
+    project_path: String,
+    output_path: String,
+    collection: Option<String>,
+    allowed_dirs: &[PathBuf],
+) -> Result<String, ProjectError> {
     let project = Project::open(&PathBuf::from(&project_path))?;
-    let chapters = collect_chapters_in_order(&project)?;
+    let hooks = ExportHooks::load(&project, "latex")?;
+    let chapters = hooks.apply_to_chapters(chapters_to_export(&project, collection.as_deref())?)?;
 
     let title = latex_escape(&project.metadata.title);
     let author = latex_escape(&project.metadata.author);
@@ -684,8 +702,9 @@ pub fn export_latex(project_path: String, output_path: String) -> Result<String,
         body = body,
     );
 
-    let out_path = PathBuf::from(&output_path);
-    fs::write(&out_path, &latex)?;
+    let latex = hooks.apply_to_document(latex)?;
+    let out_path = paths::resolve_export_path(&project.path, &output_path, paths::LATEX_EXTENSIONS, allowed_dirs)?;
+    fs::write(&out_path, &latex).with_path(&out_path)?;
 
     Ok(out_path.display().to_string())
 }
@@ -838,14 +857,19 @@ fn inline_markdown_to_xhtml(text: &str) -> String {
     let text = replace_delimited(&text, "__", "<strong>", "</strong>");
     let text = replace_delimited(&text, "*", "<em>", "</em>");
     let text = replace_delimited(&text, "_", "<em>", "</em>");
-    let text = replace_delimited(&text, "`", "<code>", "</code>");
-    text
+    replace_delimited(&text, "`", "<code>", "</code>")
 }
 
 /// Generate a valid EPUB 3.0 file from the project.
-pub fn export_epub(project_path: String, output_path: String) -> Result<String, ProjectError> {
+pub fn export_epub(
+    project_path: String,
+    output_path: String,
+    collection: Option<String>,
+    allowed_dirs: &[PathBuf],
+) -> Result<String, ProjectError> {
     let project = Project::open(&PathBuf::from(&project_path))?;
-    let chapters = collect_chapters_in_order(&project)?;
+    let hooks = ExportHooks::load(&project, "epub")?;
+    let chapters = hooks.apply_to_chapters(chapters_to_export(&project, collection.as_deref())?)?;
 
     let title = xml_escape(&project.metadata.title);
     let author = xml_escape(&project.metadata.author);
@@ -856,10 +880,8 @@ pub fn export_epub(project_path: String, output_path: String) -> Result<String,
     // 1. mimetype - MUST be first entry and stored uncompressed
     let stored_options = SimpleFileOptions::default()
         .compression_method(zip::CompressionMethod::Stored);
-    zip.start_file("mimetype", stored_options)
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
-    zip.write_all(b"application/epub+zip")
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
+    zip.start_file("mimetype", stored_options)?;
+    zip.write_all(b"application/epub+zip")?;
 
     let deflated_options = SimpleFileOptions::default()
         .compression_method(zip::CompressionMethod::Deflated);
@@ -871,10 +893,8 @@ pub fn export_epub(project_path: String, output_path: String) -> Result<String,
     <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml" />
   </rootfiles>
 </container>"#;
-    zip.start_file("META-INF/container.xml", deflated_options)
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
-    zip.write_all(container_xml.as_bytes())
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
+    zip.start_file("META-INF/container.xml", deflated_options)?;
+    zip.write_all(container_xml.as_bytes())?;
 
     // 3. OEBPS/style.css
     let style_css = r#"body {
@@ -934,10 +954,8 @@ code {
 strong { font-weight: bold; }
 em { font-style: italic; }
 "#;
-    zip.start_file("OEBPS/style.css", deflated_options)
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
-    zip.write_all(style_css.as_bytes())
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
+    zip.start_file("OEBPS/style.css", deflated_options)?;
+    zip.write_all(style_css.as_bytes())?;
 
     // 4. OEBPS/title.xhtml
     let title_xhtml = format!(
@@ -959,10 +977,8 @@ em { font-style: italic; }
         title = title,
         author = author,
     );
-    zip.start_file("OEBPS/title.xhtml", deflated_options)
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
-    zip.write_all(title_xhtml.as_bytes())
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
+    zip.start_file("OEBPS/title.xhtml", deflated_options)?;
+    zip.write_all(title_xhtml.as_bytes())?;
 
     // 5. Chapter XHTML files
     for (i, chapter) in chapters.iter().enumerate() {
@@ -986,11 +1002,10 @@ em { font-style: italic; }
             title = chap_title,
             body = chap_body,
         );
+        let chap_xhtml = hooks.apply_to_document(chap_xhtml)?;
         let filename = format!("OEBPS/chapter-{}.xhtml", chap_num);
-        zip.start_file(&filename, deflated_options)
-            .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
-        zip.write_all(chap_xhtml.as_bytes())
-            .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
+        zip.start_file(&filename, deflated_options)?;
+        zip.write_all(chap_xhtml.as_bytes())?;
     }
 
     // 6. OEBPS/nav.xhtml (EPUB 3 navigation document)
@@ -1024,20 +1039,18 @@ em { font-style: italic; }
 </html>"#,
         items = nav_items,
     );
-    zip.start_file("OEBPS/nav.xhtml", deflated_options)
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
-    zip.write_all(nav_xhtml.as_bytes())
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
+    zip.start_file("OEBPS/nav.xhtml", deflated_options)?;
+    zip.write_all(nav_xhtml.as_bytes())?;
 
     // 7. OEBPS/toc.ncx (NCX for backward compatibility)
     let mut ncx_nav_points = String::new();
-    ncx_nav_points.push_str(&format!(
+    ncx_nav_points.push_str(
         r#"    <navPoint id="title" playOrder="1">
       <navLabel><text>Title Page</text></navLabel>
       <content src="title.xhtml" />
     </navPoint>
-"#
-    ));
+"#,
+    );
     for (i, chapter) in chapters.iter().enumerate() {
         let chap_num = i + 1;
         let play_order = i + 2;
@@ -1073,10 +1086,8 @@ em { font-style: italic; }
         title = title,
         nav_points = ncx_nav_points,
     );
-    zip.start_file("OEBPS/toc.ncx", deflated_options)
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
-    zip.write_all(toc_ncx.as_bytes())
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
+    zip.start_file("OEBPS/toc.ncx", deflated_options)?;
+    zip.write_all(toc_ncx.as_bytes())?;
 
     // 8. OEBPS/content.opf (package document)
     let book_uid = uuid::Uuid::new_v4();
@@ -1123,17 +1134,55 @@ em { font-style: italic; }
         manifest = manifest_items,
         spine = spine_items,
     );
-    zip.start_file("OEBPS/content.opf", deflated_options)
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
-    zip.write_all(content_opf.as_bytes())
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
+    zip.start_file("OEBPS/content.opf", deflated_options)?;
+    zip.write_all(content_opf.as_bytes())?;
 
     // Finalize the ZIP archive
-    let cursor = zip.finish()
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
+    let cursor = zip.finish()?;
 
-    let out_path = PathBuf::from(&output_path);
-    fs::write(&out_path, cursor.into_inner())?;
+    let out_path = paths::resolve_export_path(&project.path, &output_path, paths::EPUB_EXTENSIONS, allowed_dirs)?;
+    fs::write(&out_path, cursor.into_inner()).with_path(&out_path)?;
 
     Ok(out_path.display().to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manuscript::ids::ChapterId;
+    use crate::manuscript::project::ChapterStatus;
+    use crate::search::SmartCollection;
+
+    #[test]
+    fn a_collection_exports_only_the_chapters_it_covers() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "Export", "Author").unwrap();
+        let mut ids = Vec::new();
+        let chapters = [("Arrival", "The ship docked."), ("Storm", "Rain fell."), ("Lost", "Cut scene.")];
+        for (title, content) in chapters {
+            let chapter = project.add_chapter(title, None).unwrap();
+            let id = ChapterId::parse(&chapter.id).unwrap();
+            project.update_chapter_content(&id, content).unwrap();
+            project.set_node_tags(&id, vec!["keep".to_string()]).unwrap();
+            ids.push(id);
+        }
+        project.set_node_tags(&ids[1], Vec::new()).unwrap();
+        project.set_chapter_status(&ids[2], ChapterStatus::Trash).unwrap();
+
+        let mut collections = SmartCollections::load(&project).unwrap();
+        let collection = SmartCollection::new("Kept", "tag:keep").unwrap();
+        let collection_id = collection.id.clone();
+        collections.upsert(collection).unwrap();
+        collections.save(&project).unwrap();
+
+        let project_path = project.path.display().to_string();
+        let out = export_markdown(project_path.clone(), "kept.md".to_string(), Some(collection_id), &[]).unwrap();
+        let text = fs::read_to_string(out).unwrap();
+        assert!(text.contains("## Arrival\n\nThe ship docked."));
+        assert!(!text.contains("Storm"));
+        assert!(!text.contains("Lost"));
+
+        let missing = export_markdown(project_path, "none.md".to_string(), Some("missing".to_string()), &[]);
+        assert!(matches!(missing, Err(ProjectError::CollectionNotFound(_))));
+    }
+}
@@ -0,0 +1,13 @@
+//! Everything Quillborn knows about a `.qb` project, independent of any UI:
+//! the manuscript tree and chapter files, planning data, search, prose
+//! analysis and exporters, the sandboxed plugin host and the local
+//! automation API. The desktop app and the CLI are thin layers on top.
+
+pub mod analysis;
+pub mod export;
+pub mod manuscript;
+pub mod planning;
+pub mod plugins;
+pub mod rpc;
+pub mod search;
+pub mod settings;
@@ -35,10 +35,6 @@ impl Chapter {
         }
     }
 
-    pub fn filename(&self) -> String {
-        format!("{}.md", self.id)
-    }
-
     pub fn to_markdown(&self) -> String {
         let mut output = String::new();
         output.push_str("---\n");
@@ -63,7 +59,10 @@ impl Chapter {
     }
 
     pub fn from_file(path: &Path) -> Result<Self, ChapterError> {
-        let raw = fs::read_to_string(path)?;
+        let raw = fs::read_to_string(path).map_err(|source| ChapterError::Read {
+            path: path.display().to_string(),
+            source,
+        })?;
         Self::from_markdown(&raw, path)
     }
 
@@ -76,7 +75,7 @@ impl Chapter {
 
         if !raw.starts_with("---") {
             // Plain markdown without frontmatter
-            let word_count = count_words(&raw);
+            let word_count = count_words(raw);
             let now = Utc::now();
             return Ok(Chapter {
                 id,
@@ -175,8 +174,29 @@ pub fn count_words(text: &str) -> usize {
 
 #[derive(Debug, thiserror::Error)]
 pub enum ChapterError {
-    #[error("IO error: {0}")]
-    Io(#[from] std::io::Error),
+    #[error("Could not read chapter {path}: {source}")]
+    Read {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl ChapterError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ChapterError::Read { source, .. } if source.kind() == std::io::ErrorKind::NotFound => {
+                "chapter_not_found"
+            }
+            ChapterError::Read { .. } => "chapter_unreadable",
+        }
+    }
+
+    pub fn details(&self) -> serde_json::Value {
+        match self {
+            ChapterError::Read { path, .. } => serde_json::json!({ "path": path }),
+        }
+    }
 }
 
 impl serde::Serialize for ChapterError {
@@ -184,6 +204,11 @@ impl serde::Serialize for ChapterError {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("ChapterError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
     }
 }
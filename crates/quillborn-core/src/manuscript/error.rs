@@ -0,0 +1,323 @@
+use serde::ser::SerializeStruct;
+use serde_json::{json, Value};
+use std::io;
+use std::path::Path;
+
+use super::chapter::ChapterError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProjectError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("IO error on {path}: {source}")]
+    FileIo {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid JSON in {path}: {source}")]
+    JsonFile {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("TOML serialization error: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+    #[error("TOML deserialization error: {0}")]
+    TomlDe(#[from] toml::de::Error),
+    #[error("Invalid TOML in {path}: {message}")]
+    TomlFile {
+        path: String,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+    #[error(transparent)]
+    Chapter(#[from] ChapterError),
+    #[error("Export archive error: {0}")]
+    Archive(#[from] zip::result::ZipError),
+    #[error("Project not found: {0}")]
+    NotFound(String),
+    #[error("Chapter not found: {0}")]
+    ChapterNotFound(String),
+    #[error("Character not found: {0}")]
+    CharacterNotFound(String),
+    #[error("Invalid relationship: {0}")]
+    InvalidRelationship(String),
+    #[error("Note not found: {0}")]
+    NoteNotFound(String),
+    #[error("Invalid note category: {0}")]
+    InvalidCategory(String),
+    #[error("Timeline event not found: {0}")]
+    TimelineEventNotFound(String),
+    #[error("Ghost note not found: {0}")]
+    GhostNoteNotFound(String),
+    #[error("Plot thread not found: {0}")]
+    PlotThreadNotFound(String),
+    #[error("Smart collection not found: {0}")]
+    CollectionNotFound(String),
+    #[error("Invalid {kind} id: {value}")]
+    InvalidId { kind: &'static str, value: String },
+    #[error("Path is outside the project: {0}")]
+    PathOutsideProject(String),
+    #[error("Invalid export path: {0}")]
+    InvalidExportPath(String),
+    #[error("Invalid move: {0}")]
+    InvalidMove(String),
+    #[error("Invalid plugin at {path}: {message}")]
+    InvalidPlugin { path: String, message: String },
+    #[error("Plugin not found: {0}")]
+    PluginNotFound(String),
+    #[error("No plugin provides {kind} \"{id}\"")]
+    PluginContributionNotFound { kind: &'static str, id: String },
+    #[error("Plugin {plugin} lacks the {permission} permission")]
+    PluginPermissionDenied { plugin: String, permission: String },
+    #[error("Plugin {0} exceeded its execution budget")]
+    PluginTimeout(String),
+    #[error("Plugin {plugin} failed: {message}")]
+    PluginFailed { plugin: String, message: String },
+    #[error("Error in {script}{}: {message}", .line.map(|l| format!(" at line {}", l)).unwrap_or_default())]
+    ExportScript {
+        script: String,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+    #[error("{0} took too long and was stopped")]
+    ExportScriptTimeout(String),
+    #[error("Invalid setting {field}: {message}")]
+    InvalidSettings { field: String, message: String },
+    /// `span` is the byte range of the pattern at fault, when known.
+    #[error("Invalid search pattern: {message}")]
+    InvalidSearchPattern {
+        pattern: String,
+        message: String,
+        span: Option<(usize, usize)>,
+    },
+    #[error("Can't locate the app {dir} directory: {message}")]
+    AppDirUnavailable { dir: &'static str, message: String },
+}
+
+/// Name an `io::ErrorKind` the way the frontend matches on it, e.g.
+/// `not_found`, `permission_denied` or `storage_full`. Spelled out rather
+/// than derived from `Debug`, which makes no stability promise.
+fn io_kind(error: &io::Error) -> &'static str {
+    match error.kind() {
+        io::ErrorKind::NotFound => "not_found",
+        io::ErrorKind::PermissionDenied => "permission_denied",
+        io::ErrorKind::AlreadyExists => "already_exists",
+        io::ErrorKind::IsADirectory => "is_a_directory",
+        io::ErrorKind::NotADirectory => "not_a_directory",
+        io::ErrorKind::DirectoryNotEmpty => "directory_not_empty",
+        io::ErrorKind::ReadOnlyFilesystem => "read_only_filesystem",
+        io::ErrorKind::StorageFull => "storage_full",
+        io::ErrorKind::QuotaExceeded => "quota_exceeded",
+        io::ErrorKind::FileTooLarge => "file_too_large",
+        io::ErrorKind::ResourceBusy => "resource_busy",
+        io::ErrorKind::CrossesDevices => "crosses_devices",
+        io::ErrorKind::InvalidFilename => "invalid_filename",
+        io::ErrorKind::InvalidInput => "invalid_input",
+        io::ErrorKind::InvalidData => "invalid_data",
+        io::ErrorKind::UnexpectedEof => "unexpected_eof",
+        io::ErrorKind::TimedOut => "timed_out",
+        io::ErrorKind::Interrupted => "interrupted",
+        io::ErrorKind::WouldBlock => "would_block",
+        io::ErrorKind::BrokenPipe => "broken_pipe",
+        io::ErrorKind::ConnectionRefused => "connection_refused",
+        io::ErrorKind::ConnectionReset => "connection_reset",
+        io::ErrorKind::ConnectionAborted => "connection_aborted",
+        io::ErrorKind::NotConnected => "not_connected",
+        io::ErrorKind::AddrInUse => "addr_in_use",
+        io::ErrorKind::AddrNotAvailable => "addr_not_available",
+        io::ErrorKind::Unsupported => "unsupported",
+        io::ErrorKind::OutOfMemory => "out_of_memory",
+        _ => "other",
+    }
+}
+
+fn io_details(path: Option<&str>, error: &io::Error) -> Value {
+    json!({ "path": path, "kind": io_kind(error) })
+}
+
+fn json_details(path: Option<&str>, error: &serde_json::Error) -> Value {
+    json!({ "path": path, "line": error.line(), "column": error.column() })
+}
+
+impl ProjectError {
+    /// Stable identifier the frontend can branch on. Never change an
+    /// existing code; add a new one instead.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProjectError::Io(e) | ProjectError::FileIo { source: e, .. } => match e.kind() {
+                io::ErrorKind::NotFound => "file_not_found",
+                io::ErrorKind::PermissionDenied => "permission_denied",
+                io::ErrorKind::StorageFull => "storage_full",
+                _ => "io",
+            },
+            ProjectError::Json(_) | ProjectError::JsonFile { .. } => "invalid_json",
+            ProjectError::TomlSer(_) => "toml_serialize",
+            ProjectError::TomlDe(_) | ProjectError::TomlFile { .. } => "invalid_toml",
+            ProjectError::Chapter(e) => e.code(),
+            ProjectError::Archive(_) => "export_archive",
+            ProjectError::NotFound(_) => "project_not_found",
+            ProjectError::ChapterNotFound(_) => "chapter_not_found",
+            ProjectError::CharacterNotFound(_) => "character_not_found",
+            ProjectError::InvalidRelationship(_) => "invalid_relationship",
+            ProjectError::NoteNotFound(_) => "note_not_found",
+            ProjectError::InvalidCategory(_) => "invalid_category",
+            ProjectError::TimelineEventNotFound(_) => "timeline_event_not_found",
+            ProjectError::GhostNoteNotFound(_) => "ghost_note_not_found",
+            ProjectError::PlotThreadNotFound(_) => "plot_thread_not_found",
+            ProjectError::CollectionNotFound(_) => "collection_not_found",
+            ProjectError::InvalidId { .. } => "invalid_id",
+            ProjectError::PathOutsideProject(_) => "path_outside_project",
+            ProjectError::InvalidExportPath(_) => "invalid_export_path",
+            ProjectError::InvalidMove(_) => "invalid_move",
+            ProjectError::InvalidPlugin { .. } => "invalid_plugin",
+            ProjectError::PluginNotFound(_) => "plugin_not_found",
+            ProjectError::PluginContributionNotFound { .. } => "plugin_contribution_not_found",
+            ProjectError::PluginPermissionDenied { .. } => "plugin_permission_denied",
+            ProjectError::PluginTimeout(_) => "plugin_timeout",
+            ProjectError::PluginFailed { .. } => "plugin_failed",
+            ProjectError::ExportScript { .. } => "export_script",
+            ProjectError::ExportScriptTimeout(_) => "export_script_timeout",
+            ProjectError::InvalidSettings { .. } => "invalid_settings",
+            ProjectError::InvalidSearchPattern { .. } => "invalid_search_pattern",
+            ProjectError::AppDirUnavailable { .. } => "app_dir_unavailable",
+        }
+    }
+
+    /// Context for the error, such as the file involved or where a parse
+    /// failed. `null` when there is nothing to add to the message.
+    pub fn details(&self) -> Value {
+        match self {
+            ProjectError::Io(e) => io_details(None, e),
+            ProjectError::FileIo { path, source } => io_details(Some(path), source),
+            ProjectError::Json(e) => json_details(None, e),
+            ProjectError::JsonFile { path, source } => json_details(Some(path), source),
+            ProjectError::TomlFile { path, line, column, .. } => {
+                json!({ "path": path, "line": line, "column": column })
+            }
+            ProjectError::Chapter(e) => e.details(),
+            ProjectError::NotFound(path) => json!({ "path": path }),
+            ProjectError::ChapterNotFound(id) => json!({ "chapter_id": id }),
+            ProjectError::CharacterNotFound(id) => json!({ "character_id": id }),
+            ProjectError::NoteNotFound(id) => json!({ "note_id": id }),
+            ProjectError::InvalidCategory(category) => json!({ "category": category }),
+            ProjectError::TimelineEventNotFound(id) => json!({ "event_id": id }),
+            ProjectError::GhostNoteNotFound(id) => json!({ "note_id": id }),
+            ProjectError::PlotThreadNotFound(id) => json!({ "thread_id": id }),
+            ProjectError::CollectionNotFound(id) => json!({ "collection_id": id }),
+            ProjectError::InvalidId { kind, value } => json!({ "kind": kind, "value": value }),
+            ProjectError::PathOutsideProject(path) | ProjectError::InvalidExportPath(path) => {
+                json!({ "path": path })
+            }
+            ProjectError::InvalidPlugin { path, .. } => json!({ "path": path }),
+            ProjectError::PluginNotFound(id) | ProjectError::PluginTimeout(id) => {
+                json!({ "plugin_id": id })
+            }
+            ProjectError::PluginContributionNotFound { kind, id } => {
+                json!({ "kind": kind, "id": id })
+            }
+            ProjectError::PluginPermissionDenied { plugin, permission } => {
+                json!({ "plugin_id": plugin, "permission": permission })
+            }
+            ProjectError::PluginFailed { plugin, .. } => json!({ "plugin_id": plugin }),
+            ProjectError::ExportScript { script, line, column, .. } => {
+                json!({ "script": script, "line": line, "column": column })
+            }
+            ProjectError::ExportScriptTimeout(script) => json!({ "script": script }),
+            ProjectError::InvalidSettings { field, .. } => json!({ "field": field }),
+            ProjectError::InvalidSearchPattern { pattern, span, .. } => json!({
+                "pattern": pattern,
+                "span": span.map(|(start, end)| crate::search::MatchSpan::new(pattern, start, end)),
+            }),
+            ProjectError::AppDirUnavailable { dir, .. } => json!({ "dir": dir }),
+            ProjectError::TomlSer(_)
+            | ProjectError::TomlDe(_)
+            | ProjectError::Archive(_)
+            | ProjectError::InvalidRelationship(_)
+            | ProjectError::InvalidMove(_) => Value::Null,
+        }
+    }
+}
+
+impl serde::Serialize for ProjectError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("ProjectError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+/// Attach the path of the file being read or written to an error.
+pub trait WithPath<T> {
+    fn with_path(self, path: &Path) -> Result<T, ProjectError>;
+}
+
+impl<T> WithPath<T> for Result<T, io::Error> {
+    fn with_path(self, path: &Path) -> Result<T, ProjectError> {
+        self.map_err(|source| ProjectError::FileIo {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+}
+
+impl<T> WithPath<T> for Result<T, serde_json::Error> {
+    fn with_path(self, path: &Path) -> Result<T, ProjectError> {
+        self.map_err(|source| ProjectError::JsonFile {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+}
+
+/// Read and parse a JSON file, reporting the path and parse position on failure.
+pub fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, ProjectError> {
+    let raw = std::fs::read_to_string(path).with_path(path)?;
+    serde_json::from_str(&raw).with_path(path)
+}
+
+/// Read and parse a TOML file, reporting the path and parse position on failure.
+pub fn read_toml<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, ProjectError> {
+    let raw = std::fs::read_to_string(path).with_path(path)?;
+    toml::from_str(&raw).map_err(|e| {
+        let position = e.span().map(|span| {
+            let before = &raw[..span.start.min(raw.len())];
+            let line = before.matches('\n').count() + 1;
+            let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+            (line, column)
+        });
+        ProjectError::TomlFile {
+            path: path.display().to_string(),
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+            message: e.message().to_string(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_errors_serialize_with_a_stable_kind() {
+        let error = Err::<(), _>(io::Error::from(io::ErrorKind::NotFound))
+            .with_path(Path::new("chapters/missing.md"))
+            .unwrap_err();
+        let value = serde_json::to_value(&error).unwrap();
+
+        assert_eq!(value["code"], "file_not_found");
+        assert_eq!(value["details"]["kind"], "not_found");
+        assert_eq!(value["details"]["path"], "chapters/missing.md");
+    }
+}
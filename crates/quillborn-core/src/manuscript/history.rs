@@ -0,0 +1,429 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::chapter::Chapter;
+use super::error::{read_json, WithPath};
+use super::ids::ChapterId;
+use super::project::{ManuscriptStructure, Project, ProjectError};
+
+/// How many operations are kept on each stack.
+const MAX_OPERATIONS: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    AddChapter,
+    DeleteChapter,
+    RenameChapter,
+    MoveChapter,
+    ReorderChapters,
+    SetStatus,
+    SetTags,
+    ReplaceText,
+    RestoreSnapshot,
+}
+
+/// Planning files that deleting a chapter rewrites, to drop its corkboard
+/// cards and thread memberships.
+pub const PLANNING_FILES: &[&str] = &["corkboard.json", "plot-threads.json"];
+
+/// The manuscript tree plus the raw contents of the files an operation
+/// touches, keyed by path relative to the project root. `None` means the
+/// file did not exist.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Capture {
+    structure: ManuscriptStructure,
+    files: BTreeMap<String, Option<String>>,
+}
+
+impl Capture {
+    /// Record the structure and the files at `keys`. Operations list only
+    /// what they are about to write, so the log doesn't hold copies of the
+    /// whole manuscript.
+    pub fn take<K: Into<String>>(project: &Project, keys: impl IntoIterator<Item = K>) -> Capture {
+        let files = keys
+            .into_iter()
+            .map(|key| {
+                let key = key.into();
+                let raw = fs::read_to_string(project.path.join(&key)).ok();
+                (key, raw)
+            })
+            .collect();
+        Capture {
+            structure: project.structure.clone(),
+            files,
+        }
+    }
+
+    /// Record the structure, the given chapters' files and every chapter,
+    /// note or character sheet whose text contains `text`, ignoring case.
+    /// A rename captures the files that link to the old title this way.
+    pub fn take_mentioning(project: &Project, chapters: &[&ChapterId], text: &str) -> Capture {
+        let needle = text.to_lowercase();
+        let mut keys: Vec<String> = chapters.iter().map(|id| chapter_key(id)).collect();
+        let order = project.chapter_order();
+        let candidates = order.iter().map(chapter_key).chain(note_keys(project));
+        for key in candidates {
+            if keys.contains(&key) {
+                continue;
+            }
+            let mentions = fs::read_to_string(project.path.join(&key))
+                .is_ok_and(|raw| raw.to_lowercase().contains(&needle));
+            if mentions {
+                keys.push(key);
+            }
+        }
+        Capture::take(project, keys)
+    }
+}
+
+/// The capture key of a chapter's file.
+pub fn chapter_key(chapter_id: &ChapterId) -> String {
+    format!("chapters/{}.md", chapter_id)
+}
+
+/// The chapter a capture key refers to, if it is a chapter file.
+fn chapter_of(key: &str) -> Option<ChapterId> {
+    let id = key.strip_prefix("chapters/")?.strip_suffix(".md")?;
+    ChapterId::parse(id).ok()
+}
+
+/// `notes/<category>/<file>` for every note and character sheet.
+fn note_keys(project: &Project) -> Vec<String> {
+    let mut keys = Vec::new();
+    let Ok(categories) = fs::read_dir(project.path.join("notes")) else {
+        return keys;
+    };
+    for category in categories.flatten().filter(|e| e.path().is_dir()) {
+        let Ok(files) = fs::read_dir(category.path()) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let path = file.path();
+            if path.is_file() && matches!(path.extension().and_then(|e| e.to_str()), Some("md" | "json")) {
+                keys.push(format!(
+                    "notes/{}/{}",
+                    category.file_name().to_string_lossy(),
+                    file.file_name().to_string_lossy()
+                ));
+            }
+        }
+    }
+    keys
+}
+
+/// One undoable mutation of the manuscript: the state before and after it.
+/// Of the captured files, only those the operation changed are kept.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Operation {
+    pub kind: OperationKind,
+    pub description: String,
+    pub at: DateTime<Utc>,
+    before: Capture,
+    after: Capture,
+}
+
+impl Operation {
+    fn new(kind: OperationKind, description: String, mut before: Capture, mut after: Capture) -> Self {
+        let ids: Vec<String> = before.files.keys().chain(after.files.keys()).cloned().collect();
+        for id in ids {
+            let old = before.files.remove(&id).flatten();
+            let new = after.files.remove(&id).flatten();
+            if old != new {
+                before.files.insert(id.clone(), old);
+                after.files.insert(id, new);
+            }
+        }
+        Operation {
+            kind,
+            description,
+            at: Utc::now(),
+            before,
+            after,
+        }
+    }
+
+    fn undo(&self, project: &mut Project) -> Result<(), ProjectError> {
+        restore(project, &self.before, &self.after)
+    }
+
+    fn redo(&self, project: &mut Project) -> Result<(), ProjectError> {
+        restore(project, &self.after, &self.before)
+    }
+}
+
+/// Bring the project back to `target`. A file is only overwritten or
+/// deleted when it still matches `current`; if it was edited since, its
+/// contents are kept,
+/// and for a chapter only the title and status are synced with the
+/// restored tree.
+fn restore(project: &mut Project, target: &Capture, current: &Capture) -> Result<(), ProjectError> {
+    project.structure = target.structure.clone();
+
+    for (key, wanted) in &target.files {
+        let path = project.resolve(Path::new(key))?;
+        let on_disk = fs::read_to_string(&path).ok();
+        let untouched = current.files.get(key).is_some_and(|expected| *expected == on_disk);
+
+        match (wanted, on_disk) {
+            (None, Some(_)) if untouched => fs::remove_file(&path).with_path(&path)?,
+            // Written to since, e.g. text typed into a chapter whose
+            // creation is being undone: keep it rather than lose the work.
+            (None, Some(_)) => {}
+            (Some(raw), None) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).with_path(parent)?;
+                }
+                fs::write(&path, raw).with_path(&path)?
+            }
+            (Some(raw), Some(_)) if untouched => fs::write(&path, raw).with_path(&path)?,
+            (Some(_), Some(_)) => {
+                let Some(node) = chapter_of(key).and_then(|id| project.structure.nodes.get(id.as_str())) else {
+                    continue;
+                };
+                let mut chapter = Chapter::from_file(&path)?;
+                chapter.title = node.title.clone();
+                chapter.status = node.status.as_str().to_string();
+                project.write_chapter(&chapter)?;
+            }
+            (None, None) => {}
+        }
+    }
+
+    project.metadata.modified_at = Utc::now();
+    project.save()
+}
+
+/// What the frontend needs to label the undo and redo actions.
+#[derive(Debug, Serialize, Clone)]
+pub struct OperationSummary {
+    pub kind: OperationKind,
+    pub description: String,
+    pub at: DateTime<Utc>,
+}
+
+impl From<&Operation> for OperationSummary {
+    fn from(op: &Operation) -> Self {
+        OperationSummary {
+            kind: op.kind,
+            description: op.description.clone(),
+            at: op.at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryState {
+    /// Most recent first.
+    pub undo: Vec<OperationSummary>,
+    pub redo: Vec<OperationSummary>,
+}
+
+/// Undo and redo stacks for structural changes, kept in
+/// `history/history.json` so they survive a restart.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct History {
+    #[serde(default)]
+    undo: Vec<Operation>,
+    #[serde(default)]
+    redo: Vec<Operation>,
+}
+
+fn history_path(project: &Project) -> PathBuf {
+    project.path.join("history").join("history.json")
+}
+
+impl History {
+    pub fn load(project: &Project) -> Result<Self, ProjectError> {
+        let path = history_path(project);
+        if !path.exists() {
+            return Ok(History::default());
+        }
+        read_json(&path)
+    }
+
+    pub fn save(&self, project: &Project) -> Result<(), ProjectError> {
+        let path = history_path(project);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_path(dir)?;
+        }
+        fs::write(&path, serde_json::to_string(self)?).with_path(&path)?;
+        Ok(())
+    }
+
+    pub fn state(&self) -> HistoryState {
+        HistoryState {
+            undo: self.undo.iter().rev().map(OperationSummary::from).collect(),
+            redo: self.redo.iter().rev().map(OperationSummary::from).collect(),
+        }
+    }
+
+    /// Log a finished operation. Any redo history is discarded. The
+    /// operation is already on disk, so a history that can't be read or
+    /// written is reported and otherwise ignored.
+    pub fn record(project: &Project, kind: OperationKind, description: String, before: Capture) {
+        let keys: Vec<&String> = before.files.keys().collect();
+        let after = Capture::take(project, keys);
+        let op = Operation::new(kind, description, before, after);
+        let description = op.description.clone();
+        let recorded = History::load(project).and_then(|mut history| {
+            history.undo.push(op);
+            if history.undo.len() > MAX_OPERATIONS {
+                history.undo.remove(0);
+            }
+            history.redo.clear();
+            history.save(project)
+        });
+        if let Err(e) = recorded {
+            eprintln!("[history] could not record \"{}\": {}", description, e);
+        }
+    }
+
+    /// Revert the most recent operation. Returns `None` when there is
+    /// nothing to undo.
+    pub fn undo(project: &mut Project) -> Result<Option<OperationSummary>, ProjectError> {
+        let mut history = History::load(project)?;
+        let Some(op) = history.undo.pop() else {
+            return Ok(None);
+        };
+        op.undo(project)?;
+        let summary = OperationSummary::from(&op);
+        history.redo.push(op);
+        history.save(project)?;
+        Ok(Some(summary))
+    }
+
+    /// Re-apply the most recently undone operation.
+    pub fn redo(project: &mut Project) -> Result<Option<OperationSummary>, ProjectError> {
+        let mut history = History::load(project)?;
+        let Some(op) = history.redo.pop() else {
+            return Ok(None);
+        };
+        op.redo(project)?;
+        let summary = OperationSummary::from(&op);
+        history.undo.push(op);
+        history.save(project)?;
+        Ok(Some(summary))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manuscript::project::ChapterStatus;
+    use crate::planning::corkboard::{Corkboard, CorkboardCard};
+    use crate::planning::notes;
+    use crate::planning::threads::{PlotThread, PlotThreads};
+
+    #[test]
+    fn undoing_a_delete_restores_the_chapter_and_its_planning_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "History", "Author").unwrap();
+        let chapter = project.add_chapter("Storm", None).unwrap();
+        let chapter_id = ChapterId::parse(&chapter.id).unwrap();
+        project.update_chapter_content(&chapter_id, "Thunder rolled in.").unwrap();
+
+        let mut corkboard = Corkboard::load(&project).unwrap();
+        corkboard.upsert_card(CorkboardCard {
+            id: "card".to_string(),
+            chapter_id: chapter.id.clone(),
+            x: 1.0,
+            y: 2.0,
+            pinned: false,
+            label: String::new(),
+            color: String::new(),
+        });
+        corkboard.save(&project).unwrap();
+        let thread = PlotThread::new("Weather", "#336699");
+        let mut threads = PlotThreads::load(&project).unwrap();
+        threads.upsert_thread(thread.clone());
+        threads.assign_chapter(&project, &thread.id, &chapter_id).unwrap();
+        threads.save(&project).unwrap();
+
+        project.delete_chapter(&chapter_id).unwrap();
+        assert!(Corkboard::load(&project).unwrap().cards.is_empty());
+
+        History::undo(&mut project).unwrap().unwrap();
+        assert_eq!(project.load_chapter(&chapter_id).unwrap().content, "Thunder rolled in.");
+        assert_eq!(Corkboard::load(&project).unwrap().cards.len(), 1);
+        assert_eq!(PlotThreads::load(&project).unwrap().threads[0].chapters, vec![chapter.id.clone()]);
+
+        History::redo(&mut project).unwrap().unwrap();
+        assert!(!project.chapter_path(&chapter_id).exists());
+        assert!(Corkboard::load(&project).unwrap().cards.is_empty());
+        assert!(PlotThreads::load(&project).unwrap().threads[0].chapters.is_empty());
+    }
+
+    #[test]
+    fn undoing_a_rename_reverts_rewritten_wikilinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "History", "Author").unwrap();
+        let chapter = project.add_chapter("Storm", None).unwrap();
+        let chapter_id = ChapterId::parse(&chapter.id).unwrap();
+        let note = notes::create_note(&project, "Weather", "scratch").unwrap();
+        notes::update_note(&project, &note.id, "Begins in [[Storm]].", None).unwrap();
+
+        project.rename_chapter(&chapter_id, "Tempest").unwrap();
+        assert_eq!(notes::load_note(&project, &note.id).unwrap().content, "Begins in [[Tempest]].");
+
+        History::undo(&mut project).unwrap().unwrap();
+        assert_eq!(project.structure.nodes[chapter_id.as_str()].title, "Storm");
+        assert_eq!(notes::load_note(&project, &note.id).unwrap().content, "Begins in [[Storm]].");
+
+        History::redo(&mut project).unwrap().unwrap();
+        assert_eq!(project.load_chapter(&chapter_id).unwrap().title, "Tempest");
+        assert_eq!(notes::load_note(&project, &note.id).unwrap().content, "Begins in [[Tempest]].");
+        assert!(project.path.join("history").join("history.json").exists());
+    }
+
+    #[test]
+    fn undoing_an_add_keeps_text_typed_into_the_chapter() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "History", "Author").unwrap();
+        let chapter = project.add_chapter("One", None).unwrap();
+        let chapter_id = ChapterId::parse(&chapter.id).unwrap();
+        project.update_chapter_content(&chapter_id, "Hours of typed prose.").unwrap();
+
+        History::undo(&mut project).unwrap().unwrap();
+        assert!(!project.structure.nodes.contains_key(chapter_id.as_str()));
+        assert!(project.chapter_path(&chapter_id).exists());
+
+        History::redo(&mut project).unwrap().unwrap();
+        let chapter = project.load_chapter(&chapter_id).unwrap();
+        assert_eq!(chapter.content, "Hours of typed prose.");
+        assert_eq!(chapter.title, "One");
+    }
+
+    #[test]
+    fn operations_only_log_the_files_they_touch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "History", "Author").unwrap();
+        let long = project.add_chapter("Long", None).unwrap();
+        let long_id = ChapterId::parse(&long.id).unwrap();
+        project.update_chapter_content(&long_id, "An unmistakable sentence.").unwrap();
+
+        let short = project.add_chapter("Short", None).unwrap();
+        let short_id = ChapterId::parse(&short.id).unwrap();
+        project.set_chapter_status(&short_id, ChapterStatus::Revised).unwrap();
+        project.delete_chapter(&short_id).unwrap();
+
+        let raw = fs::read_to_string(history_path(&project)).unwrap();
+        assert!(!raw.contains("unmistakable"));
+    }
+
+    #[test]
+    fn an_unreadable_history_does_not_fail_the_operation() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "History", "Author").unwrap();
+        let path = history_path(&project);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{ not json").unwrap();
+
+        let chapter = project.add_chapter("One", None).unwrap();
+        assert!(project.structure.nodes.contains_key(&chapter.id));
+        assert!(History::load(&project).is_err());
+    }
+}
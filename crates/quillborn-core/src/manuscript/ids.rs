@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+use super::project::ProjectError;
+
+/// Declares a validated identifier that must be a UUID. These ids end up in
+/// file names, so anything else (such as `../../x`) is rejected up front.
+macro_rules! uuid_id {
+    ($(#[$meta:meta])* $name:ident, $kind:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        #[serde(try_from = "String", into = "String")]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new() -> Self {
+                $name(Uuid::new_v4().to_string())
+            }
+
+            pub fn parse(value: &str) -> Result<Self, ProjectError> {
+                Uuid::parse_str(value)
+                    .map(|uuid| $name(uuid.hyphenated().to_string()))
+                    .map_err(|_| ProjectError::InvalidId {
+                        kind: $kind,
+                        value: value.to_string(),
+                    })
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = ProjectError;
+
+            fn try_from(value: String) -> Result<Self, Self::Error> {
+                Self::parse(&value)
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> String {
+                id.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<String> for $name {
+            fn eq(&self, other: &String) -> bool {
+                &self.0 == other
+            }
+        }
+    };
+}
+
+uuid_id!(
+    /// Id of a node in the manuscript tree; chapter files are named after it.
+    ChapterId,
+    "chapter"
+);
+uuid_id!(
+    /// Id of a character sheet under `notes/characters`.
+    CharacterId,
+    "character"
+);
+uuid_id!(
+    /// Id of a ghost note under `ghost-notes`.
+    GhostNoteId,
+    "ghost note"
+);
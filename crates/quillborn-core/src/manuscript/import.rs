@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+use super::chapter::Chapter;
+use super::ids::ChapterId;
+use super::project::{Project, ProjectError};
+
+/// How a text file is cut into chapters, matching the import wizard.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitMethod {
+    /// Start a new chapter at every `#` or `##` heading.
+    Heading,
+    /// Split at scene break lines such as `***`, `---` or `###`.
+    Break,
+    /// Import the whole file as one chapter.
+    Single,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportedChapter {
+    pub title: String,
+    pub content: String,
+}
+
+fn heading_title(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("##").or_else(|| line.strip_prefix('#'))?;
+    let title = rest.trim();
+    (rest.starts_with(char::is_whitespace) && !title.is_empty()).then_some(title)
+}
+
+fn is_scene_break(line: &str) -> bool {
+    let line = line.trim_end();
+    let mut chars = line.chars();
+    match chars.next() {
+        Some(marker @ ('*' | '-' | '#')) => line.len() >= 3 && chars.all(|c| c == marker),
+        _ => false,
+    }
+}
+
+pub fn split_chapters(text: &str, method: SplitMethod) -> Vec<ImportedChapter> {
+    let mut chapters = Vec::new();
+
+    match method {
+        SplitMethod::Heading => {
+            let mut title = "Untitled".to_string();
+            let mut content = String::new();
+            for line in text.lines() {
+                if let Some(heading) = heading_title(line) {
+                    if !content.trim().is_empty() {
+                        chapters.push(ImportedChapter {
+                            title: title.clone(),
+                            content: content.trim().to_string(),
+                        });
+                    }
+                    title = heading.to_string();
+                    content.clear();
+                } else {
+                    content.push_str(line);
+                    content.push('\n');
+                }
+            }
+            if !content.trim().is_empty() {
+                chapters.push(ImportedChapter {
+                    title,
+                    content: content.trim().to_string(),
+                });
+            }
+        }
+        SplitMethod::Break => {
+            let mut content = String::new();
+            let push = |content: &mut String, chapters: &mut Vec<ImportedChapter>| {
+                if !content.trim().is_empty() {
+                    chapters.push(ImportedChapter {
+                        title: format!("Chapter {}", chapters.len() + 1),
+                        content: content.trim().to_string(),
+                    });
+                }
+                content.clear();
+            };
+            for line in text.lines() {
+                if is_scene_break(line) {
+                    push(&mut content, &mut chapters);
+                } else {
+                    content.push_str(line);
+                    content.push('\n');
+                }
+            }
+            push(&mut content, &mut chapters);
+        }
+        SplitMethod::Single => {}
+    }
+
+    if chapters.is_empty() {
+        chapters.push(ImportedChapter {
+            title: "Imported Chapter".to_string(),
+            content: text.to_string(),
+        });
+    }
+    chapters
+}
+
+/// Split `text` and append each piece to the manuscript as a new chapter.
+pub fn import_text(
+    project: &mut Project,
+    text: &str,
+    method: SplitMethod,
+    parent_id: Option<&ChapterId>,
+) -> Result<Vec<Chapter>, ProjectError> {
+    let mut created = Vec::new();
+    for piece in split_chapters(text, method) {
+        let chapter = project.add_chapter(&piece.title, parent_id)?;
+        let chapter_id = ChapterId::parse(&chapter.id)?;
+        created.push(project.update_chapter_content(&chapter_id, &piece.content)?);
+    }
+    Ok(created)
+}
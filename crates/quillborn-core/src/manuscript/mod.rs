@@ -0,0 +1,7 @@
+pub mod chapter;
+pub mod error;
+pub mod history;
+pub mod import;
+pub mod ids;
+pub mod paths;
+pub mod project;
@@ -0,0 +1,157 @@
+use std::path::{Component, Path, PathBuf};
+
+use super::project::ProjectError;
+
+/// File extensions accepted for each export format.
+pub const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown"];
+pub const TEXT_EXTENSIONS: &[&str] = &["txt"];
+pub const HTML_EXTENSIONS: &[&str] = &["html", "htm"];
+pub const LATEX_EXTENSIONS: &[&str] = &["tex"];
+pub const EPUB_EXTENSIONS: &[&str] = &["epub"];
+
+fn outside(path: &Path) -> ProjectError {
+    ProjectError::PathOutsideProject(path.display().to_string())
+}
+
+/// Canonicalize the deepest part of `path` that exists, so symlinks inside
+/// the project can't be used to point outside it.
+fn canonical_existing_ancestor(path: &Path) -> Result<PathBuf, ProjectError> {
+    let mut existing = path;
+    while !existing.exists() {
+        existing = existing.parent().ok_or_else(|| outside(path))?;
+    }
+    Ok(existing.canonicalize()?)
+}
+
+/// Resolve a project-relative path, rejecting absolute paths, `..`
+/// components and symlinks that would lead outside the project root.
+pub fn resolve_in_project(root: &Path, relative: &Path) -> Result<PathBuf, ProjectError> {
+    let mut resolved = root.to_path_buf();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(outside(relative));
+            }
+        }
+    }
+
+    let canonical_root = root.canonicalize()?;
+    if !canonical_existing_ancestor(&resolved)?.starts_with(&canonical_root) {
+        return Err(outside(relative));
+    }
+    Ok(resolved)
+}
+
+/// Resolve where an export may be written. Relative paths land in the
+/// project's `exports/` directory. Absolute paths must point into `exports/`
+/// or straight into one of `allowed_dirs`, the directories the user picked
+/// in a save dialog or named on the command line. Either way the file must
+/// carry one of the format's extensions.
+pub fn resolve_export_path(
+    root: &Path,
+    output_path: &str,
+    extensions: &[&str],
+    allowed_dirs: &[PathBuf],
+) -> Result<PathBuf, ProjectError> {
+    let requested = Path::new(output_path);
+    let extension_ok = requested
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.contains(&e.to_lowercase().as_str()));
+    if !extension_ok {
+        return Err(ProjectError::InvalidExportPath(format!(
+            "{} (expected .{})",
+            output_path,
+            extensions.join(" or .")
+        )));
+    }
+
+    if requested.is_relative() {
+        let resolved = resolve_in_project(root, &Path::new("exports").join(requested))?;
+        if let Some(parent) = resolved.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        return Ok(resolved);
+    }
+
+    let parent = requested
+        .parent()
+        .filter(|p| p.is_dir())
+        .ok_or_else(|| ProjectError::InvalidExportPath(output_path.to_string()))?;
+    if requested.is_dir() {
+        return Err(ProjectError::InvalidExportPath(output_path.to_string()));
+    }
+
+    let canonical_root = root.canonicalize()?;
+    let canonical_parent = parent.canonicalize()?;
+    let exports = canonical_root.join("exports");
+    if canonical_parent.starts_with(&canonical_root) && !canonical_parent.starts_with(&exports) {
+        return Err(ProjectError::InvalidExportPath(format!(
+            "{} would overwrite project files",
+            output_path
+        )));
+    }
+
+    // Only the chosen directory itself, not everything below it.
+    let allowed = canonical_parent.starts_with(&exports)
+        || allowed_dirs
+            .iter()
+            .any(|dir| dir.canonicalize().is_ok_and(|dir| dir == canonical_parent));
+    if !allowed {
+        return Err(outside(requested));
+    }
+
+    Ok(canonical_parent.join(requested.file_name().unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(root: &Path, output: &str, allowed: &[PathBuf]) -> Result<PathBuf, ProjectError> {
+        resolve_export_path(root, output, MARKDOWN_EXTENSIONS, allowed)
+    }
+
+    #[test]
+    fn absolute_exports_need_an_allowed_directory() {
+        let project = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(project.path().join("exports")).unwrap();
+        let target = elsewhere.path().join("book.md");
+        let target = target.to_str().unwrap();
+
+        assert!(matches!(
+            export(project.path(), target, &[]),
+            Err(ProjectError::PathOutsideProject(_))
+        ));
+        assert!(export(project.path(), target, &[elsewhere.path().to_path_buf()]).is_ok());
+
+        let exports = project.path().join("exports").join("book.md");
+        assert!(export(project.path(), exports.to_str().unwrap(), &[]).is_ok());
+        assert!(export(project.path(), "drafts/book.md", &[]).is_ok());
+    }
+
+    #[test]
+    fn allowed_directories_do_not_cover_their_subdirectories() {
+        let project = tempfile::tempdir().unwrap();
+        let allowed = tempfile::tempdir().unwrap();
+        std::fs::create_dir(allowed.path().join("nested")).unwrap();
+        let target = allowed.path().join("nested").join("book.md");
+
+        assert!(matches!(
+            export(project.path(), target.to_str().unwrap(), &[allowed.path().to_path_buf()]),
+            Err(ProjectError::PathOutsideProject(_))
+        ));
+    }
+
+    #[test]
+    fn relative_exports_cannot_climb_out_of_exports() {
+        let project = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            export(project.path(), "../book.md", &[]),
+            Err(ProjectError::PathOutsideProject(_))
+        ));
+    }
+}
@@ -0,0 +1,662 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use super::chapter::Chapter;
+use super::error::{read_json, read_toml, WithPath};
+use super::history::{chapter_key, Capture, History, OperationKind, PLANNING_FILES};
+pub use super::error::ProjectError;
+use super::ids::ChapterId;
+use super::paths;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectMetadata {
+    pub title: String,
+    pub author: String,
+    #[serde(default)]
+    pub genre: String,
+    #[serde(default)]
+    pub word_count_target: Option<u64>,
+    #[serde(default)]
+    pub deadline: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+    /// Per-project overrides of application settings, as a partial copy of
+    /// the `settings.toml` layout. See `settings::Settings::with_overrides`.
+    #[serde(default, skip_serializing_if = "toml::Table::is_empty")]
+    pub settings: toml::Table,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ManuscriptNode {
+    pub id: String,
+    pub title: String,
+    pub node_type: NodeType,
+    pub children: Vec<String>,
+    #[serde(default)]
+    pub status: ChapterStatus,
+    #[serde(default)]
+    pub mood: Option<String>,
+    #[serde(default)]
+    pub pov: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub word_count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeType {
+    Book,
+    Part,
+    Chapter,
+    Scene,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChapterStatus {
+    #[default]
+    Draft,
+    Revised,
+    Final,
+    Trash,
+}
+
+impl ChapterStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChapterStatus::Draft => "draft",
+            ChapterStatus::Revised => "revised",
+            ChapterStatus::Final => "final",
+            ChapterStatus::Trash => "trash",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ManuscriptStructure {
+    pub root: String,
+    pub nodes: HashMap<String, ManuscriptNode>,
+    pub order: Vec<String>,
+}
+
+impl ManuscriptStructure {
+    /// Node ids become file names, so a manuscript.json from an untrusted
+    /// source must not smuggle in anything but UUIDs.
+    fn validate_ids(&self) -> Result<(), ProjectError> {
+        ChapterId::parse(&self.root)?;
+        for (id, node) in &self.nodes {
+            ChapterId::parse(id)?;
+            for child in &node.children {
+                ChapterId::parse(child)?;
+            }
+        }
+        for id in &self.order {
+            ChapterId::parse(id)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Project {
+    #[serde(skip)]
+    pub path: PathBuf,
+    pub metadata: ProjectMetadata,
+    pub structure: ManuscriptStructure,
+    /// Set by [`Project::open_read_only`]. Caches such as the search index
+    /// and style diagnostics are then worked out in memory and never
+    /// written back.
+    #[serde(skip)]
+    pub read_only: bool,
+}
+
+/// A file in `snapshots/`. Manual snapshots hold only the structure and
+/// metadata; snapshots taken before a replace also keep the text of every
+/// chapter it touched, keyed by chapter id.
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    timestamp: DateTime<Utc>,
+    name: String,
+    structure: ManuscriptStructure,
+    metadata: ProjectMetadata,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    chapters: BTreeMap<String, String>,
+}
+
+/// What the frontend and the automation API get back when they open a
+/// project or change its structure.
+#[derive(Debug, Serialize, Clone)]
+pub struct ProjectState {
+    pub path: String,
+    pub metadata: ProjectMetadata,
+    pub structure: ManuscriptStructure,
+    pub total_word_count: u64,
+}
+
+impl From<&Project> for ProjectState {
+    fn from(project: &Project) -> Self {
+        ProjectState {
+            path: project.path.display().to_string(),
+            metadata: project.metadata.clone(),
+            structure: project.structure.clone(),
+            total_word_count: project.total_word_count(),
+        }
+    }
+}
+
+impl Project {
+    pub fn create(dir: &Path, title: &str, author: &str) -> Result<Self, ProjectError> {
+        let project_dir = dir.join(format!("{}.qb", sanitize_filename(title)));
+        fs::create_dir_all(&project_dir)?;
+
+        // Create subdirectories
+        for sub in &[
+            "chapters",
+            "snapshots",
+            "history",
+            "notes/characters",
+            "notes/locations",
+            "notes/worldbuilding",
+            "notes/scratch",
+            "fonts",
+            "sounds",
+            "ghost-notes",
+            "exports",
+        ] {
+            fs::create_dir_all(project_dir.join(sub))?;
+        }
+
+        let root_id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+
+        let metadata = ProjectMetadata {
+            title: title.to_string(),
+            author: author.to_string(),
+            genre: String::new(),
+            word_count_target: None,
+            deadline: None,
+            created_at: now,
+            modified_at: now,
+            settings: toml::Table::new(),
+        };
+
+        let root_node = ManuscriptNode {
+            id: root_id.clone(),
+            title: title.to_string(),
+            node_type: NodeType::Book,
+            children: Vec::new(),
+            status: ChapterStatus::Draft,
+            mood: None,
+            pov: None,
+            tags: Vec::new(),
+            word_count: 0,
+        };
+
+        let mut nodes = HashMap::new();
+        nodes.insert(root_id.clone(), root_node);
+
+        let structure = ManuscriptStructure {
+            root: root_id,
+            nodes,
+            order: Vec::new(),
+        };
+
+        let project = Project {
+            path: project_dir,
+            metadata,
+            structure,
+            read_only: false,
+        };
+
+        project.save()?;
+        Ok(project)
+    }
+
+    pub fn open(project_dir: &Path) -> Result<Self, ProjectError> {
+        let manuscript_path = project_dir.join("manuscript.json");
+        let metadata_path = project_dir.join("metadata.toml");
+
+        if !manuscript_path.exists() {
+            return Err(ProjectError::NotFound(
+                manuscript_path.display().to_string(),
+            ));
+        }
+
+        let structure: ManuscriptStructure = read_json(&manuscript_path)?;
+        structure.validate_ids()?;
+
+        let metadata: ProjectMetadata = if metadata_path.exists() {
+            read_toml(&metadata_path)?
+        } else {
+            let now = Utc::now();
+            ProjectMetadata {
+                title: "Untitled".to_string(),
+                author: String::new(),
+                genre: String::new(),
+                word_count_target: None,
+                deadline: None,
+                created_at: now,
+                modified_at: now,
+                settings: toml::Table::new(),
+            }
+        };
+
+        Ok(Project {
+            path: project_dir.to_path_buf(),
+            metadata,
+            structure,
+            read_only: false,
+        })
+    }
+
+    /// Open a project for reading only, e.g. from media that can't be
+    /// written to. Lookups that normally refresh a cache on disk leave the
+    /// project directory untouched.
+    pub fn open_read_only(project_dir: &Path) -> Result<Self, ProjectError> {
+        Ok(Project {
+            read_only: true,
+            ..Project::open(project_dir)?
+        })
+    }
+
+    pub fn save(&self) -> Result<(), ProjectError> {
+        let manuscript_path = self.path.join("manuscript.json");
+        let manuscript_json = serde_json::to_string_pretty(&self.structure)?;
+        fs::write(&manuscript_path, manuscript_json).with_path(&manuscript_path)?;
+
+        let metadata_path = self.path.join("metadata.toml");
+        let metadata_toml = toml::to_string_pretty(&self.metadata)?;
+        fs::write(&metadata_path, metadata_toml).with_path(&metadata_path)?;
+
+        Ok(())
+    }
+
+    /// Path of a chapter's markdown file. Safe to build from the id alone
+    /// because a `ChapterId` is always a UUID.
+    pub fn chapter_path(&self, chapter_id: &ChapterId) -> PathBuf {
+        self.path.join("chapters").join(format!("{}.md", chapter_id))
+    }
+
+    /// Resolve a path relative to the project root, refusing anything that
+    /// would escape it.
+    pub fn resolve(&self, relative: &Path) -> Result<PathBuf, ProjectError> {
+        paths::resolve_in_project(&self.path, relative)
+    }
+
+    pub fn load_chapter(&self, chapter_id: &ChapterId) -> Result<Chapter, ProjectError> {
+        let chapter_path = self.chapter_path(chapter_id);
+        if !chapter_path.exists() {
+            return Err(ProjectError::ChapterNotFound(chapter_id.to_string()));
+        }
+        Ok(Chapter::from_file(&chapter_path)?)
+    }
+
+    pub fn write_chapter(&self, chapter: &Chapter) -> Result<(), ProjectError> {
+        let chapter_id = ChapterId::parse(&chapter.id)?;
+        let chapter_path = self.chapter_path(&chapter_id);
+        fs::write(&chapter_path, chapter.to_markdown()).with_path(&chapter_path)?;
+        Ok(())
+    }
+
+    /// Replace a chapter's text and keep its word count, the mention and
+    /// search indexes and its style diagnostics in sync.
+    pub fn update_chapter_content(&mut self, chapter_id: &ChapterId, content: &str) -> Result<Chapter, ProjectError> {
+        let mut chapter = self.load_chapter(chapter_id)?;
+        chapter.update_content(content);
+        self.write_chapter(&chapter)?;
+        if let Some(node) = self.structure.nodes.get_mut(chapter_id.as_str()) {
+            node.word_count = chapter.word_count;
+        }
+        self.save()?;
+
+        // The caches notice stale entries on their next read, so a failed
+        // refresh here must not fail an edit that is already on disk.
+        let _ = crate::planning::mentions::update_chapter_mentions(self, &chapter);
+        let _ = crate::search::SearchIndex::update_chapter(self, &chapter);
+        let _ = crate::analysis::update_chapter(self, &chapter);
+        Ok(chapter)
+    }
+
+    pub fn add_chapter(&mut self, title: &str, parent_id: Option<&ChapterId>) -> Result<Chapter, ProjectError> {
+        let chapter = Chapter::new(title);
+        let chapter_id = chapter.id.clone();
+        let before = Capture::take(self, [format!("chapters/{}.md", chapter_id)]);
+
+        let node = ManuscriptNode {
+            id: chapter_id.clone(),
+            title: title.to_string(),
+            node_type: NodeType::Chapter,
+            children: Vec::new(),
+            status: ChapterStatus::Draft,
+            mood: None,
+            pov: None,
+            tags: Vec::new(),
+            word_count: 0,
+        };
+
+        self.structure.nodes.insert(chapter_id.clone(), node);
+        self.structure.order.push(chapter_id.clone());
+
+        let parent = parent_id.map_or(self.structure.root.as_str(), |p| p.as_str());
+        if let Some(parent_node) = self.structure.nodes.get_mut(parent) {
+            parent_node.children.push(chapter_id.clone());
+        }
+
+        // Write chapter file
+        self.write_chapter(&chapter)?;
+
+        self.metadata.modified_at = Utc::now();
+        self.save()?;
+        History::record(self, OperationKind::AddChapter, format!("Add \"{}\"", title), before);
+
+        Ok(chapter)
+    }
+
+    pub fn delete_chapter(&mut self, chapter_id: &ChapterId) -> Result<(), ProjectError> {
+        let before = Capture::take(
+            self,
+            PLANNING_FILES.iter().map(|key| key.to_string()).chain([chapter_key(chapter_id)]),
+        );
+        let title = self.title_of(chapter_id);
+
+        // Remove from parent's children
+        for node in self.structure.nodes.values_mut() {
+            node.children.retain(|c| chapter_id != c);
+        }
+
+        // Remove from order
+        self.structure.order.retain(|c| chapter_id != c);
+
+        // Remove chapter file
+        if self.structure.nodes.contains_key(chapter_id.as_str()) {
+            let chapter_path = self.chapter_path(chapter_id);
+            if chapter_path.exists() {
+                fs::remove_file(&chapter_path)?;
+            }
+        }
+
+        self.structure.nodes.remove(chapter_id.as_str());
+        self.metadata.modified_at = Utc::now();
+        self.save()?;
+
+        // Cards and thread memberships can't outlive their chapter
+        crate::planning::corkboard::Corkboard::forget_chapter(self, chapter_id.as_str())?;
+        crate::planning::threads::PlotThreads::forget_chapter(self, chapter_id.as_str())?;
+
+        History::record(self, OperationKind::DeleteChapter, format!("Delete \"{}\"", title), before);
+        Ok(())
+    }
+
+    pub fn rename_chapter(&mut self, chapter_id: &ChapterId, new_title: &str) -> Result<(), ProjectError> {
+        let Some(old_title) = self.structure.nodes.get(chapter_id.as_str()).map(|n| n.title.clone()) else {
+            return Err(ProjectError::ChapterNotFound(chapter_id.to_string()));
+        };
+        // Files linking to the old title may be rewritten below.
+        let before = Capture::take_mentioning(self, &[chapter_id], old_title.trim());
+        if let Some(node) = self.structure.nodes.get_mut(chapter_id.as_str()) {
+            node.title = new_title.to_string();
+        }
+
+        // Update chapter file frontmatter
+        if self.chapter_path(chapter_id).exists() {
+            let mut chapter = self.load_chapter(chapter_id)?;
+            chapter.title = new_title.to_string();
+            self.write_chapter(&chapter)?;
+        }
+
+        self.metadata.modified_at = Utc::now();
+        self.save()?;
+
+        // Keep [[wikilinks]] to this chapter pointing at it
+        crate::planning::wiki::rewrite_links(self, chapter_id.as_str(), &old_title, new_title)?;
+
+        History::record(
+            self,
+            OperationKind::RenameChapter,
+            format!("Rename \"{}\" to \"{}\"", old_title, new_title),
+            before,
+        );
+        Ok(())
+    }
+
+    pub fn set_chapter_status(&mut self, chapter_id: &ChapterId, status: ChapterStatus) -> Result<(), ProjectError> {
+        let before = Capture::take(self, [chapter_key(chapter_id)]);
+        if let Some(node) = self.structure.nodes.get_mut(chapter_id.as_str()) {
+            node.status = status.clone();
+        } else {
+            return Err(ProjectError::ChapterNotFound(chapter_id.to_string()));
+        }
+
+        // Keep the chapter file frontmatter in sync
+        if self.chapter_path(chapter_id).exists() {
+            let mut chapter = self.load_chapter(chapter_id)?;
+            chapter.status = status.as_str().to_string();
+            self.write_chapter(&chapter)?;
+        }
+
+        self.metadata.modified_at = Utc::now();
+        self.save()?;
+
+        History::record(
+            self,
+            OperationKind::SetStatus,
+            format!("Mark \"{}\" as {}", self.title_of(chapter_id), status.as_str()),
+            before,
+        );
+        Ok(())
+    }
+
+    /// Replace a node's tags. Blank tags and repeats are dropped.
+    pub fn set_node_tags(&mut self, node_id: &ChapterId, tags: Vec<String>) -> Result<(), ProjectError> {
+        let before = Capture::take(self, Vec::<String>::new());
+        let mut cleaned: Vec<String> = Vec::new();
+        for tag in tags {
+            let tag = tag.trim();
+            if !tag.is_empty() && !cleaned.iter().any(|t| t == tag) {
+                cleaned.push(tag.to_string());
+            }
+        }
+        let node = self
+            .structure
+            .nodes
+            .get_mut(node_id.as_str())
+            .ok_or_else(|| ProjectError::ChapterNotFound(node_id.to_string()))?;
+        node.tags = cleaned;
+
+        self.metadata.modified_at = Utc::now();
+        self.save()?;
+        History::record(
+            self,
+            OperationKind::SetTags,
+            format!("Tag \"{}\"", self.title_of(node_id)),
+            before,
+        );
+        Ok(())
+    }
+
+    pub fn reorder_chapters(&mut self, new_order: Vec<ChapterId>, parent_id: Option<&ChapterId>) -> Result<(), ProjectError> {
+        let before = Capture::take(self, Vec::<String>::new());
+        let new_order: Vec<String> = new_order.into_iter().map(String::from).collect();
+        let parent = parent_id.map_or(self.structure.root.as_str(), |p| p.as_str());
+        if let Some(parent_node) = self.structure.nodes.get_mut(parent) {
+            parent_node.children = new_order.clone();
+        }
+        self.structure.order = new_order;
+        self.metadata.modified_at = Utc::now();
+        self.save()?;
+        History::record(self, OperationKind::ReorderChapters, "Reorder chapters".to_string(), before);
+        Ok(())
+    }
+
+    /// Move a node under a new parent at `position` among its children.
+    pub fn move_chapter(
+        &mut self,
+        chapter_id: &ChapterId,
+        new_parent_id: Option<&ChapterId>,
+        position: usize,
+    ) -> Result<(), ProjectError> {
+        if !self.structure.nodes.contains_key(chapter_id.as_str()) {
+            return Err(ProjectError::ChapterNotFound(chapter_id.to_string()));
+        }
+        let parent = new_parent_id.map_or(self.structure.root.clone(), |p| p.to_string());
+        if !self.structure.nodes.contains_key(&parent) {
+            return Err(ProjectError::ChapterNotFound(parent));
+        }
+        if self.is_descendant(&parent, chapter_id.as_str()) {
+            return Err(ProjectError::InvalidMove(format!(
+                "\"{}\" can't be moved inside itself",
+                self.title_of(chapter_id)
+            )));
+        }
+        let before = Capture::take(self, Vec::<String>::new());
+
+        for node in self.structure.nodes.values_mut() {
+            node.children.retain(|c| chapter_id != c);
+        }
+        if let Some(parent_node) = self.structure.nodes.get_mut(&parent) {
+            let position = position.min(parent_node.children.len());
+            parent_node.children.insert(position, chapter_id.to_string());
+        }
+        self.structure.order = self.chapter_order().into_iter().map(String::from).collect();
+
+        self.metadata.modified_at = Utc::now();
+        self.save()?;
+        History::record(
+            self,
+            OperationKind::MoveChapter,
+            format!("Move \"{}\"", self.title_of(chapter_id)),
+            before,
+        );
+        Ok(())
+    }
+
+    /// Whether `node_id` is `ancestor_id` or sits somewhere below it.
+    fn is_descendant(&self, node_id: &str, ancestor_id: &str) -> bool {
+        if node_id == ancestor_id {
+            return true;
+        }
+        self.structure
+            .nodes
+            .get(ancestor_id)
+            .is_some_and(|n| n.children.iter().any(|c| self.is_descendant(node_id, c)))
+    }
+
+    fn title_of(&self, chapter_id: &ChapterId) -> String {
+        self.structure
+            .nodes
+            .get(chapter_id.as_str())
+            .map(|n| n.title.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn create_snapshot(&self, name: Option<&str>) -> Result<String, ProjectError> {
+        self.write_snapshot(name, BTreeMap::new())
+    }
+
+    /// Snapshot the structure together with the text of `chapters`, so
+    /// [`Project::restore_snapshot`] can put that text back.
+    pub fn create_content_snapshot(&self, name: Option<&str>, chapters: &[ChapterId]) -> Result<String, ProjectError> {
+        let mut contents = BTreeMap::new();
+        for chapter_id in chapters {
+            contents.insert(chapter_id.to_string(), self.load_chapter(chapter_id)?.content);
+        }
+        self.write_snapshot(name, contents)
+    }
+
+    fn write_snapshot(&self, name: Option<&str>, chapters: BTreeMap<String, String>) -> Result<String, ProjectError> {
+        let now = Utc::now();
+        let snapshot_name = sanitize_filename(name.unwrap_or("manual"));
+        let filename = format!("{}-{}.json", now.format("%Y-%m-%dT%H-%M-%S"), snapshot_name);
+
+        let snapshot_dir = self.path.join("snapshots");
+        fs::create_dir_all(&snapshot_dir)?;
+
+        let snapshot = Snapshot {
+            timestamp: now,
+            name: snapshot_name,
+            structure: self.structure.clone(),
+            metadata: self.metadata.clone(),
+            chapters,
+        };
+
+        fs::write(
+            snapshot_dir.join(&filename),
+            serde_json::to_string_pretty(&snapshot)?,
+        )?;
+
+        Ok(filename)
+    }
+
+    /// Put back the chapter text saved in a snapshot, as one undoable
+    /// operation. Chapters deleted since are skipped. Returns the ids of the
+    /// chapters restored.
+    pub fn restore_snapshot(&mut self, filename: &str) -> Result<Vec<String>, ProjectError> {
+        let path = self.resolve(&Path::new("snapshots").join(filename))?;
+        if !path.exists() {
+            return Err(ProjectError::NotFound(path.display().to_string()));
+        }
+        let snapshot: Snapshot = read_json(&path)?;
+
+        let mut chapter_ids = Vec::new();
+        for id in snapshot.chapters.keys() {
+            chapter_ids.push(ChapterId::parse(id)?);
+        }
+        let before = Capture::take(self, chapter_ids.iter().map(chapter_key));
+        let mut restored = Vec::new();
+        for (chapter_id, content) in chapter_ids.iter().zip(snapshot.chapters.values()) {
+            if !self.chapter_path(chapter_id).exists() {
+                continue;
+            }
+            self.update_chapter_content(chapter_id, content)?;
+            restored.push(chapter_id.to_string());
+        }
+
+        if !restored.is_empty() {
+            History::record(
+                self,
+                OperationKind::RestoreSnapshot,
+                format!("Restore snapshot \"{}\"", snapshot.name),
+                before,
+            );
+        }
+        Ok(restored)
+    }
+
+    /// Ids of every chapter node in reading order, walking the tree from the root.
+    pub fn chapter_order(&self) -> Vec<ChapterId> {
+        fn walk(structure: &ManuscriptStructure, node_id: &str, ids: &mut Vec<ChapterId>) {
+            if let Some(node) = structure.nodes.get(node_id) {
+                if node.node_type == NodeType::Chapter {
+                    if let Ok(id) = ChapterId::parse(node_id) {
+                        ids.push(id);
+                    }
+                }
+                for child_id in &node.children {
+                    walk(structure, child_id, ids);
+                }
+            }
+        }
+
+        let mut ids = Vec::new();
+        walk(&self.structure, &self.structure.root, &mut ids);
+        ids
+    }
+
+    pub fn total_word_count(&self) -> u64 {
+        self.structure.nodes.values().map(|n| n.word_count).sum()
+    }
+}
+
+fn sanitize_filename(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' { c } else { '_' })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
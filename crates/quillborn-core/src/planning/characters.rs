@@ -0,0 +1,441 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::manuscript::error::{read_json, WithPath};
+use crate::manuscript::ids::CharacterId;
+use crate::manuscript::project::{Project, ProjectError};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CharacterSheet {
+    pub id: String,
+    pub name: String,
+    /// Nicknames and other names the character is referred to by in the text.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub role: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub arc_summary: String,
+    #[serde(default)]
+    pub speech_patterns: String,
+    #[serde(default)]
+    pub first_appearance: String,
+    #[serde(default)]
+    pub last_appearance: String,
+    #[serde(default)]
+    pub relationships: Vec<Relationship>,
+    /// Portrait path relative to the project root.
+    #[serde(default)]
+    pub portrait: Option<String>,
+    #[serde(default)]
+    pub custom_fields: HashMap<String, String>,
+    #[serde(default)]
+    pub possession: PossessionSettings,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Relationship {
+    pub target_id: String,
+    #[serde(rename = "type")]
+    pub relationship_type: RelationshipType,
+    #[serde(default)]
+    pub label: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum RelationshipType {
+    Ally,
+    Enemy,
+    Family,
+    Lover,
+    Mentor,
+    Rival,
+    Other,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PossessionSettings {
+    pub margin_width: String,
+    pub grain_intensity: f32,
+    pub warmth: f32,
+    #[serde(default)]
+    pub accent_tint: Option<String>,
+}
+
+impl Default for PossessionSettings {
+    fn default() -> Self {
+        PossessionSettings {
+            margin_width: "normal".to_string(),
+            grain_intensity: 0.0,
+            warmth: 0.0,
+            accent_tint: None,
+        }
+    }
+}
+
+impl CharacterSheet {
+    pub fn new(name: &str) -> Self {
+        let now = Utc::now();
+        CharacterSheet {
+            id: CharacterId::new().to_string(),
+            name: name.to_string(),
+            aliases: Vec::new(),
+            role: String::new(),
+            description: String::new(),
+            arc_summary: String::new(),
+            speech_patterns: String::new(),
+            first_appearance: String::new(),
+            last_appearance: String::new(),
+            relationships: Vec::new(),
+            portrait: None,
+            custom_fields: HashMap::new(),
+            possession: PossessionSettings::default(),
+            created_at: now,
+            modified_at: now,
+        }
+    }
+}
+
+fn characters_dir(project: &Project) -> PathBuf {
+    project.path.join("notes").join("characters")
+}
+
+fn portraits_dir(project: &Project) -> PathBuf {
+    characters_dir(project).join("portraits")
+}
+
+fn character_path(project: &Project, character_id: &CharacterId) -> PathBuf {
+    characters_dir(project).join(format!("{}.json", character_id))
+}
+
+/// Every character sheet file, unparsed.
+pub(crate) fn character_files(project: &Project) -> Result<Vec<PathBuf>, ProjectError> {
+    let dir = characters_dir(project);
+    let mut files = Vec::new();
+    if !dir.exists() {
+        return Ok(files);
+    }
+
+    for entry in fs::read_dir(&dir)?.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+pub fn list_characters(project: &Project) -> Result<Vec<CharacterSheet>, ProjectError> {
+    let mut characters = character_files(project)?
+        .iter()
+        .map(|path| read_json(path))
+        .collect::<Result<Vec<CharacterSheet>, _>>()?;
+    characters.sort_by_key(|c| c.name.to_lowercase());
+    Ok(characters)
+}
+
+pub fn load_character(project: &Project, character_id: &CharacterId) -> Result<CharacterSheet, ProjectError> {
+    let path = character_path(project, character_id);
+    if !path.exists() {
+        return Err(ProjectError::CharacterNotFound(character_id.to_string()));
+    }
+    read_json(&path)
+}
+
+pub fn create_character(project: &Project, name: &str) -> Result<CharacterSheet, ProjectError> {
+    let sheet = CharacterSheet::new(name);
+    write_character(project, &sheet)?;
+    Ok(sheet)
+}
+
+/// Save a character sheet after checking that every relationship points at
+/// another existing character.
+pub fn save_character(project: &Project, sheet: &mut CharacterSheet) -> Result<(), ProjectError> {
+    let known: HashSet<String> = list_characters(project)?
+        .into_iter()
+        .map(|c| c.id)
+        .collect();
+
+    for relationship in &sheet.relationships {
+        if relationship.target_id == sheet.id {
+            return Err(ProjectError::InvalidRelationship(format!(
+                "{} cannot have a relationship with themselves",
+                sheet.name
+            )));
+        }
+        if !known.contains(&relationship.target_id) {
+            return Err(ProjectError::InvalidRelationship(format!(
+                "unknown character: {}",
+                relationship.target_id
+            )));
+        }
+    }
+
+    sheet.modified_at = Utc::now();
+    write_character(project, sheet)
+}
+
+/// Delete a character, its portrait, and every relationship that points at it.
+pub fn delete_character(project: &Project, character_id: &CharacterId) -> Result<(), ProjectError> {
+    let sheet = load_character(project, character_id)?;
+
+    if let Some(portrait) = &sheet.portrait {
+        let portrait_path = project.resolve(Path::new(portrait))?;
+        if portrait_path.exists() {
+            fs::remove_file(&portrait_path).with_path(&portrait_path)?;
+        }
+    }
+    let path = character_path(project, character_id);
+    fs::remove_file(&path).with_path(&path)?;
+
+    for mut other in list_characters(project)? {
+        let before = other.relationships.len();
+        other.relationships.retain(|r| character_id != &r.target_id);
+        if other.relationships.len() != before {
+            other.modified_at = Utc::now();
+            write_character(project, &other)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copy an image into the project and make it the character's portrait.
+pub fn set_portrait(
+    project: &Project,
+    character_id: &CharacterId,
+    source: &Path,
+) -> Result<CharacterSheet, ProjectError> {
+    let mut sheet = load_character(project, character_id)?;
+    if !source.exists() {
+        return Err(ProjectError::NotFound(source.display().to_string()));
+    }
+
+    let ext = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_else(|| "png".to_string());
+
+    let dir = portraits_dir(project);
+    fs::create_dir_all(&dir)?;
+
+    // Replace any previous portrait
+    if let Some(old) = &sheet.portrait {
+        let old_path = project.resolve(Path::new(old))?;
+        if old_path.exists() {
+            fs::remove_file(&old_path).with_path(&old_path)?;
+        }
+    }
+
+    let filename = format!("{}.{}", character_id, ext);
+    fs::copy(source, dir.join(&filename)).with_path(source)?;
+
+    sheet.portrait = Some(format!("notes/characters/portraits/{}", filename));
+    sheet.modified_at = Utc::now();
+    write_character(project, &sheet)?;
+
+    Ok(sheet)
+}
+
+pub(crate) fn write_character(project: &Project, sheet: &CharacterSheet) -> Result<(), ProjectError> {
+    let character_id = CharacterId::parse(&sheet.id)?;
+    fs::create_dir_all(characters_dir(project))?;
+    let path = character_path(project, &character_id);
+    fs::write(&path, serde_json::to_string_pretty(sheet)?).with_path(&path)?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Outgoing,
+    Incoming,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RelationshipEdge {
+    pub from: String,
+    pub to: String,
+    #[serde(rename = "type")]
+    pub relationship_type: RelationshipType,
+    pub label: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Neighbour {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub relationship_type: RelationshipType,
+    pub label: String,
+    pub direction: Direction,
+}
+
+/// One hop along a relationship path, from the previous character to `id`.
+#[derive(Debug, Serialize, Clone)]
+pub struct PathStep {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub relationship_type: Option<RelationshipType>,
+    pub label: String,
+}
+
+/// Relationship graph over all character sheets. Relationships are stored on
+/// one side only, so traversal treats every edge as undirected and reports
+/// the stored direction separately.
+pub struct CharacterGraph {
+    names: HashMap<String, String>,
+    edges: Vec<RelationshipEdge>,
+}
+
+impl CharacterGraph {
+    pub fn build(characters: &[CharacterSheet]) -> Self {
+        let names = characters
+            .iter()
+            .map(|c| (c.id.clone(), c.name.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let edges = characters
+            .iter()
+            .flat_map(|c| {
+                c.relationships.iter().map(move |r| RelationshipEdge {
+                    from: c.id.clone(),
+                    to: r.target_id.clone(),
+                    relationship_type: r.relationship_type,
+                    label: r.label.clone(),
+                })
+            })
+            .filter(|e| names.contains_key(&e.to))
+            .collect();
+
+        CharacterGraph { names, edges }
+    }
+
+    pub fn load(project: &Project) -> Result<Self, ProjectError> {
+        Ok(Self::build(&list_characters(project)?))
+    }
+
+    fn name_of(&self, id: &str) -> String {
+        self.names.get(id).cloned().unwrap_or_default()
+    }
+
+    fn matches(edge: &RelationshipEdge, types: Option<&[RelationshipType]>) -> bool {
+        types.is_none_or(|t| t.contains(&edge.relationship_type))
+    }
+
+    pub fn neighbours(
+        &self,
+        character_id: &str,
+        types: Option<&[RelationshipType]>,
+    ) -> Result<Vec<Neighbour>, ProjectError> {
+        if !self.names.contains_key(character_id) {
+            return Err(ProjectError::CharacterNotFound(character_id.to_string()));
+        }
+
+        let mut neighbours = Vec::new();
+        for edge in self.edges.iter().filter(|e| Self::matches(e, types)) {
+            let (other, direction) = if edge.from == character_id {
+                (&edge.to, Direction::Outgoing)
+            } else if edge.to == character_id {
+                (&edge.from, Direction::Incoming)
+            } else {
+                continue;
+            };
+            neighbours.push(Neighbour {
+                id: other.clone(),
+                name: self.name_of(other),
+                relationship_type: edge.relationship_type,
+                label: edge.label.clone(),
+                direction,
+            });
+        }
+
+        neighbours.sort_by_key(|n| n.name.to_lowercase());
+        Ok(neighbours)
+    }
+
+    /// Breadth-first search for the fewest relationships linking two
+    /// characters. The first step is the starting character itself.
+    pub fn shortest_path(
+        &self,
+        from: &str,
+        to: &str,
+        types: Option<&[RelationshipType]>,
+    ) -> Result<Option<Vec<PathStep>>, ProjectError> {
+        for id in [from, to] {
+            if !self.names.contains_key(id) {
+                return Err(ProjectError::CharacterNotFound(id.to_string()));
+            }
+        }
+
+        let mut adjacency: HashMap<&str, Vec<(&str, &RelationshipEdge)>> = HashMap::new();
+        for edge in self.edges.iter().filter(|e| Self::matches(e, types)) {
+            adjacency.entry(&edge.from).or_default().push((&edge.to, edge));
+            adjacency.entry(&edge.to).or_default().push((&edge.from, edge));
+        }
+
+        let mut previous: HashMap<&str, (&str, &RelationshipEdge)> = HashMap::new();
+        let mut visited: HashSet<&str> = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                break;
+            }
+            for (next, edge) in adjacency.get(current).into_iter().flatten() {
+                if visited.insert(next) {
+                    previous.insert(next, (current, edge));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        if !visited.contains(to) {
+            return Ok(None);
+        }
+
+        let mut steps = Vec::new();
+        let mut current = to;
+        while let Some((prev, edge)) = previous.get(current) {
+            steps.push(PathStep {
+                id: current.to_string(),
+                name: self.name_of(current),
+                relationship_type: Some(edge.relationship_type),
+                label: edge.label.clone(),
+            });
+            current = prev;
+        }
+        steps.push(PathStep {
+            id: from.to_string(),
+            name: self.name_of(from),
+            relationship_type: None,
+            label: String::new(),
+        });
+        steps.reverse();
+
+        Ok(Some(steps))
+    }
+
+    pub fn edges_of_type(&self, types: &[RelationshipType]) -> Vec<RelationshipEdge> {
+        self.edges
+            .iter()
+            .filter(|e| Self::matches(e, Some(types)))
+            .cloned()
+            .collect()
+    }
+}
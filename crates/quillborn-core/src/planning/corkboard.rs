@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::manuscript::error::{read_json, WithPath};
+use crate::manuscript::project::{Project, ProjectError};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CorkboardCard {
+    pub id: String,
+    pub chapter_id: String,
+    pub x: f64,
+    pub y: f64,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub color: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Corkboard {
+    #[serde(default)]
+    pub cards: Vec<CorkboardCard>,
+}
+
+fn corkboard_path(project: &Project) -> PathBuf {
+    project.path.join("corkboard.json")
+}
+
+impl Corkboard {
+    pub fn load(project: &Project) -> Result<Self, ProjectError> {
+        let path = corkboard_path(project);
+        if !path.exists() {
+            return Ok(Corkboard::default());
+        }
+        read_json(&path)
+    }
+
+    pub fn save(&self, project: &Project) -> Result<(), ProjectError> {
+        let path = corkboard_path(project);
+        fs::write(&path, serde_json::to_string_pretty(self)?).with_path(&path)?;
+        Ok(())
+    }
+
+    /// Insert a new card or replace the one with the same id.
+    pub fn upsert_card(&mut self, card: CorkboardCard) {
+        match self.cards.iter_mut().find(|c| c.id == card.id) {
+            Some(existing) => *existing = card,
+            None => self.cards.push(card),
+        }
+    }
+
+    pub fn remove_card(&mut self, card_id: &str) {
+        self.cards.retain(|c| c.id != card_id);
+    }
+
+    /// Drop every card belonging to a deleted chapter.
+    pub fn forget_chapter(project: &Project, chapter_id: &str) -> Result<(), ProjectError> {
+        let mut corkboard = Self::load(project)?;
+        let before = corkboard.cards.len();
+        corkboard.cards.retain(|c| c.chapter_id != chapter_id);
+        if corkboard.cards.len() != before {
+            corkboard.save(project)?;
+        }
+        Ok(())
+    }
+}
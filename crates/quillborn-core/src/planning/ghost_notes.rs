@@ -0,0 +1,224 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::manuscript::error::{read_json, WithPath};
+use crate::manuscript::ids::{ChapterId, GhostNoteId};
+use crate::manuscript::project::{ChapterStatus, Project, ProjectError};
+
+/// Name of the Tauri event emitted with the note payload when a note is revealed.
+pub const REVEALED_EVENT: &str = "ghost-note-revealed";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case", rename_all_fields = "camelCase")]
+pub enum GhostNoteTrigger {
+    /// Reveal on or after a date, given as `YYYY-MM-DD` or RFC 3339.
+    Date { date: String },
+    /// Reveal once the whole manuscript reaches this many words.
+    WordCount { count: u64 },
+    /// Reveal after a chapter has been opened this many times.
+    ChapterVisits { chapter_id: String, visits: u32 },
+    /// Reveal when the note's chapter reaches this status.
+    StatusChange { status: ChapterStatus },
+    Manual,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GhostNote {
+    pub id: String,
+    pub chapter_id: String,
+    pub message: String,
+    pub trigger: GhostNoteTrigger,
+    #[serde(default)]
+    pub revealed: bool,
+    #[serde(default)]
+    pub dismissed: bool,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, with = "chrono::serde::ts_milliseconds_option")]
+    pub revealed_at: Option<DateTime<Utc>>,
+}
+
+impl GhostNote {
+    pub fn new(chapter_id: &ChapterId, message: &str, trigger: GhostNoteTrigger) -> Self {
+        GhostNote {
+            id: GhostNoteId::new().to_string(),
+            chapter_id: chapter_id.to_string(),
+            message: message.to_string(),
+            trigger,
+            revealed: false,
+            dismissed: false,
+            created_at: Utc::now(),
+            revealed_at: None,
+        }
+    }
+}
+
+fn ghost_notes_dir(project: &Project) -> PathBuf {
+    project.path.join("ghost-notes")
+}
+
+fn note_path(project: &Project, note_id: &GhostNoteId) -> PathBuf {
+    ghost_notes_dir(project).join(format!("{}.json", note_id))
+}
+
+fn visits_path(project: &Project) -> PathBuf {
+    ghost_notes_dir(project).join("visits.json")
+}
+
+pub fn list_ghost_notes(project: &Project) -> Result<Vec<GhostNote>, ProjectError> {
+    let dir = ghost_notes_dir(project);
+    let mut notes = Vec::new();
+    if !dir.exists() {
+        return Ok(notes);
+    }
+
+    for entry in fs::read_dir(&dir)?.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") || path == visits_path(project) {
+            continue;
+        }
+        // A note that can't be read is left out rather than hiding the rest.
+        if let Ok(note) = read_json(&path) {
+            notes.push(note);
+        }
+    }
+
+    notes.sort_by_key(|n: &GhostNote| n.created_at);
+    Ok(notes)
+}
+
+pub fn load_ghost_note(project: &Project, note_id: &GhostNoteId) -> Result<GhostNote, ProjectError> {
+    let path = note_path(project, note_id);
+    if !path.exists() {
+        return Err(ProjectError::GhostNoteNotFound(note_id.to_string()));
+    }
+    read_json(&path)
+}
+
+pub fn save_ghost_note(project: &Project, note: &GhostNote) -> Result<(), ProjectError> {
+    fs::create_dir_all(ghost_notes_dir(project))?;
+    let note_id = GhostNoteId::parse(&note.id)?;
+    let path = note_path(project, &note_id);
+    fs::write(&path, serde_json::to_string_pretty(note)?).with_path(&path)?;
+    Ok(())
+}
+
+pub fn delete_ghost_note(project: &Project, note_id: &GhostNoteId) -> Result<(), ProjectError> {
+    let path = note_path(project, note_id);
+    if !path.exists() {
+        return Err(ProjectError::GhostNoteNotFound(note_id.to_string()));
+    }
+    fs::remove_file(&path).with_path(&path)?;
+    Ok(())
+}
+
+pub fn load_visits(project: &Project) -> HashMap<String, u32> {
+    fs::read_to_string(visits_path(project))
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Count one more visit to a chapter and return the new total.
+pub fn record_visit(project: &Project, chapter_id: &ChapterId) -> Result<u32, ProjectError> {
+    let mut visits = load_visits(project);
+    let count = visits.entry(chapter_id.to_string()).or_insert(0);
+    *count += 1;
+    let count = *count;
+
+    fs::create_dir_all(ghost_notes_dir(project))?;
+    let path = visits_path(project);
+    fs::write(&path, serde_json::to_string_pretty(&visits)?).with_path(&path)?;
+    Ok(count)
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+}
+
+fn has_fired(note: &GhostNote, project: &Project, visits: &HashMap<String, u32>) -> bool {
+    match &note.trigger {
+        GhostNoteTrigger::Manual => true,
+        GhostNoteTrigger::Date { date } => parse_date(date).is_some_and(|d| Utc::now() >= d),
+        GhostNoteTrigger::WordCount { count } => project.total_word_count() >= *count,
+        GhostNoteTrigger::ChapterVisits { chapter_id, visits: needed } => {
+            visits.get(chapter_id).copied().unwrap_or(0) >= *needed
+        }
+        GhostNoteTrigger::StatusChange { status } => project
+            .structure
+            .nodes
+            .get(&note.chapter_id)
+            .is_some_and(|n| n.status == *status),
+    }
+}
+
+/// Reveal every pending note whose trigger has fired and return the notes
+/// that were revealed by this call.
+pub fn evaluate_triggers(project: &Project) -> Result<Vec<GhostNote>, ProjectError> {
+    let visits = load_visits(project);
+    let mut revealed = Vec::new();
+
+    for mut note in list_ghost_notes(project)? {
+        if note.revealed || note.dismissed || !has_fired(&note, project, &visits) {
+            continue;
+        }
+        note.revealed = true;
+        note.revealed_at = Some(Utc::now());
+        save_ghost_note(project, &note)?;
+        revealed.push(note);
+    }
+
+    Ok(revealed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_notes_in_the_frontend_shape() {
+        let note: GhostNote = serde_json::from_value(serde_json::json!({
+            "id": "n1",
+            "chapterId": "c1",
+            "message": "Remember the lighthouse",
+            "trigger": { "type": "chapter_visits", "chapterId": "c2", "visits": 3 },
+            "revealed": false,
+            "dismissed": false,
+            "createdAt": 1_700_000_000_000i64,
+        }))
+        .unwrap();
+
+        assert_eq!(note.chapter_id, "c1");
+        assert_eq!(note.created_at.timestamp_millis(), 1_700_000_000_000);
+        assert!(matches!(
+            note.trigger,
+            GhostNoteTrigger::ChapterVisits { ref chapter_id, visits: 3 } if chapter_id == "c2"
+        ));
+        assert_eq!(serde_json::to_value(&note).unwrap()["trigger"]["chapterId"], "c2");
+    }
+
+    #[test]
+    fn unreadable_notes_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "Ghosts", "Author").unwrap();
+        let chapter = ChapterId::parse(&project.add_chapter("One", None).unwrap().id).unwrap();
+        let note = GhostNote::new(&chapter, "Remember the lighthouse", GhostNoteTrigger::Manual);
+        save_ghost_note(&project, &note).unwrap();
+        fs::write(ghost_notes_dir(&project).join("broken.json"), "{ not json").unwrap();
+
+        let notes = list_ghost_notes(&project).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].id, note.id);
+        assert!(evaluate_triggers(&project).is_ok());
+    }
+}
@@ -0,0 +1,412 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use super::characters::{self, CharacterSheet};
+use crate::manuscript::chapter::Chapter;
+use crate::manuscript::error::WithPath;
+use crate::manuscript::ids::{ChapterId, CharacterId};
+use crate::manuscript::project::{Project, ProjectError};
+
+/// Verbs that attribute a line of dialogue to the name next to them.
+const SPEECH_VERBS: &[&str] = &[
+    "said", "says", "asked", "asks", "replied", "replies", "answered", "whispered",
+    "shouted", "murmured", "muttered", "called", "cried", "yelled", "snapped", "added",
+    "continued", "told", "exclaimed", "hissed", "sighed", "laughed", "insisted",
+];
+
+/// Default number of consecutive chapters without a mention that counts as
+/// an absence worth reporting.
+pub const DEFAULT_MIN_ABSENCE: usize = 5;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Mention {
+    pub chapter_id: String,
+    pub chapter_title: String,
+    pub line_number: usize,
+    pub start: usize,
+    pub end: usize,
+    pub matched: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ChapterRef {
+    pub chapter_id: String,
+    pub chapter_title: String,
+    /// Zero-based position of the chapter in manuscript order.
+    pub position: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ChapterMentionCount {
+    pub chapter_id: String,
+    pub chapter_title: String,
+    pub position: usize,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Absence {
+    /// Last chapter the character appeared in before the gap.
+    pub last_seen: ChapterRef,
+    /// Chapter where the character returns, or `None` if they never do.
+    pub returns_in: Option<ChapterRef>,
+    pub chapters_missing: usize,
+    pub summary: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CharacterMentionReport {
+    pub character_id: String,
+    pub name: String,
+    pub total_mentions: usize,
+    pub first_appearance: Option<ChapterRef>,
+    pub last_appearance: Option<ChapterRef>,
+    pub mentions_per_chapter: Vec<ChapterMentionCount>,
+    pub speaking_chapters: Vec<ChapterRef>,
+    pub absences: Vec<Absence>,
+}
+
+/// Per-chapter scan results, cached in `index/mentions.json` so that saving
+/// one chapter only rescans that chapter.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct MentionCache {
+    /// Changes whenever a character name or alias changes, which invalidates
+    /// every cached chapter.
+    fingerprint: String,
+    chapters: HashMap<String, ChapterScan>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct ChapterScan {
+    modified_at: DateTime<Utc>,
+    counts: HashMap<String, usize>,
+    speakers: Vec<String>,
+}
+
+fn cache_path(project: &Project) -> PathBuf {
+    project.path.join("index").join("mentions.json")
+}
+
+fn fingerprint(characters: &[CharacterSheet]) -> String {
+    let mut parts: Vec<String> = characters
+        .iter()
+        .map(|c| format!("{}={}|{}", c.id, c.name, c.aliases.join("|")))
+        .collect();
+    parts.sort();
+    parts.join("\n")
+}
+
+fn load_cache(project: &Project) -> MentionCache {
+    fs::read_to_string(cache_path(project))
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(project: &Project, cache: &MentionCache) -> Result<(), ProjectError> {
+    let path = cache_path(project);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string(cache)?).with_path(&path)?;
+    Ok(())
+}
+
+/// Every name a character can be referred to by, longest first so that
+/// "Mara Venn" wins over "Mara" when both match at the same spot.
+fn names_for(character: &CharacterSheet) -> Vec<String> {
+    let mut names: Vec<String> = std::iter::once(character.name.clone())
+        .chain(character.aliases.iter().cloned())
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .collect();
+    names.sort_by_key(|n| std::cmp::Reverse(n.len()));
+    names.dedup();
+    names
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Byte ranges of whole-word occurrences of any of `names` in `line`.
+fn find_names(line: &str, names: &[String]) -> Vec<(usize, usize)> {
+    let mut found: Vec<(usize, usize)> = Vec::new();
+    for name in names {
+        for (start, _) in line.match_indices(name.as_str()) {
+            let end = start + name.len();
+            let before_ok = line[..start].chars().next_back().is_none_or(|c| !is_word_char(c));
+            let after_ok = line[end..].chars().next().is_none_or(|c| !is_word_char(c));
+            let overlaps = found.iter().any(|&(s, e)| start < e && end > s);
+            if before_ok && after_ok && !overlaps {
+                found.push((start, end));
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+/// Text of `line` outside quotation marks, with quoted spans blanked out.
+fn narration_only(line: &str) -> String {
+    let mut inside = false;
+    line.chars()
+        .map(|c| match c {
+            '"' => {
+                inside = !inside;
+                ' '
+            }
+            '\u{201C}' => {
+                inside = true;
+                ' '
+            }
+            '\u{201D}' => {
+                inside = false;
+                ' '
+            }
+            _ if inside => ' ',
+            _ => c,
+        })
+        .collect()
+}
+
+fn has_dialogue(line: &str) -> bool {
+    line.contains('"') || line.contains('\u{201C}')
+}
+
+/// Whether a name in the narration is attributed as the speaker, as in
+/// `"…," Mara said`, `Mara quietly said` or `said Mara`.
+fn is_speaker(narration: &str, names: &[String]) -> bool {
+    let words: Vec<String> = narration
+        .split(|c: char| !is_word_char(c) && c != '\'')
+        .filter(|w| !w.is_empty())
+        .map(|w| w.trim_end_matches("'s").to_string())
+        .collect();
+    let is_verb = |i: usize| {
+        words
+            .get(i)
+            .is_some_and(|w| SPEECH_VERBS.contains(&w.to_lowercase().as_str()))
+    };
+
+    for name in names {
+        let name_words: Vec<&str> = name.split_whitespace().collect();
+        let Some(first) = name_words.first() else {
+            continue;
+        };
+        let last = name_words[name_words.len() - 1];
+        for (i, word) in words.iter().enumerate() {
+            if word == last {
+                let adverb = words.get(i + 1).is_some_and(|w| w.ends_with("ly"));
+                if is_verb(i + 1) || (adverb && is_verb(i + 2)) {
+                    return true;
+                }
+            }
+            if word == first && i > 0 && is_verb(i - 1) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn scan_chapter(chapter: &Chapter, characters: &[CharacterSheet]) -> ChapterScan {
+    let mut counts = HashMap::new();
+    let mut speakers = Vec::new();
+
+    for character in characters {
+        let names = names_for(character);
+        let mut count = 0;
+        let mut speaks = false;
+        for line in chapter.content.lines() {
+            count += find_names(line, &names).len();
+            if !speaks && has_dialogue(line) {
+                speaks = is_speaker(&narration_only(line), &names);
+            }
+        }
+        if count > 0 {
+            counts.insert(character.id.clone(), count);
+        }
+        if speaks {
+            speakers.push(character.id.clone());
+        }
+    }
+
+    ChapterScan {
+        modified_at: chapter.modified_at,
+        counts,
+        speakers,
+    }
+}
+
+fn load_chapter(project: &Project, chapter_id: &ChapterId) -> Result<Option<Chapter>, ProjectError> {
+    if !project.chapter_path(chapter_id).exists() {
+        return Ok(None);
+    }
+    project.load_chapter(chapter_id).map(Some)
+}
+
+/// Bring the cache up to date with the current chapters and characters,
+/// rescanning only chapters that changed since they were last scanned.
+fn refresh_cache(
+    project: &Project,
+    characters: &[CharacterSheet],
+) -> Result<(MentionCache, Vec<Chapter>), ProjectError> {
+    let mut cache = load_cache(project);
+    let current = fingerprint(characters);
+    if cache.fingerprint != current {
+        cache = MentionCache {
+            fingerprint: current,
+            chapters: HashMap::new(),
+        };
+    }
+
+    let mut chapters = Vec::new();
+    let mut dirty = false;
+    for chapter_id in project.chapter_order() {
+        let Some(chapter) = load_chapter(project, &chapter_id)? else {
+            continue;
+        };
+        let fresh = cache
+            .chapters
+            .get(&chapter.id)
+            .is_some_and(|scan| scan.modified_at == chapter.modified_at);
+        if !fresh {
+            cache
+                .chapters
+                .insert(chapter.id.clone(), scan_chapter(&chapter, characters));
+            dirty = true;
+        }
+        chapters.push(chapter);
+    }
+
+    let before = cache.chapters.len();
+    cache
+        .chapters
+        .retain(|id, _| chapters.iter().any(|c| &c.id == id));
+    if dirty || cache.chapters.len() != before {
+        save_cache(project, &cache)?;
+    }
+
+    Ok((cache, chapters))
+}
+
+/// Rescan a single chapter after it was saved.
+pub fn update_chapter_mentions(project: &Project, chapter: &Chapter) -> Result<(), ProjectError> {
+    let characters = characters::list_characters(project)?;
+    let mut cache = load_cache(project);
+    if cache.fingerprint != fingerprint(&characters) {
+        // Names changed since the last scan; the next report rebuilds everything
+        return Ok(());
+    }
+    cache
+        .chapters
+        .insert(chapter.id.clone(), scan_chapter(chapter, &characters));
+    save_cache(project, &cache)
+}
+
+pub fn mention_reports(
+    project: &Project,
+    min_absence: usize,
+) -> Result<Vec<CharacterMentionReport>, ProjectError> {
+    let characters = characters::list_characters(project)?;
+    let (cache, chapters) = refresh_cache(project, &characters)?;
+
+    let chapter_ref = |position: usize| ChapterRef {
+        chapter_id: chapters[position].id.clone(),
+        chapter_title: chapters[position].title.clone(),
+        position,
+    };
+
+    let mut reports = Vec::new();
+    for character in &characters {
+        let mut mentions_per_chapter = Vec::new();
+        let mut speaking_chapters = Vec::new();
+        for (position, chapter) in chapters.iter().enumerate() {
+            let Some(scan) = cache.chapters.get(&chapter.id) else {
+                continue;
+            };
+            if let Some(&count) = scan.counts.get(&character.id) {
+                mentions_per_chapter.push(ChapterMentionCount {
+                    chapter_id: chapter.id.clone(),
+                    chapter_title: chapter.title.clone(),
+                    position,
+                    count,
+                });
+            }
+            if scan.speakers.contains(&character.id) {
+                speaking_chapters.push(chapter_ref(position));
+            }
+        }
+
+        let positions: Vec<usize> = mentions_per_chapter.iter().map(|m| m.position).collect();
+        let mut absences = Vec::new();
+        for pair in positions.windows(2) {
+            let missing = pair[1] - pair[0] - 1;
+            if missing >= min_absence.max(1) {
+                absences.push(Absence {
+                    last_seen: chapter_ref(pair[0]),
+                    returns_in: Some(chapter_ref(pair[1])),
+                    chapters_missing: missing,
+                    summary: format!("{} disappears for {} chapters", character.name, missing),
+                });
+            }
+        }
+        if let Some(&last) = positions.last() {
+            let missing = chapters.len() - last - 1;
+            if missing >= min_absence.max(1) {
+                absences.push(Absence {
+                    last_seen: chapter_ref(last),
+                    returns_in: None,
+                    chapters_missing: missing,
+                    summary: format!(
+                        "{} disappears for the last {} chapters",
+                        character.name, missing
+                    ),
+                });
+            }
+        }
+
+        reports.push(CharacterMentionReport {
+            character_id: character.id.clone(),
+            name: character.name.clone(),
+            total_mentions: mentions_per_chapter.iter().map(|m| m.count).sum(),
+            first_appearance: positions.first().map(|&p| chapter_ref(p)),
+            last_appearance: positions.last().map(|&p| chapter_ref(p)),
+            mentions_per_chapter,
+            speaking_chapters,
+            absences,
+        });
+    }
+
+    Ok(reports)
+}
+
+/// Every mention of one character across the manuscript, in reading order.
+pub fn find_mentions(project: &Project, character_id: &CharacterId) -> Result<Vec<Mention>, ProjectError> {
+    let character = characters::load_character(project, character_id)?;
+    let names = names_for(&character);
+    let mut mentions = Vec::new();
+
+    for chapter_id in project.chapter_order() {
+        let Some(chapter) = load_chapter(project, &chapter_id)? else {
+            continue;
+        };
+        for (line_idx, line) in chapter.content.lines().enumerate() {
+            for (start, end) in find_names(line, &names) {
+                mentions.push(Mention {
+                    chapter_id: chapter.id.clone(),
+                    chapter_title: chapter.title.clone(),
+                    line_number: line_idx + 1,
+                    start,
+                    end,
+                    matched: line[start..end].to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(mentions)
+}
@@ -0,0 +1,8 @@
+pub mod characters;
+pub mod corkboard;
+pub mod ghost_notes;
+pub mod mentions;
+pub mod notes;
+pub mod threads;
+pub mod timeline;
+pub mod wiki;
@@ -0,0 +1,228 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::manuscript::error::WithPath;
+use crate::manuscript::project::{Project, ProjectError};
+
+/// Note categories whose files are not markdown notes.
+const RESERVED_CATEGORIES: &[&str] = &["characters"];
+
+/// A markdown note stored under `notes/<category>/<id>.md`, used for wiki
+/// entries, worldbuilding and scratch pages.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Note {
+    pub id: String,
+    pub title: String,
+    pub category: String,
+    pub content: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+impl Note {
+    pub fn new(title: &str, category: &str) -> Self {
+        let now = Utc::now();
+        Note {
+            id: Uuid::new_v4().to_string(),
+            title: title.to_string(),
+            category: category.to_string(),
+            content: String::new(),
+            tags: Vec::new(),
+            created_at: now,
+            modified_at: now,
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut output = String::new();
+        output.push_str("---\n");
+        output.push_str(&format!("id: \"{}\"\n", self.id));
+        output.push_str(&format!("title: \"{}\"\n", self.title));
+        if !self.tags.is_empty() {
+            output.push_str(&format!("tags: \"{}\"\n", self.tags.join(", ")));
+        }
+        output.push_str(&format!("created_at: \"{}\"\n", self.created_at.to_rfc3339()));
+        output.push_str(&format!(
+            "modified_at: \"{}\"\n",
+            self.modified_at.to_rfc3339()
+        ));
+        output.push_str("---\n\n");
+        output.push_str(&self.content);
+        output
+    }
+
+    pub fn from_file(path: &Path, category: &str) -> Result<Self, ProjectError> {
+        let raw = fs::read_to_string(path).with_path(path)?;
+        let id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        let mut note = Note {
+            id,
+            title: "Untitled".to_string(),
+            category: category.to_string(),
+            content: raw.clone(),
+            tags: Vec::new(),
+            created_at: Utc::now(),
+            modified_at: Utc::now(),
+        };
+
+        let parts: Vec<&str> = raw.splitn(3, "---").collect();
+        if !raw.starts_with("---") || parts.len() < 3 {
+            return Ok(note);
+        }
+
+        note.content = parts[2].trim().to_string();
+        for line in parts[1].trim().lines() {
+            if let Some((key, value)) = line.trim().split_once(':') {
+                let value = value.trim().trim_matches('"');
+                match key.trim() {
+                    "title" => note.title = value.to_string(),
+                    "tags" => {
+                        note.tags = value
+                            .split(',')
+                            .map(|t| t.trim().to_string())
+                            .filter(|t| !t.is_empty())
+                            .collect()
+                    }
+                    "created_at" => {
+                        if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+                            note.created_at = dt.with_timezone(&Utc);
+                        }
+                    }
+                    "modified_at" => {
+                        if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+                            note.modified_at = dt.with_timezone(&Utc);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(note)
+    }
+}
+
+fn notes_dir(project: &Project) -> PathBuf {
+    project.path.join("notes")
+}
+
+fn note_path(project: &Project, note: &Note) -> PathBuf {
+    notes_dir(project)
+        .join(&note.category)
+        .join(format!("{}.md", note.id))
+}
+
+fn validate_category(category: &str) -> Result<(), ProjectError> {
+    let valid = !category.is_empty()
+        && category
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        && !RESERVED_CATEGORIES.contains(&category);
+    if valid {
+        Ok(())
+    } else {
+        Err(ProjectError::InvalidCategory(category.to_string()))
+    }
+}
+
+/// Every markdown note file with its category, without reading them.
+pub(crate) fn note_files(project: &Project) -> Result<Vec<(String, PathBuf)>, ProjectError> {
+    let mut files = Vec::new();
+    let dir = notes_dir(project);
+    if !dir.exists() {
+        return Ok(files);
+    }
+
+    for category_entry in fs::read_dir(&dir)?.flatten() {
+        let category_path = category_entry.path();
+        let Some(category) = category_path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if !category_path.is_dir() || RESERVED_CATEGORIES.contains(&category) {
+            continue;
+        }
+        for entry in fs::read_dir(&category_path)?.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("md") {
+                files.push((category.to_string(), path));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// List every markdown note across all note categories, sorted by title.
+pub fn list_notes(project: &Project) -> Result<Vec<Note>, ProjectError> {
+    let mut notes = note_files(project)?
+        .into_iter()
+        .map(|(category, path)| Note::from_file(&path, &category))
+        .collect::<Result<Vec<_>, _>>()?;
+    notes.sort_by_key(|n| n.title.to_lowercase());
+    Ok(notes)
+}
+
+pub fn load_note(project: &Project, note_id: &str) -> Result<Note, ProjectError> {
+    list_notes(project)?
+        .into_iter()
+        .find(|n| n.id == note_id)
+        .ok_or_else(|| ProjectError::NoteNotFound(note_id.to_string()))
+}
+
+pub fn create_note(project: &Project, title: &str, category: &str) -> Result<Note, ProjectError> {
+    validate_category(category)?;
+    let note = Note::new(title, category);
+    write_note(project, &note)?;
+    Ok(note)
+}
+
+pub fn update_note(
+    project: &Project,
+    note_id: &str,
+    content: &str,
+    tags: Option<Vec<String>>,
+) -> Result<Note, ProjectError> {
+    let mut note = load_note(project, note_id)?;
+    note.content = content.to_string();
+    if let Some(tags) = tags {
+        note.tags = tags;
+    }
+    note.modified_at = Utc::now();
+    write_note(project, &note)?;
+    Ok(note)
+}
+
+/// Rename a note and rewrite every `[[wikilink]]` that pointed at its old title.
+pub fn rename_note(project: &mut Project, note_id: &str, new_title: &str) -> Result<Note, ProjectError> {
+    let mut note = load_note(project, note_id)?;
+    let old_title = std::mem::replace(&mut note.title, new_title.to_string());
+    note.modified_at = Utc::now();
+    write_note(project, &note)?;
+
+    super::wiki::rewrite_links(project, &note.id, &old_title, new_title)?;
+    load_note(project, note_id)
+}
+
+pub fn delete_note(project: &Project, note_id: &str) -> Result<(), ProjectError> {
+    let note = load_note(project, note_id)?;
+    let path = note_path(project, &note);
+    fs::remove_file(&path).with_path(&path)?;
+    Ok(())
+}
+
+pub(crate) fn write_note(project: &Project, note: &Note) -> Result<(), ProjectError> {
+    let path = note_path(project, note);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, note.to_markdown()).with_path(&path)?;
+    Ok(())
+}
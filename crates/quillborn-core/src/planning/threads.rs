@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::manuscript::ids::ChapterId;
+use crate::manuscript::error::{read_json, WithPath};
+use crate::manuscript::project::{Project, ProjectError};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlotThread {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub color: String,
+    #[serde(default)]
+    pub chapters: Vec<String>,
+}
+
+impl PlotThread {
+    pub fn new(name: &str, color: &str) -> Self {
+        PlotThread {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            color: color.to_string(),
+            chapters: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PlotThreads {
+    #[serde(default)]
+    pub threads: Vec<PlotThread>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ThreadChapter {
+    pub chapter_id: String,
+    pub chapter_title: String,
+    /// Zero-based position of the chapter in manuscript order.
+    pub position: usize,
+    pub word_count: u64,
+}
+
+/// A run of chapters between two chapters of a thread where it goes unmentioned.
+#[derive(Debug, Serialize, Clone)]
+pub struct ThreadGap {
+    pub after_chapter_id: String,
+    pub before_chapter_id: String,
+    pub chapter_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ThreadReport {
+    pub thread_id: String,
+    pub name: String,
+    pub color: String,
+    pub chapters: Vec<ThreadChapter>,
+    pub word_count: u64,
+    /// Fraction of the manuscript's words that fall in this thread's chapters.
+    pub word_share: f64,
+    pub gaps: Vec<ThreadGap>,
+}
+
+fn threads_path(project: &Project) -> PathBuf {
+    project.path.join("plot-threads.json")
+}
+
+impl PlotThreads {
+    pub fn load(project: &Project) -> Result<Self, ProjectError> {
+        let path = threads_path(project);
+        if !path.exists() {
+            return Ok(PlotThreads::default());
+        }
+        read_json(&path)
+    }
+
+    pub fn save(&self, project: &Project) -> Result<(), ProjectError> {
+        let path = threads_path(project);
+        fs::write(&path, serde_json::to_string_pretty(self)?).with_path(&path)?;
+        Ok(())
+    }
+
+    fn thread_mut(&mut self, thread_id: &str) -> Result<&mut PlotThread, ProjectError> {
+        self.threads
+            .iter_mut()
+            .find(|t| t.id == thread_id)
+            .ok_or_else(|| ProjectError::PlotThreadNotFound(thread_id.to_string()))
+    }
+
+    /// Insert a new thread or replace the one with the same id.
+    pub fn upsert_thread(&mut self, thread: PlotThread) {
+        match self.threads.iter_mut().find(|t| t.id == thread.id) {
+            Some(existing) => *existing = thread,
+            None => self.threads.push(thread),
+        }
+    }
+
+    pub fn remove_thread(&mut self, thread_id: &str) -> Result<(), ProjectError> {
+        self.thread_mut(thread_id)?;
+        self.threads.retain(|t| t.id != thread_id);
+        Ok(())
+    }
+
+    pub fn assign_chapter(
+        &mut self,
+        project: &Project,
+        thread_id: &str,
+        chapter_id: &ChapterId,
+    ) -> Result<(), ProjectError> {
+        if !project.structure.nodes.contains_key(chapter_id.as_str()) {
+            return Err(ProjectError::ChapterNotFound(chapter_id.to_string()));
+        }
+        let thread = self.thread_mut(thread_id)?;
+        if !thread.chapters.iter().any(|c| chapter_id == c) {
+            thread.chapters.push(chapter_id.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn unassign_chapter(&mut self, thread_id: &str, chapter_id: &str) -> Result<(), ProjectError> {
+        self.thread_mut(thread_id)?
+            .chapters
+            .retain(|c| c != chapter_id);
+        Ok(())
+    }
+
+    /// Drop a deleted chapter from every thread.
+    pub fn forget_chapter(project: &Project, chapter_id: &str) -> Result<(), ProjectError> {
+        let mut threads = Self::load(project)?;
+        let mut changed = false;
+        for thread in &mut threads.threads {
+            let before = thread.chapters.len();
+            thread.chapters.retain(|c| c != chapter_id);
+            changed |= thread.chapters.len() != before;
+        }
+        if changed {
+            threads.save(project)?;
+        }
+        Ok(())
+    }
+
+    pub fn reports(&self, project: &Project) -> Vec<ThreadReport> {
+        let order = project.chapter_order();
+        let total_words = project.total_word_count();
+        let word_count_of = |id: &str| {
+            project
+                .structure
+                .nodes
+                .get(id)
+                .map(|n| n.word_count)
+                .unwrap_or(0)
+        };
+
+        self.threads
+            .iter()
+            .map(|thread| {
+                let chapters: Vec<ThreadChapter> = order
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, id)| thread.chapters.iter().any(|c| *id == c))
+                    .map(|(position, id)| ThreadChapter {
+                        chapter_id: id.to_string(),
+                        chapter_title: project
+                            .structure
+                            .nodes
+                            .get(id.as_str())
+                            .map(|n| n.title.clone())
+                            .unwrap_or_default(),
+                        position,
+                        word_count: word_count_of(id.as_str()),
+                    })
+                    .collect();
+
+                let gaps = chapters
+                    .windows(2)
+                    .filter(|pair| pair[1].position - pair[0].position > 1)
+                    .map(|pair| ThreadGap {
+                        after_chapter_id: pair[0].chapter_id.clone(),
+                        before_chapter_id: pair[1].chapter_id.clone(),
+                        chapter_ids: order[pair[0].position + 1..pair[1].position]
+                            .iter()
+                            .map(|id| id.to_string())
+                            .collect(),
+                    })
+                    .collect();
+
+                let word_count: u64 = chapters.iter().map(|c| c.word_count).sum();
+                ThreadReport {
+                    thread_id: thread.id.clone(),
+                    name: thread.name.clone(),
+                    color: thread.color.clone(),
+                    word_share: if total_words == 0 {
+                        0.0
+                    } else {
+                        word_count as f64 / total_words as f64
+                    },
+                    word_count,
+                    chapters,
+                    gaps,
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,301 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+use super::characters;
+use crate::manuscript::ids::ChapterId;
+use crate::manuscript::error::{read_json, WithPath};
+use crate::manuscript::project::{Project, ProjectError};
+
+/// Events carrying this tag are allowed to sit out of chronological order.
+pub const FLASHBACK_TAG: &str = "flashback";
+
+/// When an event happens inside the story: either a real calendar date or an
+/// ordinal position for invented calendars. Only times of the same kind can
+/// be compared with each other.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum StoryTime {
+    Date { at: NaiveDateTime },
+    Ordinal { position: f64 },
+}
+
+impl StoryTime {
+    fn compare(&self, other: &StoryTime) -> Option<Ordering> {
+        match (self, other) {
+            (StoryTime::Date { at: a }, StoryTime::Date { at: b }) => Some(a.cmp(b)),
+            (StoryTime::Ordinal { position: a }, StoryTime::Ordinal { position: b }) => {
+                a.partial_cmp(b)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineEvent {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub plot_thread: Option<String>,
+    #[serde(default)]
+    pub linked_chapters: Vec<String>,
+    pub start: StoryTime,
+    /// End of the event for events that span time; point events leave it empty.
+    #[serde(default)]
+    pub end: Option<StoryTime>,
+    #[serde(default)]
+    pub track: u32,
+    #[serde(default)]
+    pub color: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Ids of characters present at the event.
+    #[serde(default)]
+    pub characters: Vec<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+impl TimelineEvent {
+    pub fn is_flashback(&self) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(FLASHBACK_TAG))
+    }
+
+    fn end_or_start(&self) -> &StoryTime {
+        self.end.as_ref().unwrap_or(&self.start)
+    }
+
+    fn overlaps(&self, other: &TimelineEvent) -> bool {
+        let starts_before_other_ends = self.start.compare(other.end_or_start());
+        let ends_after_other_starts = self.end_or_start().compare(&other.start);
+        matches!(
+            (starts_before_other_ends, ends_after_other_starts),
+            (Some(Ordering::Less | Ordering::Equal), Some(Ordering::Greater | Ordering::Equal))
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimelineTrack {
+    pub id: u32,
+    pub name: String,
+    #[serde(default)]
+    pub color: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Timeline {
+    #[serde(default)]
+    pub tracks: Vec<TimelineTrack>,
+    #[serde(default)]
+    pub events: Vec<TimelineEvent>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineIssueKind {
+    Chronology,
+    LocationConflict,
+    InvalidRange,
+    MissingChapter,
+    UnknownCharacter,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TimelineIssue {
+    pub kind: TimelineIssueKind,
+    pub message: String,
+    pub event_ids: Vec<String>,
+    pub chapter_ids: Vec<String>,
+}
+
+fn timeline_path(project: &Project) -> PathBuf {
+    project.path.join("timeline.json")
+}
+
+impl Timeline {
+    pub fn load(project: &Project) -> Result<Self, ProjectError> {
+        let path = timeline_path(project);
+        if !path.exists() {
+            return Ok(Timeline::default());
+        }
+        read_json(&path)
+    }
+
+    pub fn save(&self, project: &Project) -> Result<(), ProjectError> {
+        let path = timeline_path(project);
+        fs::write(&path, serde_json::to_string_pretty(self)?).with_path(&path)?;
+        Ok(())
+    }
+
+    /// Insert a new event or replace the one with the same id.
+    pub fn upsert_event(&mut self, event: TimelineEvent) {
+        match self.events.iter_mut().find(|e| e.id == event.id) {
+            Some(existing) => *existing = event,
+            None => self.events.push(event),
+        }
+    }
+
+    pub fn remove_event(&mut self, event_id: &str) -> Result<(), ProjectError> {
+        let before = self.events.len();
+        self.events.retain(|e| e.id != event_id);
+        if self.events.len() == before {
+            return Err(ProjectError::TimelineEventNotFound(event_id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Look for contradictions between the timeline and the manuscript.
+    pub fn check(&self, project: &Project) -> Result<Vec<TimelineIssue>, ProjectError> {
+        let mut issues = Vec::new();
+        let chapter_order = project.chapter_order();
+        let character_names: HashMap<String, String> = characters::list_characters(project)?
+            .into_iter()
+            .map(|c| (c.id, c.name))
+            .collect();
+
+        for event in &self.events {
+            if let Some(end) = &event.end {
+                let problem = match end.compare(&event.start) {
+                    Some(Ordering::Less) => Some("ends before it starts"),
+                    None => Some("starts and ends on different kinds of time"),
+                    _ => None,
+                };
+                if let Some(problem) = problem {
+                    issues.push(TimelineIssue {
+                        kind: TimelineIssueKind::InvalidRange,
+                        message: format!("\"{}\" {}", event.title, problem),
+                        event_ids: vec![event.id.clone()],
+                        chapter_ids: Vec::new(),
+                    });
+                }
+            }
+            for chapter_id in &event.linked_chapters {
+                if !chapter_order.iter().any(|c| c == chapter_id) {
+                    issues.push(TimelineIssue {
+                        kind: TimelineIssueKind::MissingChapter,
+                        message: format!(
+                            "\"{}\" is linked to a chapter that no longer exists",
+                            event.title
+                        ),
+                        event_ids: vec![event.id.clone()],
+                        chapter_ids: vec![chapter_id.clone()],
+                    });
+                }
+            }
+            for character_id in &event.characters {
+                if !character_names.contains_key(character_id) {
+                    issues.push(TimelineIssue {
+                        kind: TimelineIssueKind::UnknownCharacter,
+                        message: format!(
+                            "\"{}\" lists a character that no longer exists",
+                            event.title
+                        ),
+                        event_ids: vec![event.id.clone()],
+                        chapter_ids: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        issues.extend(self.chronology_issues(project, &chapter_order));
+        issues.extend(self.location_conflicts(&character_names));
+        Ok(issues)
+    }
+
+    /// A chapter's events should not happen after the events of any later
+    /// chapter, unless one of them is tagged as a flashback.
+    fn chronology_issues(&self, project: &Project, chapter_order: &[ChapterId]) -> Vec<TimelineIssue> {
+        let events_for = |chapter_id: &ChapterId| -> Vec<&TimelineEvent> {
+            self.events
+                .iter()
+                .filter(|e| !e.is_flashback() && e.linked_chapters.iter().any(|c| chapter_id == c))
+                .collect()
+        };
+        let title_of = |chapter_id: &ChapterId| {
+            project
+                .structure
+                .nodes
+                .get(chapter_id.as_str())
+                .map(|n| n.title.clone())
+                .unwrap_or_default()
+        };
+
+        let mut issues = Vec::new();
+        let mut reported: HashSet<(String, String)> = HashSet::new();
+        for (i, earlier_chapter) in chapter_order.iter().enumerate() {
+            let earlier_events = events_for(earlier_chapter);
+            if earlier_events.is_empty() {
+                continue;
+            }
+            for later_chapter in &chapter_order[i + 1..] {
+                for later in events_for(later_chapter) {
+                    for earlier in &earlier_events {
+                        if earlier.id == later.id
+                            || earlier.start.compare(&later.start) != Some(Ordering::Greater)
+                            || !reported.insert((earlier.id.clone(), later.id.clone()))
+                        {
+                            continue;
+                        }
+                        issues.push(TimelineIssue {
+                            kind: TimelineIssueKind::Chronology,
+                            message: format!(
+                                "\"{}\" in \"{}\" happens after \"{}\" in the later chapter \"{}\" but is not tagged as a flashback",
+                                earlier.title,
+                                title_of(earlier_chapter),
+                                later.title,
+                                title_of(later_chapter),
+                            ),
+                            event_ids: vec![earlier.id.clone(), later.id.clone()],
+                            chapter_ids: vec![earlier_chapter.to_string(), later_chapter.to_string()],
+                        });
+                    }
+                }
+            }
+        }
+        issues
+    }
+
+    /// A character can't be at two different locations at overlapping times.
+    fn location_conflicts(&self, names: &HashMap<String, String>) -> Vec<TimelineIssue> {
+        let mut issues = Vec::new();
+        for (i, a) in self.events.iter().enumerate() {
+            for b in &self.events[i + 1..] {
+                let (Some(loc_a), Some(loc_b)) = (&a.location, &b.location) else {
+                    continue;
+                };
+                if loc_a.trim().eq_ignore_ascii_case(loc_b.trim()) || !a.overlaps(b) {
+                    continue;
+                }
+                for character_id in a.characters.iter().filter(|c| b.characters.contains(c)) {
+                    let name = names
+                        .get(character_id)
+                        .cloned()
+                        .unwrap_or_else(|| character_id.clone());
+                    issues.push(TimelineIssue {
+                        kind: TimelineIssueKind::LocationConflict,
+                        message: format!(
+                            "{} is in {} (\"{}\") and {} (\"{}\") at the same time",
+                            name, loc_a, a.title, loc_b, b.title
+                        ),
+                        event_ids: vec![a.id.clone(), b.id.clone()],
+                        chapter_ids: a
+                            .linked_chapters
+                            .iter()
+                            .chain(&b.linked_chapters)
+                            .cloned()
+                            .collect(),
+                    });
+                }
+            }
+        }
+        issues
+    }
+}
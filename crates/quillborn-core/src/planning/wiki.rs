@@ -0,0 +1,344 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::characters::{self, CharacterSheet};
+use super::notes;
+use crate::manuscript::chapter::Chapter;
+use crate::manuscript::ids::ChapterId;
+use crate::manuscript::project::{Project, ProjectError};
+
+/// A `[[Target]]` or `[[Target|alias]]` link found in a piece of text.
+/// `start` and `end` are byte offsets of the whole link including brackets.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct WikiLink {
+    pub target: String,
+    pub alias: Option<String>,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    Chapter,
+    Note,
+    Character,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct IndexEntry {
+    pub id: String,
+    pub title: String,
+    pub kind: EntryKind,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct IndexedLink {
+    pub source_id: String,
+    pub source_title: String,
+    pub source_kind: EntryKind,
+    pub target: String,
+    pub alias: Option<String>,
+    pub line_number: usize,
+    /// Id of the entry the link resolves to, or `None` when the link is broken.
+    pub resolved_id: Option<String>,
+}
+
+pub fn parse_links(text: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    let mut offset = 0;
+
+    while let Some(open) = text[offset..].find("[[") {
+        let inner_start = offset + open + 2;
+        let Some(close) = text[inner_start..].find("]]") else {
+            break;
+        };
+        let inner = &text[inner_start..inner_start + close];
+
+        // A nested opener or a line break means this isn't a link; retry from
+        // the next bracket pair.
+        if inner.contains("[[") || inner.contains('\n') {
+            offset = inner_start;
+            continue;
+        }
+
+        let (target, alias) = match inner.split_once('|') {
+            Some((target, alias)) => (target.trim(), Some(alias.trim().to_string())),
+            None => (inner.trim(), None),
+        };
+
+        let end = inner_start + close + 2;
+        if !target.is_empty() {
+            links.push(WikiLink {
+                target: target.to_string(),
+                alias,
+                start: offset + open,
+                end,
+            });
+        }
+        offset = end;
+    }
+
+    links
+}
+
+/// Replace the target of every link pointing at `old_title`, keeping aliases.
+/// Returns `None` when the text contains no such link.
+pub fn rewrite_link_targets(text: &str, old_title: &str, new_title: &str) -> Option<String> {
+    let old_key = old_title.trim().to_lowercase();
+    let links: Vec<WikiLink> = parse_links(text)
+        .into_iter()
+        .filter(|l| l.target.to_lowercase() == old_key)
+        .collect();
+    if links.is_empty() {
+        return None;
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut last = 0;
+    for link in links {
+        output.push_str(&text[last..link.start]);
+        match &link.alias {
+            Some(alias) => output.push_str(&format!("[[{}|{}]]", new_title, alias)),
+            None => output.push_str(&format!("[[{}]]", new_title)),
+        }
+        last = link.end;
+    }
+    output.push_str(&text[last..]);
+    Some(output)
+}
+
+fn character_text(sheet: &CharacterSheet) -> String {
+    [
+        sheet.description.as_str(),
+        sheet.arc_summary.as_str(),
+        sheet.speech_patterns.as_str(),
+    ]
+    .join("\n")
+}
+
+/// Every linkable entry in the project together with all links between them.
+pub struct LinkIndex {
+    pub entries: Vec<IndexEntry>,
+    pub links: Vec<IndexedLink>,
+}
+
+impl LinkIndex {
+    pub fn build(project: &Project) -> Result<Self, ProjectError> {
+        let sources = load_sources(project)?;
+        let entries: Vec<IndexEntry> = sources.iter().map(|(entry, _)| entry.clone()).collect();
+        let by_title = titles(&entries);
+
+        let mut links = Vec::new();
+        for (source, text) in &sources {
+            for (line_idx, line) in text.lines().enumerate() {
+                for link in parse_links(line) {
+                    links.push(IndexedLink {
+                        source_id: source.id.clone(),
+                        source_title: source.title.clone(),
+                        source_kind: source.kind,
+                        resolved_id: by_title.get(&link.target.to_lowercase()).cloned(),
+                        target: link.target,
+                        alias: link.alias,
+                        line_number: line_idx + 1,
+                    });
+                }
+            }
+        }
+
+        Ok(LinkIndex { entries, links })
+    }
+
+    pub fn broken_links(&self) -> Vec<IndexedLink> {
+        self.links
+            .iter()
+            .filter(|l| l.resolved_id.is_none())
+            .cloned()
+            .collect()
+    }
+
+    pub fn backlinks(&self, entry_id: &str) -> Vec<IndexedLink> {
+        self.links
+            .iter()
+            .filter(|l| l.resolved_id.as_deref() == Some(entry_id))
+            .cloned()
+            .collect()
+    }
+
+    pub fn outgoing(&self, entry_id: &str) -> Vec<IndexedLink> {
+        self.links
+            .iter()
+            .filter(|l| l.source_id == entry_id)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Every linkable entry paired with the text its links are parsed from.
+fn load_sources(project: &Project) -> Result<Vec<(IndexEntry, String)>, ProjectError> {
+    let mut sources = Vec::new();
+
+    for note in notes::list_notes(project)? {
+        let entry = IndexEntry {
+            id: note.id.clone(),
+            title: note.title.clone(),
+            kind: EntryKind::Note,
+        };
+        sources.push((entry, note.content));
+    }
+
+    for sheet in characters::list_characters(project)? {
+        let entry = IndexEntry {
+            id: sheet.id.clone(),
+            title: sheet.name.clone(),
+            kind: EntryKind::Character,
+        };
+        let text = character_text(&sheet);
+        sources.push((entry, text));
+    }
+
+    for chapter in load_chapters(project)? {
+        let entry = IndexEntry {
+            id: chapter.id.clone(),
+            title: chapter.title.clone(),
+            kind: EntryKind::Chapter,
+        };
+        sources.push((entry, chapter.content));
+    }
+
+    Ok(sources)
+}
+
+/// Map each lowercased title to the id a link with that target resolves to.
+/// `entries` must be in precedence order: notes take precedence over
+/// characters, and characters over chapters, when two entries share a title.
+fn titles(entries: &[IndexEntry]) -> HashMap<String, String> {
+    let mut by_title: HashMap<String, String> = HashMap::new();
+    for entry in entries {
+        by_title
+            .entry(entry.title.trim().to_lowercase())
+            .or_insert_with(|| entry.id.clone());
+    }
+    by_title
+}
+
+fn load_chapters(project: &Project) -> Result<Vec<Chapter>, ProjectError> {
+    let mut chapters = Vec::new();
+    for chapter_id in project.chapter_order() {
+        if project.chapter_path(&chapter_id).exists() {
+            chapters.push(project.load_chapter(&chapter_id)?);
+        }
+    }
+    Ok(chapters)
+}
+
+/// After `renamed_id` has been renamed from `old_title` to `new_title`, point
+/// the links that resolved to it at the new title. Links to `old_title` that
+/// resolved to another entry sharing that title are left alone. Returns the
+/// number of entries rewritten.
+pub fn rewrite_links(
+    project: &mut Project,
+    renamed_id: &str,
+    old_title: &str,
+    new_title: &str,
+) -> Result<usize, ProjectError> {
+    if old_title == new_title {
+        return Ok(0);
+    }
+
+    // Resolve the old title as it stood before the rename. Links resolve by
+    // title alone, so either every `[[old_title]]` pointed at the renamed
+    // entry or none did.
+    let mut entries: Vec<IndexEntry> = load_sources(project)?
+        .into_iter()
+        .map(|(entry, _)| entry)
+        .collect();
+    for entry in entries.iter_mut().filter(|e| e.id == renamed_id) {
+        entry.title = old_title.to_string();
+    }
+    if titles(&entries).get(&old_title.trim().to_lowercase()).map(String::as_str) != Some(renamed_id) {
+        return Ok(0);
+    }
+
+    let mut rewritten = 0;
+
+    for chapter in load_chapters(project)? {
+        if let Some(content) = rewrite_link_targets(&chapter.content, old_title, new_title) {
+            let chapter_id = ChapterId::parse(&chapter.id)?;
+            project.update_chapter_content(&chapter_id, &content)?;
+            rewritten += 1;
+        }
+    }
+
+    for mut note in notes::list_notes(project)? {
+        if let Some(content) = rewrite_link_targets(&note.content, old_title, new_title) {
+            note.content = content;
+            note.modified_at = chrono::Utc::now();
+            notes::write_note(project, &note)?;
+            rewritten += 1;
+        }
+    }
+
+    for mut sheet in characters::list_characters(project)? {
+        let mut changed = false;
+        for field in [
+            &mut sheet.description,
+            &mut sheet.arc_summary,
+            &mut sheet.speech_patterns,
+        ] {
+            if let Some(text) = rewrite_link_targets(field, old_title, new_title) {
+                *field = text;
+                changed = true;
+            }
+        }
+        if changed {
+            sheet.modified_at = chrono::Utc::now();
+            characters::write_character(project, &sheet)?;
+            rewritten += 1;
+        }
+    }
+
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renaming_a_chapter_leaves_links_to_a_same_titled_note() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "Links", "Author").unwrap();
+        let chapter = project.add_chapter("Harbour", None).unwrap();
+        let chapter_id = ChapterId::parse(&chapter.id).unwrap();
+        let note = notes::create_note(&project, "Harbour", "locations").unwrap();
+        let other = notes::create_note(&project, "Gazetteer", "locations").unwrap();
+        notes::update_note(&project, &other.id, "See [[Harbour]].", None).unwrap();
+
+        project.rename_chapter(&chapter_id, "The Docks").unwrap();
+
+        // The note wins resolution, so its backlink must survive the rename.
+        let other = notes::load_note(&project, &other.id).unwrap();
+        assert_eq!(other.content, "See [[Harbour]].");
+        assert_eq!(LinkIndex::build(&project).unwrap().backlinks(&note.id).len(), 1);
+    }
+
+    #[test]
+    fn renaming_rewrites_chapters_and_keeps_word_counts_current() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "Links", "Author").unwrap();
+        let chapter = project.add_chapter("Opening", None).unwrap();
+        let chapter_id = ChapterId::parse(&chapter.id).unwrap();
+        project
+            .update_chapter_content(&chapter_id, "They sailed for [[Harbour|the harbour]].")
+            .unwrap();
+        let mut note = notes::create_note(&project, "Harbour", "locations").unwrap();
+
+        note = notes::rename_note(&mut project, &note.id, "Port of Saints").unwrap();
+
+        let chapter = project.load_chapter(&chapter_id).unwrap();
+        assert_eq!(chapter.content, "They sailed for [[Port of Saints|the harbour]].");
+        assert_eq!(project.structure.nodes[chapter_id.as_str()].word_count, chapter.word_count);
+        assert_eq!(LinkIndex::build(&project).unwrap().backlinks(&note.id).len(), 1);
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+use crate::manuscript::error::read_toml;
+use crate::manuscript::project::ProjectError;
+
+/// Version of the host ABI described in `plugins/mod.rs`. Bumped whenever an
+/// import or entry point changes shape; plugins built against another
+/// version are refused rather than half-working.
+pub const API_VERSION: u32 = 1;
+
+pub const MANIFEST_FILE: &str = "plugin.toml";
+
+fn default_module() -> String {
+    "plugin.wasm".to_string()
+}
+
+/// What a plugin may do. Anything not granted here traps when the plugin
+/// tries it.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// List chapters and read their text.
+    ReadChapters,
+    /// Read and write notes in the plugin's own `plugin-<id>` category.
+    Notes,
+    RegisterExport,
+    RegisterCheck,
+    RegisterCommand,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ReadChapters => "read_chapters",
+            Permission::Notes => "notes",
+            Permission::RegisterExport => "register_export",
+            Permission::RegisterCheck => "register_check",
+            Permission::RegisterCommand => "register_command",
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// `plugin.toml`, sitting next to the plugin's WASM module.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginManifest {
+    pub id: String,
+    pub name: String,
+    /// The plugin's own `major.minor.patch` version.
+    pub version: String,
+    /// Host ABI the plugin was built against; must equal [`API_VERSION`].
+    pub api_version: u32,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: String,
+    /// WASM file name, relative to the plugin directory.
+    #[serde(default = "default_module")]
+    pub module: String,
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+}
+
+impl PluginManifest {
+    pub fn load(plugin_dir: &Path) -> Result<Self, ProjectError> {
+        let path = plugin_dir.join(MANIFEST_FILE);
+        let manifest: PluginManifest = read_toml(&path)?;
+        manifest.validate().map_err(|message| ProjectError::InvalidPlugin {
+            path: path.display().to_string(),
+            message,
+        })?;
+        Ok(manifest)
+    }
+
+    pub fn allows(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
+
+    /// The note category the plugin's notes live in.
+    pub fn notes_category(&self) -> String {
+        format!("plugin-{}", self.id)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let id_ok = !self.id.is_empty()
+            && self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !id_ok {
+            return Err(format!(
+                "id \"{}\" must be lowercase letters, digits and dashes",
+                self.id
+            ));
+        }
+
+        let parts: Vec<&str> = self.version.split('.').collect();
+        if parts.len() != 3 || parts.iter().any(|p| p.parse::<u32>().is_err()) {
+            return Err(format!(
+                "version \"{}\" is not major.minor.patch",
+                self.version
+            ));
+        }
+
+        if self.api_version != API_VERSION {
+            return Err(format!(
+                "built for plugin API {}, this version of Quillborn supports {}",
+                self.api_version, API_VERSION
+            ));
+        }
+
+        let module = Path::new(&self.module);
+        if module.components().count() != 1 || module.file_name().is_none() {
+            return Err(format!(
+                "module \"{}\" must be a file in the plugin directory",
+                self.module
+            ));
+        }
+        Ok(())
+    }
+}
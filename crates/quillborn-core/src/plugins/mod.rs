@@ -0,0 +1,483 @@
+//! Sandboxed WASM plugins.
+//!
+//! A plugin is a directory holding a `plugin.toml` manifest and a WASM
+//! module. The module runs under `wasmi` with fuel and memory limits and can
+//! only reach the project through the host functions in the `quillborn`
+//! import module, each gated by a manifest permission:
+//!
+//! | import | permission |
+//! |---|---|
+//! | `log(ptr, len)` | — |
+//! | `register_export(ptr, len)` — `{id, name, extension}` | `register_export` |
+//! | `register_check(ptr, len)` — `{id, name, description?}` | `register_check` |
+//! | `register_command(ptr, len)` — `{id, label}` | `register_command` |
+//! | `list_chapters() -> packed` | `read_chapters` |
+//! | `read_chapter(ptr, len) -> packed` | `read_chapters` |
+//! | `note_read(ptr, len) -> packed` | `notes` |
+//! | `note_write(key_ptr, key_len, value_ptr, value_len)` | `notes` |
+//!
+//! Strings and JSON cross the boundary as UTF-8 in the plugin's linear
+//! memory. Buffers handed back by the host are allocated with the plugin's
+//! `alloc(len) -> ptr` export and returned packed as `ptr << 32 | len`.
+//!
+//! The module must export `memory` and `alloc`, and may export `register()`
+//! plus the entry points `export`, `check` and `command`, each taking a JSON
+//! request `(ptr, len)` and returning a packed buffer.
+
+pub mod manifest;
+mod runtime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::export::collect_chapters_in_order;
+use crate::export::hooks::ExportHooks;
+use crate::manuscript::error::WithPath;
+use crate::manuscript::paths;
+use crate::manuscript::project::{Project, ProjectError};
+use manifest::{PluginManifest, MANIFEST_FILE};
+use runtime::Runtime;
+
+/// An export format a plugin registered. The plugin's `export` entry point
+/// returns the file contents.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportFormat {
+    pub id: String,
+    pub name: String,
+    /// File extension without the dot, e.g. `fountain`.
+    pub extension: String,
+}
+
+/// A manuscript check a plugin registered, run alongside the built-in ones.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnalysisCheck {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginCommand {
+    pub id: String,
+    pub label: String,
+}
+
+/// Everything a plugin registered from its `register` export.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Contributions {
+    pub exports: Vec<ExportFormat>,
+    pub checks: Vec<AnalysisCheck>,
+    pub commands: Vec<PluginCommand>,
+}
+
+/// An issue reported by a plugin check.
+#[derive(Debug, Serialize, Clone)]
+pub struct PluginIssue {
+    pub plugin_id: String,
+    pub check_id: String,
+    pub message: String,
+    pub chapter_id: Option<String>,
+    pub line: Option<usize>,
+}
+
+/// A plugin check that could not run to completion.
+#[derive(Debug, Serialize)]
+pub struct PluginCheckError {
+    pub plugin_id: String,
+    pub check_id: String,
+    pub error: ProjectError,
+}
+
+/// Everything the plugin checks reported, plus the checks that failed.
+#[derive(Debug, Serialize, Default)]
+pub struct CheckResults {
+    pub issues: Vec<PluginIssue>,
+    pub errors: Vec<PluginCheckError>,
+}
+
+/// The shape a plugin's `check` entry point returns for each issue.
+#[derive(Deserialize)]
+struct ReportedIssue {
+    message: String,
+    #[serde(default)]
+    chapter_id: Option<String>,
+    #[serde(default)]
+    line: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct Plugin {
+    #[serde(flatten)]
+    pub manifest: PluginManifest,
+    pub contributions: Contributions,
+    #[serde(skip)]
+    runtime: Runtime,
+}
+
+impl Plugin {
+    pub fn load(plugin_dir: &Path) -> Result<Self, ProjectError> {
+        let manifest = PluginManifest::load(plugin_dir)?;
+        let module_path = plugin_dir.join(&manifest.module);
+        let wasm = fs::read(&module_path).with_path(&module_path)?;
+        let runtime = Runtime::new(manifest.clone(), &wasm, &module_path.display().to_string())?;
+        let contributions = runtime.register()?;
+        Ok(Plugin {
+            manifest,
+            contributions,
+            runtime,
+        })
+    }
+}
+
+/// A plugin directory that could not be loaded. The rest still are.
+#[derive(Debug, Serialize)]
+pub struct PluginLoadError {
+    pub path: String,
+    pub error: ProjectError,
+}
+
+/// The plugins installed in one directory, each in its own subdirectory.
+#[derive(Serialize, Default)]
+pub struct PluginHost {
+    pub plugins: Vec<Plugin>,
+    pub errors: Vec<PluginLoadError>,
+}
+
+impl PluginHost {
+    /// Load every plugin under `plugins_dir`. A missing directory simply
+    /// means no plugins are installed.
+    pub fn load(plugins_dir: &Path) -> Result<Self, ProjectError> {
+        let mut host = PluginHost::default();
+        if !plugins_dir.is_dir() {
+            return Ok(host);
+        }
+
+        let mut dirs: Vec<_> = fs::read_dir(plugins_dir)
+            .with_path(plugins_dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.join(MANIFEST_FILE).is_file())
+            .collect();
+        dirs.sort();
+
+        for dir in dirs {
+            let path = dir.display().to_string();
+            match Plugin::load(&dir) {
+                Ok(plugin) if host.plugin(&plugin.manifest.id).is_ok() => {
+                    host.errors.push(PluginLoadError {
+                        error: ProjectError::InvalidPlugin {
+                            path: path.clone(),
+                            message: format!("duplicate plugin id \"{}\"", plugin.manifest.id),
+                        },
+                        path,
+                    });
+                }
+                Ok(plugin) => host.plugins.push(plugin),
+                Err(error) => host.errors.push(PluginLoadError { path, error }),
+            }
+        }
+        Ok(host)
+    }
+
+    pub fn plugin(&self, plugin_id: &str) -> Result<&Plugin, ProjectError> {
+        self.plugins
+            .iter()
+            .find(|p| p.manifest.id == plugin_id)
+            .ok_or_else(|| ProjectError::PluginNotFound(plugin_id.to_string()))
+    }
+
+    /// Export the manuscript through a plugin-registered format and return
+    /// the path written. Output paths follow the same rules as the built-in
+    /// exporters.
+    pub fn export(
+        &self,
+        project: &Project,
+        format_id: &str,
+        output_path: &str,
+        allowed_dirs: &[PathBuf],
+    ) -> Result<String, ProjectError> {
+        let (plugin, format) = self
+            .plugins
+            .iter()
+            .find_map(|p| {
+                p.contributions
+                    .exports
+                    .iter()
+                    .find(|f| f.id == format_id)
+                    .map(|f| (p, f))
+            })
+            .ok_or_else(|| ProjectError::PluginContributionNotFound {
+                kind: "export format",
+                id: format_id.to_string(),
+            })?;
+
+        let out_path = paths::resolve_export_path(&project.path, output_path, &[format.extension.as_str()], allowed_dirs)?;
+        let hooks = ExportHooks::load(project, &format.id)?;
+        let request = json!({
+            "format": format.id,
+            "title": project.metadata.title,
+            "author": project.metadata.author,
+            "chapters": hooks.apply_to_chapters(collect_chapters_in_order(project)?)?,
+        });
+        // Post hooks only make sense for text; binary output is left alone.
+        let contents = match String::from_utf8(plugin.runtime.call(project, "export", &request)?) {
+            Ok(text) => hooks.apply_to_document(text)?.into_bytes(),
+            Err(binary) => binary.into_bytes(),
+        };
+        fs::write(&out_path, contents).with_path(&out_path)?;
+        Ok(out_path.to_string_lossy().to_string())
+    }
+
+    /// Run every check registered by every plugin. A check that fails is
+    /// reported in [`CheckResults::errors`] and the others still run.
+    pub fn run_checks(&self, project: &Project) -> CheckResults {
+        let mut results = CheckResults::default();
+        for plugin in &self.plugins {
+            for check in &plugin.contributions.checks {
+                let reported = plugin
+                    .runtime
+                    .call(project, "check", &json!({ "check": check.id }))
+                    .and_then(|output| {
+                        serde_json::from_slice::<Vec<ReportedIssue>>(&output).map_err(|e| ProjectError::PluginFailed {
+                            plugin: plugin.manifest.id.clone(),
+                            message: format!("check {} returned invalid JSON: {}", check.id, e),
+                        })
+                    });
+                match reported {
+                    Ok(reported) => results.issues.extend(reported.into_iter().map(|issue| PluginIssue {
+                        plugin_id: plugin.manifest.id.clone(),
+                        check_id: check.id.clone(),
+                        message: issue.message,
+                        chapter_id: issue.chapter_id,
+                        line: issue.line,
+                    })),
+                    Err(error) => results.errors.push(PluginCheckError {
+                        plugin_id: plugin.manifest.id.clone(),
+                        check_id: check.id.clone(),
+                        error,
+                    }),
+                }
+            }
+        }
+        results
+    }
+
+    /// Run a plugin command and return whatever JSON it produced, or `null`
+    /// if it returned nothing.
+    pub fn run_command(
+        &self,
+        project: &Project,
+        plugin_id: &str,
+        command_id: &str,
+        args: Value,
+    ) -> Result<Value, ProjectError> {
+        let plugin = self.plugin(plugin_id)?;
+        if !plugin.contributions.commands.iter().any(|c| c.id == command_id) {
+            return Err(ProjectError::PluginContributionNotFound {
+                kind: "command",
+                id: command_id.to_string(),
+            });
+        }
+
+        let output = plugin
+            .runtime
+            .call(project, "command", &json!({ "command": command_id, "args": args }))?;
+        if output.is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_slice(&output).map_err(|e| ProjectError::PluginFailed {
+            plugin: plugin_id.to_string(),
+            message: format!("command {} returned invalid JSON: {}", command_id, e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manuscript::ids::ChapterId;
+
+    /// A module with the host imports, a memory, a bump allocator and
+    /// whatever functions `body` adds.
+    fn module(body: &str) -> String {
+        format!(
+            r#"(module
+                (import "quillborn" "log" (func $log (param i32 i32)))
+                (import "quillborn" "register_check" (func $register_check (param i32 i32)))
+                (import "quillborn" "list_chapters" (func $list_chapters (result i64)))
+                (memory (export "memory") 1)
+                (global $next (mut i32) (i32.const 4096))
+                (func (export "alloc") (param $len i32) (result i32)
+                    (local $ptr i32)
+                    (local.set $ptr (global.get $next))
+                    (global.set $next (i32.add (global.get $next) (local.get $len)))
+                    (local.get $ptr))
+                (data (i32.const 0) "{{\"id\":\"lint\",\"name\":\"Lint\"}}")
+                (data (i32.const 64) "[{{\"message\":\"too long\"}}]")
+                {}
+            )"#,
+            body
+        )
+    }
+
+    /// Registers the `lint` check, whose entry point returns one issue.
+    const CHECKER: &str = r#"
+        (func (export "register") (call $register_check (i32.const 0) (i32.const 27)))
+        (func (export "check") (param i32 i32) (result i64)
+            (i64.or (i64.shl (i64.const 64) (i64.const 32)) (i64.const 24)))
+    "#;
+
+    fn install(plugins: &Path, id: &str, permissions: &[&str], wat: &str) {
+        let dir = plugins.join(id);
+        fs::create_dir_all(&dir).unwrap();
+        let permissions: Vec<String> = permissions.iter().map(|p| format!("\"{}\"", p)).collect();
+        fs::write(
+            dir.join(MANIFEST_FILE),
+            format!(
+                "id = \"{}\"\nname = \"{}\"\nversion = \"1.0.0\"\napi_version = {}\npermissions = [{}]\n",
+                id,
+                id,
+                manifest::API_VERSION,
+                permissions.join(", ")
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("plugin.wasm"), wat::parse_str(wat).unwrap()).unwrap();
+    }
+
+    fn setup() -> (tempfile::TempDir, Project) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "Plugins", "Author").unwrap();
+        let chapter = project.add_chapter("One", None).unwrap();
+        project
+            .update_chapter_content(&ChapterId::parse(&chapter.id).unwrap(), "Some text.")
+            .unwrap();
+        (dir, project)
+    }
+
+    /// Call an entry point of the only plugin installed under `plugins`.
+    fn call(plugins: &Path, project: &Project, entry: &str) -> Result<Vec<u8>, ProjectError> {
+        let host = PluginHost::load(plugins).unwrap();
+        assert!(host.errors.is_empty(), "{:?}", host.errors);
+        host.plugins[0].runtime.call(project, entry, &json!({}))
+    }
+
+    #[test]
+    fn host_calls_need_their_permission() {
+        let (dir, project) = setup();
+        let plugins = dir.path().join("plugins");
+        install(&plugins, "granted", &["register_check"], &module(CHECKER));
+        install(&plugins, "refused", &[], &module(CHECKER));
+        install(
+            &plugins,
+            "reader",
+            &[],
+            &module(r#"(func (export "run") (param i32 i32) (result i64) (call $list_chapters))"#),
+        );
+
+        let host = PluginHost::load(&plugins).unwrap();
+        assert_eq!(host.plugins.len(), 2);
+        assert_eq!(host.plugins[0].contributions.checks[0].id, "lint");
+        assert!(matches!(
+            &host.errors[0].error,
+            ProjectError::PluginPermissionDenied { plugin, permission } if plugin == "refused" && permission == "register_check"
+        ));
+        let reader = host.plugin("reader").unwrap();
+        assert!(matches!(
+            reader.runtime.call(&project, "run", &json!({})),
+            Err(ProjectError::PluginPermissionDenied { permission, .. }) if permission == "read_chapters"
+        ));
+    }
+
+    #[test]
+    fn endless_loops_run_out_of_fuel() {
+        let (dir, project) = setup();
+        let plugins = dir.path().join("plugins");
+        install(
+            &plugins,
+            "spinner",
+            &[],
+            &module(r#"(func (export "run") (param i32 i32) (result i64) (loop $again (br $again)) (i64.const 0))"#),
+        );
+        assert!(matches!(
+            call(&plugins, &project, "run"),
+            Err(ProjectError::PluginTimeout(id)) if id == "spinner"
+        ));
+    }
+
+    #[test]
+    fn memory_is_capped_at_64_mib() {
+        let (dir, project) = setup();
+        let plugins = dir.path().join("plugins");
+        // Grow by `pages` and trap if that was refused.
+        let grow = |pages: u32| {
+            format!(
+                r#"(func (export "run") (param i32 i32) (result i64)
+                    (if (i32.eq (memory.grow (i32.const {})) (i32.const -1)) (then unreachable))
+                    (i64.const 0))"#,
+                pages
+            )
+        };
+        install(&plugins, "small", &[], &module(&grow(64)));
+        assert_eq!(call(&plugins, &project, "run").unwrap(), Vec::<u8>::new());
+
+        fs::remove_dir_all(&plugins).unwrap();
+        // 1024 pages of 64 KiB on top of the first one is just over 64 MiB.
+        install(&plugins, "greedy", &[], &module(&grow(1024)));
+        assert!(matches!(call(&plugins, &project, "run"), Err(ProjectError::PluginFailed { .. })));
+    }
+
+    #[test]
+    fn out_of_bounds_buffers_are_refused() {
+        let (dir, project) = setup();
+        let plugins = dir.path().join("plugins");
+        for (id, body) in [
+            // A log message past the end of memory.
+            ("far-log", r#"(call $log (i32.const 65530) (i32.const 100)) (i64.const 0)"#),
+            ("negative-log", r#"(call $log (i32.const 0) (i32.const -1)) (i64.const 0)"#),
+            // A result pointing past the end of memory.
+            ("far-result", r#"(i64.or (i64.shl (i64.const 65530) (i64.const 32)) (i64.const 100))"#),
+            // A result longer than any transfer may be.
+            ("huge-result", r#"(i64.const 0xffffffff)"#),
+        ] {
+            install(
+                &plugins,
+                id,
+                &[],
+                &module(&format!(r#"(func (export "run") (param i32 i32) (result i64) {})"#, body)),
+            );
+            assert!(
+                matches!(call(&plugins, &project, "run"), Err(ProjectError::PluginFailed { .. })),
+                "{} was not refused",
+                id
+            );
+            fs::remove_dir_all(&plugins).unwrap();
+        }
+    }
+
+    #[test]
+    fn a_failing_check_does_not_hide_the_others() {
+        let (dir, project) = setup();
+        let plugins = dir.path().join("plugins");
+        install(&plugins, "a-good", &["register_check"], &module(CHECKER));
+        install(
+            &plugins,
+            "b-broken",
+            &["register_check"],
+            &module(
+                r#"(func (export "register") (call $register_check (i32.const 0) (i32.const 27)))
+                   (func (export "check") (param i32 i32) (result i64) unreachable)"#,
+            ),
+        );
+
+        let results = PluginHost::load(&plugins).unwrap().run_checks(&project);
+        assert_eq!(results.issues.len(), 1);
+        assert_eq!(results.issues[0].plugin_id, "a-good");
+        assert_eq!(results.issues[0].message, "too long");
+        assert_eq!(results.errors.len(), 1);
+        assert_eq!(results.errors[0].plugin_id, "b-broken");
+    }
+}
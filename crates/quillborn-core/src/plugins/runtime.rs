@@ -0,0 +1,364 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasmi::core::TrapCode;
+use wasmi::{
+    Caller, Config, Engine, Error, Extern, Instance, Linker, Memory, Module, Store, StoreLimits,
+    StoreLimitsBuilder,
+};
+
+use super::manifest::{Permission, PluginManifest};
+use super::{AnalysisCheck, Contributions, ExportFormat, PluginCommand};
+use crate::manuscript::ids::ChapterId;
+use crate::manuscript::project::{ChapterStatus, Project, ProjectError};
+use crate::planning::notes;
+
+/// Instructions a single call into a plugin may execute before it is
+/// stopped, which stands in for a timeout.
+const FUEL_PER_CALL: u64 = 100_000_000;
+const MAX_MEMORY_BYTES: usize = 64 << 20;
+/// Largest single buffer passed across the host boundary in either direction.
+const MAX_TRANSFER_BYTES: usize = 16 << 20;
+const HOST_MODULE: &str = "quillborn";
+
+/// Everything a running plugin can reach. The project is a copy; the only
+/// way to touch disk is through the host functions below.
+struct HostState {
+    manifest: PluginManifest,
+    project: Option<Project>,
+    contributions: Contributions,
+    /// Set when a host function refused a call, so the trap it raises can be
+    /// reported as a permission error rather than a generic failure.
+    denied: Option<Permission>,
+    limits: StoreLimits,
+}
+
+#[derive(Serialize)]
+struct ChapterSummary<'a> {
+    id: &'a str,
+    title: &'a str,
+    status: &'a ChapterStatus,
+    word_count: u64,
+}
+
+pub(crate) struct Runtime {
+    manifest: PluginManifest,
+    engine: Engine,
+    module: Module,
+    linker: Linker<HostState>,
+}
+
+impl Runtime {
+    pub(crate) fn new(manifest: PluginManifest, wasm: &[u8], path: &str) -> Result<Self, ProjectError> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm).map_err(|e| ProjectError::InvalidPlugin {
+            path: path.to_string(),
+            message: e.to_string(),
+        })?;
+        let linker = host_linker(&engine).map_err(|e| ProjectError::InvalidPlugin {
+            path: path.to_string(),
+            message: e.to_string(),
+        })?;
+        Ok(Runtime {
+            manifest,
+            engine,
+            module,
+            linker,
+        })
+    }
+
+    /// Run the plugin's optional `register` export and collect what it
+    /// contributed. No project is available at this point.
+    pub(crate) fn register(&self) -> Result<Contributions, ProjectError> {
+        let (mut store, instance) = self.instantiate(None)?;
+        if let Ok(register) = instance.get_typed_func::<(), ()>(&store, "register") {
+            if let Err(e) = register.call(&mut store, ()) {
+                return Err(self.failure(&store, e));
+            }
+        }
+        Ok(store.into_data().contributions)
+    }
+
+    /// Call an entry point with a JSON request and return the raw bytes it
+    /// hands back. Every call gets a fresh instance, so plugins keep no state
+    /// between calls except what they store in their notes.
+    pub(crate) fn call<T: Serialize>(
+        &self,
+        project: &Project,
+        entry: &str,
+        request: &T,
+    ) -> Result<Vec<u8>, ProjectError> {
+        let (mut store, instance) = self.instantiate(Some(project.clone()))?;
+        let input = serde_json::to_vec(request)?;
+        let result = (|| {
+            let func = instance.get_typed_func::<(i32, i32), i64>(&store, entry)?;
+            let alloc = instance.get_typed_func::<i32, i32>(&store, "alloc")?;
+            let memory = instance
+                .get_memory(&store, "memory")
+                .ok_or_else(|| Error::new("plugin does not export its memory"))?;
+            let ptr = alloc.call(&mut store, input.len() as i32)?;
+            memory
+                .write(&mut store, ptr as u32 as usize, &input)
+                .map_err(|e| Error::new(e.to_string()))?;
+            let packed = func.call(&mut store, (ptr, input.len() as i32))?;
+            let (ptr, len) = unpack(packed)?;
+            let mut output = vec![0; len];
+            memory
+                .read(&store, ptr, &mut output)
+                .map_err(|e| Error::new(e.to_string()))?;
+            Ok(output)
+        })();
+        result.map_err(|e| self.failure(&store, e))
+    }
+
+    fn instantiate(&self, project: Option<Project>) -> Result<(Store<HostState>, Instance), ProjectError> {
+        let state = HostState {
+            manifest: self.manifest.clone(),
+            project,
+            contributions: Contributions::default(),
+            denied: None,
+            limits: StoreLimitsBuilder::new()
+                .memory_size(MAX_MEMORY_BYTES)
+                .instances(1)
+                .build(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store
+            .set_fuel(FUEL_PER_CALL)
+            .map_err(|e| self.failure(&store, Error::new(e.to_string())))?;
+
+        let instance = self
+            .linker
+            .instantiate(&mut store, &self.module)
+            .and_then(|pre| pre.start(&mut store));
+        match instance {
+            Ok(instance) => Ok((store, instance)),
+            Err(e) => Err(self.failure(&store, e)),
+        }
+    }
+
+    fn failure(&self, store: &Store<HostState>, error: Error) -> ProjectError {
+        let plugin = self.manifest.id.clone();
+        if let Some(permission) = store.data().denied {
+            return ProjectError::PluginPermissionDenied {
+                plugin,
+                permission: permission.to_string(),
+            };
+        }
+        if error.as_trap_code() == Some(TrapCode::OutOfFuel) {
+            return ProjectError::PluginTimeout(plugin);
+        }
+        ProjectError::PluginFailed {
+            plugin,
+            message: error.to_string(),
+        }
+    }
+}
+
+fn pack(ptr: i32, len: usize) -> i64 {
+    ((ptr as u32 as i64) << 32) | len as i64
+}
+
+fn unpack(packed: i64) -> Result<(usize, usize), Error> {
+    let ptr = (packed as u64 >> 32) as usize;
+    let len = (packed as u64 & 0xffff_ffff) as usize;
+    if len > MAX_TRANSFER_BYTES {
+        return Err(Error::new("plugin returned an oversized buffer"));
+    }
+    Ok((ptr, len))
+}
+
+fn memory(caller: &Caller<'_, HostState>) -> Result<Memory, Error> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| Error::new("plugin does not export its memory"))
+}
+
+fn read_bytes(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Result<Vec<u8>, Error> {
+    let len = usize::try_from(len).map_err(|_| Error::new("negative buffer length"))?;
+    if len > MAX_TRANSFER_BYTES {
+        return Err(Error::new("buffer passed to the host is too large"));
+    }
+    let mut buffer = vec![0; len];
+    memory(caller)?
+        .read(caller, ptr as u32 as usize, &mut buffer)
+        .map_err(|e| Error::new(e.to_string()))?;
+    Ok(buffer)
+}
+
+fn read_string(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Result<String, Error> {
+    String::from_utf8(read_bytes(caller, ptr, len)?).map_err(|e| Error::new(e.to_string()))
+}
+
+fn read_json<T: DeserializeOwned>(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Result<T, Error> {
+    serde_json::from_slice(&read_bytes(caller, ptr, len)?).map_err(|e| Error::new(e.to_string()))
+}
+
+/// Copy a JSON value into a buffer obtained from the plugin's `alloc` and
+/// return it packed as `ptr << 32 | len`.
+fn write_json<T: Serialize>(caller: &mut Caller<'_, HostState>, value: &T) -> Result<i64, Error> {
+    let bytes = serde_json::to_vec(value).map_err(|e| Error::new(e.to_string()))?;
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(Extern::into_func)
+        .ok_or_else(|| Error::new("plugin does not export alloc"))?
+        .typed::<i32, i32>(&*caller)?;
+    let ptr = alloc.call(&mut *caller, bytes.len() as i32)?;
+    memory(caller)?
+        .write(&mut *caller, ptr as u32 as usize, &bytes)
+        .map_err(|e| Error::new(e.to_string()))?;
+    Ok(pack(ptr, bytes.len()))
+}
+
+fn require(caller: &mut Caller<'_, HostState>, permission: Permission) -> Result<(), Error> {
+    if caller.data().manifest.allows(permission) {
+        return Ok(());
+    }
+    caller.data_mut().denied = Some(permission);
+    Err(Error::new(format!("missing the {} permission", permission)))
+}
+
+fn project(caller: &Caller<'_, HostState>) -> Result<Project, Error> {
+    caller
+        .data()
+        .project
+        .clone()
+        .ok_or_else(|| Error::new("no project is open during registration"))
+}
+
+fn host_error(error: ProjectError) -> Error {
+    Error::new(error.to_string())
+}
+
+fn host_linker(engine: &Engine) -> Result<Linker<HostState>, wasmi::errors::LinkerError> {
+    let mut linker = Linker::new(engine);
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "log",
+        |caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<(), Error> {
+            let message = read_string(&caller, ptr, len)?;
+            eprintln!("[plugin {}] {}", caller.data().manifest.id, message);
+            Ok(())
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "register_export",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<(), Error> {
+            require(&mut caller, Permission::RegisterExport)?;
+            let format: ExportFormat = read_json(&caller, ptr, len)?;
+            caller.data_mut().contributions.exports.push(format);
+            Ok(())
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "register_check",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<(), Error> {
+            require(&mut caller, Permission::RegisterCheck)?;
+            let check: AnalysisCheck = read_json(&caller, ptr, len)?;
+            caller.data_mut().contributions.checks.push(check);
+            Ok(())
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "register_command",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<(), Error> {
+            require(&mut caller, Permission::RegisterCommand)?;
+            let command: PluginCommand = read_json(&caller, ptr, len)?;
+            caller.data_mut().contributions.commands.push(command);
+            Ok(())
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "list_chapters",
+        |mut caller: Caller<'_, HostState>| -> Result<i64, Error> {
+            require(&mut caller, Permission::ReadChapters)?;
+            let project = project(&caller)?;
+            let chapters: Vec<ChapterSummary> = project
+                .chapter_order()
+                .iter()
+                .filter_map(|id| project.structure.nodes.get(id.as_str()))
+                .map(|node| ChapterSummary {
+                    id: &node.id,
+                    title: &node.title,
+                    status: &node.status,
+                    word_count: node.word_count,
+                })
+                .collect();
+            write_json(&mut caller, &chapters)
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "read_chapter",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<i64, Error> {
+            require(&mut caller, Permission::ReadChapters)?;
+            let project = project(&caller)?;
+            let chapter = match ChapterId::parse(&read_string(&caller, ptr, len)?) {
+                Ok(id) if project.structure.nodes.contains_key(id.as_str()) => {
+                    Some(project.load_chapter(&id).map_err(host_error)?)
+                }
+                _ => None,
+            };
+            write_json(&mut caller, &chapter)
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "note_read",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<i64, Error> {
+            require(&mut caller, Permission::Notes)?;
+            let project = project(&caller)?;
+            let key = read_string(&caller, ptr, len)?;
+            let category = caller.data().manifest.notes_category();
+            let content = notes::list_notes(&project)
+                .map_err(host_error)?
+                .into_iter()
+                .find(|n| n.category == category && n.title == key)
+                .map(|n| n.content);
+            write_json(&mut caller, &content)
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "note_write",
+        |mut caller: Caller<'_, HostState>,
+         key_ptr: i32,
+         key_len: i32,
+         value_ptr: i32,
+         value_len: i32|
+         -> Result<(), Error> {
+            require(&mut caller, Permission::Notes)?;
+            let project = project(&caller)?;
+            let key = read_string(&caller, key_ptr, key_len)?;
+            let value = read_string(&caller, value_ptr, value_len)?;
+            let category = caller.data().manifest.notes_category();
+            let existing = notes::list_notes(&project)
+                .map_err(host_error)?
+                .into_iter()
+                .find(|n| n.category == category && n.title == key);
+            let note = match existing {
+                Some(note) => note,
+                None => notes::create_note(&project, &key, &category).map_err(host_error)?,
+            };
+            notes::update_note(&project, &note.id, &value, None).map_err(host_error)?;
+            Ok(())
+        },
+    )?;
+
+    Ok(linker)
+}
@@ -0,0 +1,386 @@
+//! Local automation API: JSON-RPC 2.0 over HTTP on `127.0.0.1`.
+//!
+//! Method names and parameters match the desktop app's Tauri commands, so
+//! `{"method": "get_chapter_content", "params": {"project_path": ...,
+//! "chapter_id": ...}}` does what `invoke("get_chapter_content", ...)` does.
+//! Every request must carry `Authorization: Bearer <token>`. In read-only
+//! mode, methods that change the project or write files are refused, and
+//! the rest open the project with [`Project::open_read_only`] so lookups
+//! never refresh a cache on disk.
+//!
+//! Any HTTP client will do, e.g. with `quillborn-cli serve` running:
+//!
+//! ```text
+//! curl -H "Authorization: Bearer $TOKEN" http://127.0.0.1:7417/ \
+//!   -d '{"jsonrpc": "2.0", "id": 1, "method": "get_project_state",
+//!        "params": {"project_path": "/path/to/Book.qb"}}'
+//! ```
+
+mod server;
+
+pub use server::{RpcConfig, RpcServer};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+use crate::analysis;
+use crate::export;
+use crate::manuscript::chapter::Chapter;
+use crate::manuscript::ids::ChapterId;
+use crate::manuscript::import::{self, SplitMethod};
+use crate::manuscript::project::{Project, ProjectError, ProjectState};
+use crate::search::{self, SearchOptions, SmartCollections};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// The method ran and failed; `data` holds the structured `ProjectError`.
+pub const PROJECT_ERROR: i64 = -32000;
+pub const READ_ONLY: i64 = -32001;
+
+/// Every method the API serves, and whether it changes anything on disk.
+pub const METHODS: &[(&str, bool)] = &[
+    ("open_project", false),
+    ("get_project_state", false),
+    ("get_chapter_content", false),
+    ("search_manuscript", false),
+    ("search_project", false),
+    ("search_index", false),
+    ("fuzzy_search", false),
+    ("list_collections", false),
+    ("get_collection_members", false),
+    ("lint_manuscript", false),
+    ("lint_chapter", false),
+    ("overused_words", false),
+    ("preview_replace", false),
+    ("create_chapter", true),
+    ("update_chapter", true),
+    ("import_text", true),
+    ("create_snapshot", true),
+    ("apply_replace", true),
+    ("export_markdown", true),
+    ("export_plain_text", true),
+    ("export_html", true),
+    ("export_latex", true),
+    ("export_epub", true),
+];
+
+#[derive(Debug, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<ProjectError> for RpcError {
+    fn from(error: ProjectError) -> Self {
+        RpcError {
+            code: PROJECT_ERROR,
+            message: error.to_string(),
+            data: serde_json::to_value(&error).ok(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for RpcError {
+    fn from(error: serde_json::Error) -> Self {
+        RpcError::new(PROJECT_ERROR, error.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct PathParams {
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct ProjectParams {
+    project_path: String,
+}
+
+#[derive(Deserialize)]
+struct ChapterParams {
+    project_path: String,
+    chapter_id: ChapterId,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    project_path: String,
+    query: String,
+    #[serde(flatten)]
+    options: SearchOptions,
+}
+
+#[derive(Deserialize)]
+struct IndexParams {
+    project_path: String,
+    query: String,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    50
+}
+
+#[derive(Deserialize)]
+struct OverusedParams {
+    project_path: String,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+#[derive(Deserialize)]
+struct FuzzyParams {
+    project_path: String,
+    word: String,
+}
+
+#[derive(Deserialize)]
+struct CollectionParams {
+    project_path: String,
+    collection_id: String,
+}
+
+#[derive(Deserialize)]
+struct ReplaceParams {
+    project_path: String,
+    query: String,
+    replacement: String,
+    #[serde(flatten)]
+    options: SearchOptions,
+    #[serde(default)]
+    selected: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CreateChapterParams {
+    project_path: String,
+    title: String,
+    parent_id: Option<ChapterId>,
+}
+
+#[derive(Deserialize)]
+struct UpdateChapterParams {
+    project_path: String,
+    chapter_id: ChapterId,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ImportParams {
+    project_path: String,
+    text: String,
+    #[serde(default = "default_split")]
+    method: SplitMethod,
+    parent_id: Option<ChapterId>,
+}
+
+fn default_split() -> SplitMethod {
+    SplitMethod::Heading
+}
+
+#[derive(Deserialize)]
+struct SnapshotParams {
+    project_path: String,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    project_path: String,
+    output_path: String,
+    collection: Option<String>,
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn open_project(project_path: &str, read_only: bool) -> Result<Project, RpcError> {
+    let path = PathBuf::from(project_path);
+    Ok(if read_only {
+        Project::open_read_only(&path)?
+    } else {
+        Project::open(&path)?
+    })
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    Ok(serde_json::to_value(value)?)
+}
+
+/// Run one method call.
+pub fn call(method: &str, raw_params: Value, read_only: bool) -> Result<Value, RpcError> {
+    let writes = METHODS
+        .iter()
+        .find(|(name, _)| *name == method)
+        .map(|(_, writes)| *writes)
+        .ok_or_else(|| RpcError::new(METHOD_NOT_FOUND, format!("Unknown method: {}", method)))?;
+    if writes && read_only {
+        return Err(RpcError::new(
+            READ_ONLY,
+            format!("{} is not available in read-only mode", method),
+        ));
+    }
+    let open = |project_path: &str| open_project(project_path, read_only);
+
+    match method {
+        "open_project" => {
+            let p: PathParams = params(raw_params)?;
+            to_value(ProjectState::from(&open(&p.path)?))
+        }
+        "get_project_state" => {
+            let p: ProjectParams = params(raw_params)?;
+            to_value(ProjectState::from(&open(&p.project_path)?))
+        }
+        "get_chapter_content" => {
+            let p: ChapterParams = params(raw_params)?;
+            let chapter: Chapter = open(&p.project_path)?.load_chapter(&p.chapter_id)?;
+            to_value(chapter)
+        }
+        "search_manuscript" => {
+            let p: SearchParams = params(raw_params)?;
+            let project = open(&p.project_path)?;
+            to_value(search::search_manuscript(&project, &p.query, &p.options)?)
+        }
+        "search_project" => {
+            let p: SearchParams = params(raw_params)?;
+            let project = open(&p.project_path)?;
+            to_value(search::search_project(&project, &p.query, &p.options)?)
+        }
+        "search_index" => {
+            let p: IndexParams = params(raw_params)?;
+            to_value(search::search_index(&open(&p.project_path)?, &p.query, p.limit)?)
+        }
+        "fuzzy_search" => {
+            let p: FuzzyParams = params(raw_params)?;
+            to_value(search::fuzzy_search(&open(&p.project_path)?, &p.word)?)
+        }
+        "list_collections" => {
+            let p: ProjectParams = params(raw_params)?;
+            to_value(SmartCollections::load(&open(&p.project_path)?)?.collections)
+        }
+        "get_collection_members" => {
+            let p: CollectionParams = params(raw_params)?;
+            let project = open(&p.project_path)?;
+            to_value(SmartCollections::load(&project)?.get(&p.collection_id)?.members(&project)?)
+        }
+        "lint_manuscript" => {
+            let p: ProjectParams = params(raw_params)?;
+            to_value(analysis::lint_manuscript(&open(&p.project_path)?)?)
+        }
+        "lint_chapter" => {
+            let p: ChapterParams = params(raw_params)?;
+            to_value(analysis::lint_chapter(&open(&p.project_path)?, &p.chapter_id)?)
+        }
+        "overused_words" => {
+            let p: OverusedParams = params(raw_params)?;
+            to_value(analysis::overused_words(&open(&p.project_path)?, p.limit)?)
+        }
+        "preview_replace" => {
+            let p: ReplaceParams = params(raw_params)?;
+            let project = open(&p.project_path)?;
+            to_value(search::preview_replace(&project, &p.query, &p.replacement, &p.options)?)
+        }
+        "apply_replace" => {
+            let p: ReplaceParams = params(raw_params)?;
+            let mut project = open(&p.project_path)?;
+            to_value(search::apply_replace(&mut project, &p.query, &p.replacement, &p.options, &p.selected)?)
+        }
+        "create_chapter" => {
+            let p: CreateChapterParams = params(raw_params)?;
+            let chapter = open(&p.project_path)?.add_chapter(&p.title, p.parent_id.as_ref())?;
+            to_value(chapter)
+        }
+        "update_chapter" => {
+            let p: UpdateChapterParams = params(raw_params)?;
+            let chapter = open(&p.project_path)?.update_chapter_content(&p.chapter_id, &p.content)?;
+            to_value(chapter.word_count)
+        }
+        "import_text" => {
+            let p: ImportParams = params(raw_params)?;
+            let mut project = open(&p.project_path)?;
+            to_value(import::import_text(&mut project, &p.text, p.method, p.parent_id.as_ref())?)
+        }
+        "create_snapshot" => {
+            let p: SnapshotParams = params(raw_params)?;
+            to_value(open(&p.project_path)?.create_snapshot(p.name.as_deref())?)
+        }
+        _ => {
+            // Automation clients never get to pick a directory through a
+            // dialog, so they can only write into the project's exports/.
+            let p: ExportParams = params(raw_params)?;
+            let written = match method {
+                "export_markdown" => export::export_markdown(p.project_path, p.output_path, p.collection, &[])?,
+                "export_plain_text" => export::export_plain_text(p.project_path, p.output_path, p.collection, &[])?,
+                "export_html" => export::export_html(p.project_path, p.output_path, p.collection, &[])?,
+                "export_latex" => export::export_latex(p.project_path, p.output_path, p.collection, &[])?,
+                _ => export::export_epub(p.project_path, p.output_path, p.collection, &[])?,
+            };
+            to_value(written)
+        }
+    }
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    }
+}
+
+fn handle_one(request: Value, read_only: bool) -> Option<Value> {
+    let Value::Object(mut request) = request else {
+        return Some(response(Value::Null, Err(RpcError::new(INVALID_REQUEST, "Request must be an object"))));
+    };
+    // Requests without an id are notifications: run them, answer nothing.
+    let id = request.remove("id");
+    let method = match request.remove("method") {
+        Some(Value::String(method)) if request.get("jsonrpc") == Some(&json!("2.0")) => method,
+        _ => {
+            let error = RpcError::new(INVALID_REQUEST, "Expected jsonrpc \"2.0\" and a method name");
+            return Some(response(id.unwrap_or(Value::Null), Err(error)));
+        }
+    };
+    let result = call(&method, request.remove("params").unwrap_or(Value::Null), read_only);
+    id.map(|id| response(id, result))
+}
+
+/// Handle a request body, single or batch. `None` means nothing should be
+/// sent back, because every request in it was a notification.
+pub fn handle(body: &[u8], read_only: bool) -> Option<Value> {
+    let request: Value = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => return Some(response(Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string())))),
+    };
+    match request {
+        Value::Array(batch) if batch.is_empty() => {
+            Some(response(Value::Null, Err(RpcError::new(INVALID_REQUEST, "Empty batch"))))
+        }
+        Value::Array(batch) => {
+            let responses: Vec<Value> = batch
+                .into_iter()
+                .filter_map(|request| handle_one(request, read_only))
+                .collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        request => handle_one(request, read_only),
+    }
+}
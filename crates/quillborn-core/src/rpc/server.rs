@@ -0,0 +1,436 @@
+use serde_json::json;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::manuscript::project::ProjectError;
+
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Connections served at once. Past this, new ones get a 503 until a slot
+/// frees up.
+const MAX_CONNECTIONS: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct RpcConfig {
+    /// Port on `127.0.0.1`; `0` picks a free one.
+    pub port: u16,
+    pub token: String,
+    pub read_only: bool,
+}
+
+impl RpcConfig {
+    /// A fresh random bearer token.
+    pub fn generate_token() -> String {
+        format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+    }
+}
+
+/// A running automation server. It stops when `stop` is called or the
+/// handle is dropped.
+pub struct RpcServer {
+    addr: SocketAddr,
+    stopping: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RpcServer {
+    /// Bind to `127.0.0.1` and start serving in the background.
+    pub fn start(config: RpcConfig) -> Result<Self, ProjectError> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, config.port))?;
+        let addr = listener.local_addr()?;
+        let stopping = Arc::new(AtomicBool::new(false));
+
+        let flag = stopping.clone();
+        let config = Arc::new(config);
+        let active = Arc::new(AtomicUsize::new(0));
+        let thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if flag.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let Some(slot) = Slot::claim(&active) else {
+                    let _ = stream.set_write_timeout(Some(READ_TIMEOUT));
+                    let _ = reject(&stream, Rejection(503, "Too many connections"));
+                    continue;
+                };
+                let config = config.clone();
+                thread::spawn(move || {
+                    let _slot = slot;
+                    // A client that hangs up early is not the server's problem.
+                    let _ = serve_connection(stream, &config);
+                });
+            }
+        });
+
+        Ok(RpcServer {
+            addr,
+            stopping,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Block until the server stops, which for a foreground server means
+    /// until the process is killed.
+    pub fn wait(mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    pub fn stop(self) {
+        drop(self);
+    }
+}
+
+impl Drop for RpcServer {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// One of the `MAX_CONNECTIONS` connection slots, freed when dropped.
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    fn claim(active: &Arc<AtomicUsize>) -> Option<Slot> {
+        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            active.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(Slot(active.clone()))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct Request {
+    method: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// An HTTP status and message for a request that never reaches JSON-RPC.
+struct Rejection(u16, &'static str);
+
+/// Read a request, rejecting it before the body is read if it isn't
+/// allowed in.
+fn read_request(stream: &TcpStream, config: &RpcConfig) -> Result<Result<Request, Rejection>, io::Error> {
+    let mut reader = BufReader::new(stream);
+    let mut header_bytes = 0;
+    let mut line = String::new();
+
+    header_bytes += reader.read_line(&mut line)?;
+    let method = line.split_whitespace().next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line)?;
+        header_bytes += read;
+        if header_bytes > MAX_HEADER_BYTES {
+            return Ok(Err(Rejection(431, "Request headers too large")));
+        }
+        if read == 0 || line.trim_end().is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut request = Request {
+        method,
+        headers,
+        body: Vec::new(),
+    };
+    if let Err(rejection) = check(&request, config) {
+        return Ok(Err(rejection));
+    }
+    let length = match request.header("content-length").map(str::parse::<usize>) {
+        Some(Ok(length)) => length,
+        Some(Err(_)) => return Ok(Err(Rejection(400, "Invalid Content-Length"))),
+        None if request.method == "POST" => return Ok(Err(Rejection(411, "Content-Length required"))),
+        None => 0,
+    };
+    if length > MAX_BODY_BYTES {
+        return Ok(Err(Rejection(413, "Request body too large")));
+    }
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body)?;
+    Ok(Ok(request))
+}
+
+/// Only answer requests addressed to the loopback interface, so a web page
+/// can't reach the server through DNS rebinding.
+fn is_local_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    matches!(name, "127.0.0.1" | "localhost" | "[::1]")
+}
+
+/// Compare without exiting at the first differing byte.
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn check(request: &Request, config: &RpcConfig) -> Result<(), Rejection> {
+    if !request.header("host").is_some_and(is_local_host) {
+        return Err(Rejection(403, "Host must be localhost"));
+    }
+    let token = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !token_matches(token.trim(), &config.token) {
+        return Err(Rejection(401, "Missing or invalid bearer token"));
+    }
+    if request.method != "POST" {
+        return Err(Rejection(405, "Use POST"));
+    }
+    Ok(())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Error",
+    }
+}
+
+fn write_response(mut stream: &TcpStream, status: u16, body: Option<Vec<u8>>) -> io::Result<()> {
+    let body = body.unwrap_or_default();
+    let mut head = format!("HTTP/1.1 {} {}\r\nConnection: close\r\n", status, reason(status));
+    if status == 401 {
+        head.push_str("WWW-Authenticate: Bearer\r\n");
+    }
+    if !body.is_empty() {
+        head.push_str("Content-Type: application/json\r\n");
+    }
+    head.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(&body)?;
+    stream.flush()
+}
+
+fn reject(stream: &TcpStream, Rejection(status, message): Rejection) -> io::Result<()> {
+    let body = json!({ "error": message }).to_string().into_bytes();
+    write_response(stream, status, Some(body))
+}
+
+fn serve_connection(stream: TcpStream, config: &RpcConfig) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    match read_request(&stream, config)? {
+        Ok(request) => match super::handle(&request.body, config.read_only) {
+            Some(response) => write_response(&stream, 200, Some(response.to_string().into_bytes())),
+            None => write_response(&stream, 204, None),
+        },
+        Err(rejection) => reject(&stream, rejection),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manuscript::ids::ChapterId;
+    use crate::manuscript::project::Project;
+    use crate::rpc::{PROJECT_ERROR, READ_ONLY};
+    use crate::search::{SmartCollection, SmartCollections};
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    const TOKEN: &str = "secret-token";
+
+    fn start(read_only: bool) -> RpcServer {
+        RpcServer::start(RpcConfig {
+            port: 0,
+            token: TOKEN.to_string(),
+            read_only,
+        })
+        .unwrap()
+    }
+
+    /// Send one request and return the status code and the parsed body.
+    fn post(server: &RpcServer, host: &str, token: Option<&str>, body: &Value) -> (u16, Value) {
+        let body = body.to_string();
+        let mut request = format!("POST / HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n", host, body.len());
+        if let Some(token) = token {
+            request.push_str(&format!("Authorization: Bearer {}\r\n", token));
+        }
+        request.push_str("\r\n");
+        request.push_str(&body);
+
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        read_response(&mut stream)
+    }
+
+    fn read_response(stream: &mut TcpStream) -> (u16, Value) {
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split_once("\r\n\r\n").map_or("", |(_, body)| body);
+        (status, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+
+    fn call(method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params })
+    }
+
+    #[test]
+    fn requires_the_bearer_token() {
+        let server = start(false);
+        let request = call("get_project_state", json!({ "project_path": "/nowhere" }));
+
+        assert_eq!(post(&server, "127.0.0.1", None, &request).0, 401);
+        assert_eq!(post(&server, "127.0.0.1", Some("wrong-token!"), &request).0, 401);
+        let (status, body) = post(&server, "127.0.0.1", Some(TOKEN), &request);
+        assert_eq!(status, 200);
+        assert_eq!(body["error"]["code"], PROJECT_ERROR);
+    }
+
+    #[test]
+    fn rejects_non_local_hosts() {
+        let server = start(false);
+        let request = call("get_project_state", json!({ "project_path": "/nowhere" }));
+
+        assert_eq!(post(&server, "attacker.example:7417", Some(TOKEN), &request).0, 403);
+        assert_eq!(post(&server, &format!("localhost:{}", server.addr().port()), Some(TOKEN), &request).0, 200);
+    }
+
+    #[test]
+    fn read_only_servers_refuse_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = Project::create(dir.path(), "Rpc", "Author").unwrap();
+        let project_path = project.path.display().to_string();
+        let server = start(true);
+
+        let (_, body) = post(
+            &server,
+            "127.0.0.1",
+            Some(TOKEN),
+            &call("create_chapter", json!({ "project_path": project_path, "title": "One" })),
+        );
+        assert_eq!(body["error"]["code"], READ_ONLY);
+
+        let (_, body) = post(
+            &server,
+            "127.0.0.1",
+            Some(TOKEN),
+            &call("get_project_state", json!({ "project_path": project_path })),
+        );
+        assert_eq!(body["result"]["metadata"]["title"], "Rpc");
+    }
+
+    #[test]
+    fn turns_away_connections_past_the_limit() {
+        let server = start(false);
+        let idle: Vec<TcpStream> = (0..MAX_CONNECTIONS)
+            .map(|_| TcpStream::connect(server.addr()).unwrap())
+            .collect();
+
+        let mut extra = TcpStream::connect(server.addr()).unwrap();
+        assert_eq!(read_response(&mut extra).0, 503);
+        drop(idle);
+    }
+
+    /// Every file under `dir` with its contents.
+    fn files(dir: &Path) -> BTreeMap<PathBuf, Vec<u8>> {
+        let mut found = BTreeMap::new();
+        for entry in fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                found.extend(files(&path));
+            } else {
+                found.insert(path.clone(), fs::read(&path).unwrap());
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn read_only_servers_leave_the_project_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "Rpc", "Author").unwrap();
+        let chapter = project.add_chapter("One", None).unwrap();
+        let chapter_id = ChapterId::parse(&chapter.id).unwrap();
+        project
+            .update_chapter_content(&chapter_id, "She walked very slowly to the door. The door was shut.")
+            .unwrap();
+        let collection = SmartCollection::new("Drafts", "status:draft").unwrap();
+        let mut collections = SmartCollections::default();
+        collections.upsert(collection.clone()).unwrap();
+        collections.save(&project).unwrap();
+        // A cache entry for a chapter that no longer exists, which a
+        // writable lint would clean up.
+        let style = project.path.join("index").join("style");
+        fs::create_dir_all(&style).unwrap();
+        fs::write(style.join("gone.json"), "{}").unwrap();
+        let before = files(&project.path);
+
+        let project_path = project.path.display().to_string();
+        let server = start(true);
+        let calls = [
+            ("search_index", json!({ "project_path": project_path, "query": "door" })),
+            ("get_collection_members", json!({ "project_path": project_path, "collection_id": collection.id })),
+            ("lint_manuscript", json!({ "project_path": project_path })),
+            ("lint_chapter", json!({ "project_path": project_path, "chapter_id": chapter.id })),
+            ("search_manuscript", json!({ "project_path": project_path, "query": "door" })),
+            ("search_project", json!({ "project_path": project_path, "query": "door" })),
+            ("fuzzy_search", json!({ "project_path": project_path, "word": "dor" })),
+            ("overused_words", json!({ "project_path": project_path })),
+            ("preview_replace", json!({ "project_path": project_path, "query": "door", "replacement": "gate" })),
+        ];
+        for (method, params) in calls {
+            let (_, body) = post(&server, "127.0.0.1", Some(TOKEN), &call(method, params));
+            assert!(body.get("result").is_some(), "{} failed: {}", method, body);
+        }
+
+        assert_eq!(files(&project.path), before);
+    }
+}
@@ -0,0 +1,192 @@
+//! Smart collections: saved queries, such as `pov:Mara status:draft` or
+//! `type:scene tag:flashback`, kept in `collections.json`. Only the query
+//! is stored; members are worked out from the manuscript each time they
+//! are asked for, so they never go stale.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use super::index::matching_nodes;
+use super::query;
+use crate::manuscript::error::{read_json, WithPath};
+use crate::manuscript::ids::ChapterId;
+use crate::manuscript::project::{ChapterStatus, ManuscriptNode, Project, ProjectError};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SmartCollection {
+    pub id: String,
+    pub name: String,
+    /// A query in the index's language, such as `pov:Mara status:draft`.
+    pub query: String,
+}
+
+impl SmartCollection {
+    /// A new collection, once `query` is known to parse.
+    pub fn new(name: &str, query: &str) -> Result<Self, ProjectError> {
+        query::parse(query)?;
+        Ok(SmartCollection {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            query: query.to_string(),
+        })
+    }
+
+    /// The manuscript nodes currently matching the query, in reading order.
+    pub fn members(&self, project: &Project) -> Result<Vec<ManuscriptNode>, ProjectError> {
+        Ok(matching_nodes(project, &self.query)?
+            .iter()
+            .filter_map(|id| project.structure.nodes.get(id).cloned())
+            .collect())
+    }
+
+    /// The chapters a collection covers, in reading order: chapters that
+    /// match, and the chapters inside any part or other node that matches.
+    /// Trashed chapters are left out unless the query asks for them, as
+    /// they are for the nodes that match directly.
+    pub fn chapters(&self, project: &Project) -> Result<Vec<ChapterId>, ProjectError> {
+        let wants_trash = query::parse(&self.query)?.is_some_and(|expr| expr.wants_trash());
+        let mut covered: HashSet<String> = HashSet::new();
+        let mut pending = matching_nodes(project, &self.query)?;
+        while let Some(id) = pending.pop() {
+            if let Some(node) = project.structure.nodes.get(&id) {
+                let children = node.children.iter().filter(|child| {
+                    wants_trash
+                        || project.structure.nodes.get(*child).is_some_and(|c| c.status != ChapterStatus::Trash)
+                });
+                pending.extend(children.cloned());
+            }
+            covered.insert(id);
+        }
+        Ok(project
+            .chapter_order()
+            .into_iter()
+            .filter(|id| covered.contains(id.as_str()))
+            .collect())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SmartCollections {
+    #[serde(default)]
+    pub collections: Vec<SmartCollection>,
+}
+
+fn collections_path(project: &Project) -> PathBuf {
+    project.path.join("collections.json")
+}
+
+impl SmartCollections {
+    pub fn load(project: &Project) -> Result<Self, ProjectError> {
+        let path = collections_path(project);
+        if !path.exists() {
+            return Ok(SmartCollections::default());
+        }
+        read_json(&path)
+    }
+
+    pub fn save(&self, project: &Project) -> Result<(), ProjectError> {
+        let path = collections_path(project);
+        fs::write(&path, serde_json::to_string_pretty(self)?).with_path(&path)?;
+        Ok(())
+    }
+
+    pub fn get(&self, collection_id: &str) -> Result<&SmartCollection, ProjectError> {
+        self.collections
+            .iter()
+            .find(|c| c.id == collection_id)
+            .ok_or_else(|| ProjectError::CollectionNotFound(collection_id.to_string()))
+    }
+
+    /// Insert a new collection or replace the one with the same id. The
+    /// query must parse.
+    pub fn upsert(&mut self, collection: SmartCollection) -> Result<(), ProjectError> {
+        query::parse(&collection.query)?;
+        match self.collections.iter_mut().find(|c| c.id == collection.id) {
+            Some(existing) => *existing = collection,
+            None => self.collections.push(collection),
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, collection_id: &str) -> Result<(), ProjectError> {
+        self.get(collection_id)?;
+        self.collections.retain(|c| c.id != collection_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A project with "Act One" holding "Arrival" and "Departure", then a
+    /// top-level "Coda". Returns the ids in that order.
+    fn project(dir: &std::path::Path) -> (Project, Vec<ChapterId>) {
+        let mut project = Project::create(dir, "Collections", "Author").unwrap();
+        let act = ChapterId::parse(&project.add_chapter("Act One", None).unwrap().id).unwrap();
+        let mut ids = vec![act.clone()];
+        for title in ["Arrival", "Departure"] {
+            let chapter = project.add_chapter(title, Some(&act)).unwrap();
+            ids.push(ChapterId::parse(&chapter.id).unwrap());
+        }
+        ids.push(ChapterId::parse(&project.add_chapter("Coda", None).unwrap().id).unwrap());
+        project.set_node_tags(&act, vec!["act1".to_string()]).unwrap();
+        project.set_node_tags(&ids[3], vec!["act1".to_string()]).unwrap();
+        (project, ids)
+    }
+
+    fn titles(project: &Project, ids: &[ChapterId]) -> Vec<String> {
+        ids.iter().map(|id| project.structure.nodes[id.as_str()].title.clone()).collect()
+    }
+
+    #[test]
+    fn members_are_the_matching_nodes_and_chapters_include_their_children() {
+        let dir = tempfile::tempdir().unwrap();
+        let (project, _) = project(dir.path());
+        let collection = SmartCollection::new("Act one", "tag:act1").unwrap();
+
+        let members: Vec<String> = collection.members(&project).unwrap().into_iter().map(|n| n.title).collect();
+        assert_eq!(members, ["Act One", "Coda"]);
+        let chapters = collection.chapters(&project).unwrap();
+        assert_eq!(titles(&project, &chapters), ["Act One", "Arrival", "Departure", "Coda"]);
+    }
+
+    #[test]
+    fn trashed_children_are_only_covered_when_asked_for() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut project, ids) = project(dir.path());
+        project.set_chapter_status(&ids[2], ChapterStatus::Trash).unwrap();
+
+        let collection = SmartCollection::new("Act one", "tag:act1").unwrap();
+        let chapters = collection.chapters(&project).unwrap();
+        assert_eq!(titles(&project, &chapters), ["Act One", "Arrival", "Coda"]);
+
+        let collection = SmartCollection::new("With trash", "tag:act1 OR status:trash").unwrap();
+        let chapters = collection.chapters(&project).unwrap();
+        assert_eq!(titles(&project, &chapters), ["Act One", "Arrival", "Departure", "Coda"]);
+    }
+
+    #[test]
+    fn collections_round_trip_and_reject_bad_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let (project, _) = project(dir.path());
+        let mut collections = SmartCollections::load(&project).unwrap();
+        let collection = SmartCollection::new("Act one", "tag:act1").unwrap();
+        let id = collection.id.clone();
+        collections.upsert(collection).unwrap();
+        collections.save(&project).unwrap();
+
+        let mut loaded = SmartCollections::load(&project).unwrap();
+        assert_eq!(loaded.get(&id).unwrap().query, "tag:act1");
+        let broken = SmartCollection {
+            query: "(tag:act1".to_string(),
+            ..loaded.get(&id).unwrap().clone()
+        };
+        assert!(loaded.upsert(broken).is_err());
+        loaded.remove(&id).unwrap();
+        assert!(loaded.get(&id).is_err());
+    }
+}
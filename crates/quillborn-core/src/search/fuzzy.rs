@@ -0,0 +1,278 @@
+//! Typo-tolerant search for a single word.
+//!
+//! Words are compared after case folding and accent stripping. A word
+//! matches when it is within a few edits of the query (Damerau-Levenshtein,
+//! so a swapped pair of letters is one edit), or when it sounds alike and
+//! is within twice that many edits. Allowed edits grow with the length of
+//! the query: none up to three letters, one up to five, two up to nine and
+//! three beyond.
+
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::matcher::{fold_str, LineMatch};
+use super::{ManuscriptSearch, SearchMode, SearchOptions, SearchResult};
+use crate::manuscript::project::{Project, ProjectError};
+
+pub(super) struct FuzzyWord {
+    folded: String,
+    sound: String,
+    max_edits: usize,
+    /// Verdicts for words already seen, as written; a manuscript uses far
+    /// fewer distinct words than it has words.
+    seen: RefCell<HashMap<String, bool>>,
+}
+
+/// Each way a term is written, with its count and the order in which it
+/// first appears.
+type Spellings<'a> = HashMap<&'a str, (usize, usize)>;
+
+/// How close a word came to the query.
+#[derive(Debug, Clone, Copy)]
+struct Closeness {
+    edits: usize,
+    sounds_alike: bool,
+}
+
+fn fold(word: &str) -> String {
+    fold_str(word, true, true)
+}
+
+/// A rough key for how a folded word sounds: consonants grouped by sound,
+/// vowels dropped after the first letter and repeats collapsed, so
+/// `caelith`, `kaelith` and `kaylith` all give `klt`.
+fn sound(folded: &str) -> String {
+    let letters: Vec<char> = folded.chars().filter(|c| c.is_alphabetic()).collect();
+    let mut key = String::new();
+    let mut i = 0;
+    while i < letters.len() {
+        let next = letters.get(i + 1).copied();
+        let (code, width) = match (letters[i], next) {
+            ('p', Some('h')) => (Some('f'), 2),
+            ('t', Some('h')) => (Some('t'), 2),
+            ('c', Some('k')) => (Some('k'), 2),
+            ('c', Some('e' | 'i' | 'y')) => (Some('s'), 1),
+            ('c' | 'k' | 'q', _) => (Some('k'), 1),
+            ('b' | 'p', _) => (Some('p'), 1),
+            ('f' | 'v', _) => (Some('f'), 1),
+            ('d' | 't', _) => (Some('t'), 1),
+            ('g', _) => (Some('g'), 1),
+            ('j', _) => (Some('j'), 1),
+            ('s' | 'z', _) => (Some('s'), 1),
+            ('x', _) => (Some('x'), 1),
+            ('l', _) => (Some('l'), 1),
+            ('m' | 'n', _) => (Some('n'), 1),
+            ('r', _) => (Some('r'), 1),
+            // Vowels and the semi-vowels only count at the start.
+            _ if i == 0 => (Some('a'), 1),
+            _ => (None, 1),
+        };
+        if let Some(code) = code {
+            if !key.ends_with(code) {
+                key.push(code);
+            }
+        }
+        i += width;
+    }
+    key
+}
+
+impl FuzzyWord {
+    pub fn new(query: &str) -> Result<Self, ProjectError> {
+        let mut words = query.unicode_words();
+        let (Some(word), None) = (words.next(), words.next()) else {
+            return Err(ProjectError::InvalidSearchPattern {
+                pattern: query.to_string(),
+                message: "fuzzy search looks for a single word".to_string(),
+                span: None,
+            });
+        };
+        let folded = fold(word);
+        let max_edits = match folded.chars().count() {
+            0..=3 => 0,
+            4..=5 => 1,
+            6..=9 => 2,
+            _ => 3,
+        };
+        Ok(FuzzyWord {
+            sound: sound(&folded),
+            folded,
+            max_edits,
+            seen: RefCell::default(),
+        })
+    }
+
+    fn closeness(&self, word: &str) -> Option<Closeness> {
+        let folded = fold(word);
+        // Lengths alone can rule most words out before counting edits.
+        let gap = folded.chars().count().abs_diff(self.folded.chars().count());
+        if gap > self.max_edits * 2 {
+            return None;
+        }
+        let edits = strsim::damerau_levenshtein(&folded, &self.folded);
+        if edits <= self.max_edits {
+            let sounds_alike = sound(&folded) == self.sound;
+            return Some(Closeness { edits, sounds_alike });
+        }
+        (edits <= self.max_edits * 2 && sound(&folded) == self.sound).then_some(Closeness {
+            edits,
+            sounds_alike: true,
+        })
+    }
+
+    /// Every word in `line` close enough to the query.
+    pub fn find_line(&self, line: &str) -> Vec<LineMatch> {
+        let mut seen = self.seen.borrow_mut();
+        line.unicode_word_indices()
+            .filter(|(_, word)| match seen.get(*word) {
+                Some(&close) => close,
+                None => *seen.entry(word.to_string()).or_insert(self.closeness(word).is_some()),
+            })
+            .map(|(start, word)| {
+                let end = start + word.len();
+                LineMatch {
+                    start,
+                    end,
+                    groups: vec![Some((start, end))],
+                }
+            })
+            .collect()
+    }
+}
+
+/// A spelling found by a fuzzy search.
+#[derive(Debug, Serialize, Clone)]
+pub struct FuzzyTerm {
+    /// The spelling as most often written.
+    pub term: String,
+    /// Every way it is written, differing in case or accents.
+    pub spellings: Vec<String>,
+    pub count: usize,
+    /// Edits away from the query; 0 for the query itself.
+    pub edits: usize,
+    pub sounds_alike: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FuzzyResults {
+    /// Closest first, then most used.
+    pub terms: Vec<FuzzyTerm>,
+    /// Every occurrence of any of the terms, in reading order.
+    pub results: Vec<SearchResult>,
+}
+
+/// Find words in the manuscript spelled or sounding like `word`, such as
+/// `Kaelith` for `Caelith`: the distinct spellings with how often each is
+/// used, and where they occur. Handy for catching inconsistent spellings.
+pub fn fuzzy_search(project: &Project, word: &str) -> Result<FuzzyResults, ProjectError> {
+    let fuzzy = FuzzyWord::new(word)?;
+    let options = SearchOptions {
+        mode: SearchMode::Fuzzy,
+        ..SearchOptions::default()
+    };
+    let mut results = Vec::new();
+    ManuscriptSearch::new(word, &options)?.run(project, || false, |result| results.push(result));
+
+    // Folded form to closeness and spellings; ties go to the earlier one.
+    let mut tally: HashMap<String, (Closeness, Spellings)> = HashMap::new();
+    for (order, found) in results.iter().flat_map(|r| &r.matches).enumerate() {
+        let spelling = found.snippet.matched.as_str();
+        let Some(closeness) = fuzzy.closeness(spelling) else {
+            continue;
+        };
+        let entry = tally.entry(fold(spelling)).or_insert_with(|| (closeness, HashMap::new()));
+        entry.1.entry(spelling).or_insert((0, order)).0 += 1;
+    }
+
+    let mut terms: Vec<FuzzyTerm> = tally
+        .into_values()
+        .map(|(closeness, spellings)| {
+            let mut spellings: Vec<(&str, usize, usize)> =
+                spellings.into_iter().map(|(s, (count, first))| (s, count, first)).collect();
+            spellings.sort_by(|a, b| b.1.cmp(&a.1).then(a.2.cmp(&b.2)));
+            FuzzyTerm {
+                term: spellings[0].0.to_string(),
+                count: spellings.iter().map(|(_, count, _)| count).sum(),
+                spellings: spellings.into_iter().map(|(s, _, _)| s.to_string()).collect(),
+                edits: closeness.edits,
+                sounds_alike: closeness.sounds_alike,
+            }
+        })
+        .collect();
+    terms.sort_by(|a, b| a.edits.cmp(&b.edits).then(b.count.cmp(&a.count)).then(a.term.cmp(&b.term)));
+    Ok(FuzzyResults { terms, results })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manuscript::ids::ChapterId;
+
+    fn close(query: &str, word: &str) -> Option<(usize, bool)> {
+        FuzzyWord::new(query).unwrap().closeness(word).map(|c| (c.edits, c.sounds_alike))
+    }
+
+    #[test]
+    fn sound_keys_group_spellings_that_sound_alike() {
+        for word in ["caelith", "kaelith", "kaylith"] {
+            assert_eq!(sound(word), "klt", "{}", word);
+        }
+        assert_eq!(sound("phone"), sound("fone"));
+        assert_eq!(sound("cider"), sound("sider"));
+        assert_ne!(sound("caleb"), sound("caelith"));
+    }
+
+    #[test]
+    fn allowed_edits_grow_with_the_query() {
+        let bands = [("cat", 0), ("star", 1), ("stars", 1), ("lantern", 2), ("mountains", 2), ("starlighter", 3)];
+        for (query, edits) in bands {
+            assert_eq!(FuzzyWord::new(query).unwrap().max_edits, edits, "{}", query);
+        }
+        assert!(FuzzyWord::new("two words").is_err());
+    }
+
+    #[test]
+    fn near_spellings_match() {
+        assert_eq!(close("Caelith", "Kaelith"), Some((1, true)));
+        assert_eq!(close("Caelith", "CAËLITH"), Some((0, true)));
+        // A swapped pair of letters is one edit.
+        assert_eq!(close("Caelith", "Caleith"), Some((1, true)));
+        // Too many edits, but it sounds the same.
+        assert_eq!(close("Caelith", "Kayleth"), Some((3, true)));
+        assert_eq!(close("Caelith", "Caleb"), None);
+    }
+
+    #[test]
+    fn short_words_only_match_exactly() {
+        assert_eq!(close("cat", "Cat"), Some((0, true)));
+        assert_eq!(close("cat", "cot"), None);
+        assert_eq!(close("cat", "act"), None);
+        assert_eq!(close("star", "stab"), Some((1, false)));
+    }
+
+    #[test]
+    fn spellings_are_tallied_per_term() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "Fuzzy", "Author").unwrap();
+        for (title, text) in [
+            ("One", "Caelith rode north. Kaelith followed.\nCaelith stopped."),
+            ("Two", "Then caelith and Caëlith met Caleb."),
+        ] {
+            let chapter = project.add_chapter(title, None).unwrap();
+            let id = ChapterId::parse(&chapter.id).unwrap();
+            project.update_chapter_content(&id, text).unwrap();
+        }
+
+        let found = fuzzy_search(&project, "Caelith").unwrap();
+        let terms: Vec<(&str, usize, usize)> =
+            found.terms.iter().map(|t| (t.term.as_str(), t.count, t.edits)).collect();
+        assert_eq!(terms, [("Caelith", 4, 0), ("Kaelith", 1, 1)]);
+        assert_eq!(found.terms[0].spellings, ["Caelith", "caelith", "Caëlith"]);
+        assert!(found.terms[1].sounds_alike);
+
+        let per_chapter: Vec<usize> = found.results.iter().map(|r| r.matches.len()).collect();
+        assert_eq!(per_chapter, [3, 2]);
+    }
+}
@@ -0,0 +1,836 @@
+//! Full-text index over chapters and notes, kept in `index/search.json`.
+//!
+//! Words are split on Unicode word boundaries and stored case-folded and
+//! without accents, with their positions, so lookups can rank by BM25 and
+//! check phrases without reading any chapter. The index notices files that
+//! changed since they were indexed (by modification time and size) and
+//! reindexes just those; a missing, unreadable or outdated index file is
+//! rebuilt from scratch.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::UNIX_EPOCH;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::matcher::fold_str;
+use super::query::{self, Expr, Filter, Scope, Text};
+use crate::manuscript::chapter::Chapter;
+use crate::manuscript::error::WithPath;
+use crate::manuscript::ids::ChapterId;
+use crate::manuscript::project::{
+    ChapterStatus, ManuscriptNode, ManuscriptStructure, NodeType, Project, ProjectError,
+};
+use crate::planning::notes::{self, Note};
+
+/// Bump when the on-disk layout or the tokenizer changes; older index
+/// files are then rebuilt.
+const INDEX_VERSION: u32 = 2;
+
+/// BM25 parameters.
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// Positions skipped between a document's title and its text, so a phrase
+/// can't run from one into the other.
+const TITLE_GAP: u32 = 2;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DocKind {
+    Chapter,
+    Note,
+}
+
+/// File modification time in nanoseconds and size, to spot files that
+/// changed since they were indexed.
+pub(crate) type Stamp = (u64, u64);
+
+/// Indexes this process has loaded, by index file, with the stamp of the
+/// file they match. Spares the app and the automation server from parsing
+/// the whole index on every search.
+static LOADED: Mutex<BTreeMap<PathBuf, (Stamp, SearchIndex)>> = Mutex::new(BTreeMap::new());
+
+/// A document the index knows about.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Doc {
+    kind: DocKind,
+    id: String,
+    title: String,
+    category: Option<String>,
+    /// Note tags.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    stamp: Stamp,
+    /// Number of words, for length normalisation.
+    length: u32,
+}
+
+impl Doc {
+    fn key(&self) -> (DocKind, &str) {
+        (self.kind, &self.id)
+    }
+}
+
+/// A document that exists on disk right now.
+struct Source {
+    kind: DocKind,
+    id: String,
+    category: Option<String>,
+    path: PathBuf,
+    stamp: Stamp,
+}
+
+/// What gets indexed from a document.
+struct Contents {
+    title: String,
+    tags: Vec<String>,
+    text: String,
+}
+
+impl Source {
+    fn load(&self, project: &Project) -> Result<Contents, ProjectError> {
+        match self.kind {
+            DocKind::Chapter => {
+                let chapter = Chapter::from_file(&self.path)?;
+                let title = project
+                    .structure
+                    .nodes
+                    .get(&self.id)
+                    .map_or(chapter.title, |node| node.title.clone());
+                Ok(Contents {
+                    title,
+                    tags: Vec::new(),
+                    text: chapter.content,
+                })
+            }
+            DocKind::Note => {
+                let note = Note::from_file(&self.path, self.category.as_deref().unwrap_or_default())?;
+                Ok(Contents {
+                    title: note.title,
+                    tags: note.tags,
+                    text: note.content,
+                })
+            }
+        }
+    }
+}
+
+/// A ranked result from [`SearchIndex::query`].
+#[derive(Debug, Serialize, Clone)]
+pub struct IndexHit {
+    pub kind: DocKind,
+    pub id: String,
+    pub title: String,
+    /// Note category; `None` for chapters.
+    pub category: Option<String>,
+    pub score: f64,
+    /// How many times the query matched in the document.
+    pub matches: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct SearchIndex {
+    version: u32,
+    /// Slots are reused when documents go away; `None` is a free slot.
+    docs: Vec<Option<Doc>>,
+    /// Term to the documents containing it, with word positions.
+    postings: BTreeMap<String, Vec<(u32, Vec<u32>)>>,
+}
+
+fn index_path(project: &Project) -> PathBuf {
+    project.path.join("index").join("search.json")
+}
+
+pub(crate) fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((modified.as_nanos() as u64, metadata.len()))
+}
+
+/// Normalise one word the way both documents and queries are indexed.
+fn normalize(word: &str) -> String {
+    fold_str(&word.replace('\u{2019}', "'"), true, true)
+}
+
+/// The indexed words of `text`, in order.
+pub(super) fn tokenize(text: &str) -> Vec<String> {
+    text.unicode_words().map(normalize).collect()
+}
+
+/// Everything that should currently be in the index.
+fn sources(project: &Project) -> Result<Vec<Source>, ProjectError> {
+    let mut sources = Vec::new();
+    for id in project.chapter_order() {
+        let path = project.chapter_path(&id);
+        if let Some(stamp) = stamp(&path) {
+            sources.push(Source {
+                kind: DocKind::Chapter,
+                id: id.to_string(),
+                category: None,
+                path,
+                stamp,
+            });
+        }
+    }
+    for (category, path) in notes::note_files(project)? {
+        let (Some(id), Some(stamp)) = (path.file_stem().and_then(|s| s.to_str()), stamp(&path)) else {
+            continue;
+        };
+        sources.push(Source {
+            kind: DocKind::Note,
+            id: id.to_string(),
+            category: Some(category),
+            path: path.clone(),
+            stamp,
+        });
+    }
+    Ok(sources)
+}
+
+/// Per-document score and match count.
+type Hits = HashMap<u32, (f64, usize)>;
+
+/// Where some text occurs in each document, as inclusive word ranges in
+/// order.
+type Occurrences = HashMap<u32, Vec<(u32, u32)>>;
+
+impl SearchIndex {
+    /// An empty index at the current version.
+    fn empty() -> Self {
+        SearchIndex {
+            version: INDEX_VERSION,
+            ..SearchIndex::default()
+        }
+    }
+
+    /// Run `f` on the project's index and save it if `f` reports a change,
+    /// unless the project is read-only. The index comes from [`LOADED`]
+    /// while the file on disk is still the one it was loaded from or saved
+    /// to, and is read (or started afresh, if unreadable or outdated)
+    /// otherwise.
+    fn with_loaded<T>(
+        project: &Project,
+        f: impl FnOnce(&mut SearchIndex) -> Result<(bool, T), ProjectError>,
+    ) -> Result<T, ProjectError> {
+        let path = index_path(project);
+        let mut loaded = LOADED.lock().unwrap_or_else(PoisonError::into_inner);
+        let on_disk = stamp(&path);
+        let mut index = match loaded.remove(&path) {
+            Some((cached, index)) if Some(cached) == on_disk => index,
+            _ => SearchIndex::load(project)
+                .filter(|index| index.version == INDEX_VERSION)
+                .unwrap_or_else(SearchIndex::empty),
+        };
+        let (changed, value) = f(&mut index)?;
+        if changed && !project.read_only {
+            index.save(project)?;
+        }
+        // Unsaved changes can stay cached against the file they build on;
+        // without a file there is nothing to check them against.
+        if let Some(saved) = stamp(&path) {
+            loaded.insert(path, (saved, index));
+        }
+        Ok(value)
+    }
+
+    /// Throw the index away and index everything again.
+    pub fn rebuild(project: &Project) -> Result<(), ProjectError> {
+        SearchIndex::with_loaded(project, |index| {
+            *index = SearchIndex::empty();
+            index.refresh(project)?;
+            Ok((true, ()))
+        })
+    }
+
+    fn load(project: &Project) -> Option<Self> {
+        let raw = fs::read(index_path(project)).ok()?;
+        serde_json::from_slice(&raw).ok()
+    }
+
+    fn save(&self, project: &Project) -> Result<(), ProjectError> {
+        let path = index_path(project);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
+        fs::write(&path, serde_json::to_vec(self)?).with_path(&path)?;
+        Ok(())
+    }
+
+    fn live_docs(&self) -> impl Iterator<Item = (u32, &Doc)> {
+        self.docs
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, doc)| doc.as_ref().map(|doc| (slot as u32, doc)))
+    }
+
+    /// Reindex documents that changed on disk and drop ones that are gone.
+    /// Returns whether anything changed.
+    fn refresh(&mut self, project: &Project) -> Result<bool, ProjectError> {
+        let sources = sources(project)?;
+        let slots: HashMap<(DocKind, &str), (u32, Stamp)> = self
+            .live_docs()
+            .map(|(slot, doc)| (doc.key(), (slot, doc.stamp)))
+            .collect();
+
+        let mut stale = HashSet::new();
+        let mut changed = Vec::new();
+        let mut seen = HashSet::new();
+        for source in &sources {
+            let key = (source.kind, source.id.as_str());
+            seen.insert(key);
+            match slots.get(&key) {
+                Some(&(_, stamp)) if stamp == source.stamp => {}
+                Some(&(slot, _)) => {
+                    stale.insert(slot);
+                    changed.push(source);
+                }
+                None => changed.push(source),
+            }
+        }
+        stale.extend(
+            slots
+                .iter()
+                .filter(|(key, _)| !seen.contains(*key))
+                .map(|(_, (slot, _))| *slot),
+        );
+
+        if stale.is_empty() && changed.is_empty() {
+            return Ok(false);
+        }
+        self.remove_slots(&stale);
+        for source in changed {
+            // A file that can't be parsed is left out until it is fixed.
+            if let Ok(contents) = source.load(project) {
+                self.insert(source, contents);
+            }
+        }
+        Ok(true)
+    }
+
+    fn remove_slots(&mut self, slots: &HashSet<u32>) {
+        if slots.is_empty() {
+            return;
+        }
+        for &slot in slots {
+            self.docs[slot as usize] = None;
+        }
+        self.postings.retain(|_, docs| {
+            docs.retain(|(slot, _)| !slots.contains(slot));
+            !docs.is_empty()
+        });
+    }
+
+    fn insert(&mut self, source: &Source, contents: Contents) {
+        let mut positions: HashMap<String, Vec<u32>> = HashMap::new();
+        let title_words = tokenize(&contents.title);
+        let offset = title_words.len() as u32 + TITLE_GAP;
+        let words = tokenize(&contents.text);
+        let all = title_words.into_iter().enumerate().map(|(i, w)| (i as u32, w));
+        for (position, word) in all.chain(words.into_iter().enumerate().map(|(i, w)| (offset + i as u32, w)))
+        {
+            positions.entry(word).or_default().push(position);
+        }
+
+        let doc = Doc {
+            kind: source.kind,
+            id: source.id.clone(),
+            title: contents.title,
+            category: source.category.clone(),
+            tags: contents.tags,
+            stamp: source.stamp,
+            length: positions.values().map(|p| p.len() as u32).sum(),
+        };
+        let slot = match self.docs.iter().position(Option::is_none) {
+            Some(free) => {
+                self.docs[free] = Some(doc);
+                free as u32
+            }
+            None => {
+                self.docs.push(Some(doc));
+                (self.docs.len() - 1) as u32
+            }
+        };
+        for (word, positions) in positions {
+            self.postings.entry(word).or_default().push((slot, positions));
+        }
+    }
+
+    /// Reindex one chapter straight after it was saved. A missing or
+    /// outdated index is left for the next search to rebuild.
+    pub(crate) fn update_chapter(project: &Project, chapter: &Chapter) -> Result<(), ProjectError> {
+        if stamp(&index_path(project)).is_none() {
+            return Ok(());
+        }
+        let Ok(chapter_id) = ChapterId::parse(&chapter.id) else {
+            return Ok(());
+        };
+        let path = project.chapter_path(&chapter_id);
+        let Some(stamp) = stamp(&path) else {
+            return Ok(());
+        };
+
+        SearchIndex::with_loaded(project, |index| {
+            let old: HashSet<u32> = index
+                .live_docs()
+                .filter(|(_, doc)| doc.key() == (DocKind::Chapter, chapter.id.as_str()))
+                .map(|(slot, _)| slot)
+                .collect();
+            index.remove_slots(&old);
+            let title = project
+                .structure
+                .nodes
+                .get(&chapter.id)
+                .map_or(&chapter.title, |node| &node.title);
+            let contents = Contents {
+                title: title.clone(),
+                tags: Vec::new(),
+                text: chapter.content.clone(),
+            };
+            let source = Source {
+                kind: DocKind::Chapter,
+                id: chapter.id.clone(),
+                category: None,
+                path,
+                stamp,
+            };
+            index.insert(&source, contents);
+            Ok((true, ()))
+        })
+    }
+
+    /// BM25 weight of a word that occurs `tf` times in a document of
+    /// `length` words and in `df` of the documents.
+    fn weight(&self, tf: usize, df: usize, length: u32, stats: (f64, f64)) -> f64 {
+        let (doc_count, average_length) = stats;
+        let idf = (1.0 + (doc_count - df as f64 + 0.5) / (df as f64 + 0.5)).ln();
+        let tf = tf as f64;
+        let norm = 1.0 - B + B * length as f64 / average_length.max(1.0);
+        idf * tf * (K1 + 1.0) / (tf + K1 * norm)
+    }
+
+    fn length(&self, slot: u32) -> u32 {
+        self.docs[slot as usize].as_ref().map_or(0, |doc| doc.length)
+    }
+
+    /// Where `text` occurs in each document.
+    fn occurrences(&self, text: &Text) -> Occurrences {
+        let add = |occurrences: &mut Occurrences, docs: &Vec<(u32, Vec<u32>)>| {
+            for (slot, positions) in docs {
+                occurrences.entry(*slot).or_default().extend(positions.iter().map(|&p| (p, p)));
+            }
+        };
+        let mut occurrences = Occurrences::new();
+        match text {
+            Text::Word(word) => {
+                if let Some(docs) = self.postings.get(word) {
+                    add(&mut occurrences, docs);
+                }
+            }
+            Text::Prefix(prefix) => {
+                let expansions = self
+                    .postings
+                    .range(prefix.clone()..)
+                    .take_while(|(term, _)| term.starts_with(prefix.as_str()));
+                for (_, docs) in expansions {
+                    add(&mut occurrences, docs);
+                }
+                for spans in occurrences.values_mut() {
+                    spans.sort_unstable();
+                }
+            }
+            Text::Phrase(words) => {
+                let lists: Option<Vec<HashMap<u32, &Vec<u32>>>> = words
+                    .iter()
+                    .map(|w| {
+                        self.postings
+                            .get(w)
+                            .map(|docs| docs.iter().map(|(slot, positions)| (*slot, positions)).collect())
+                    })
+                    .collect();
+                let Some(lists) = lists else {
+                    return occurrences;
+                };
+                let last = words.len() as u32 - 1;
+                for (slot, first) in &lists[0] {
+                    let spans: Vec<(u32, u32)> = first
+                        .iter()
+                        .filter(|&&start| {
+                            lists[1..].iter().enumerate().all(|(i, list)| {
+                                list.get(slot)
+                                    .is_some_and(|positions| positions.binary_search(&(start + i as u32 + 1)).is_ok())
+                            })
+                        })
+                        .map(|&start| (start, start + last))
+                        .collect();
+                    if !spans.is_empty() {
+                        occurrences.insert(*slot, spans);
+                    }
+                }
+            }
+        }
+        occurrences
+    }
+
+    /// Occurrences of `a` with an occurrence of `b` at most `distance` words
+    /// away on either side, each widened to cover both.
+    fn near(&self, a: &Text, b: &Text, distance: u32) -> Occurrences {
+        let right = self.occurrences(b);
+        self.occurrences(a)
+            .into_iter()
+            .filter_map(|(slot, left)| {
+                let right = right.get(&slot)?;
+                let spans: Vec<(u32, u32)> = left
+                    .into_iter()
+                    .filter_map(|l| {
+                        let reach = distance.saturating_add(1);
+                        let from = right.partition_point(|r| r.1.saturating_add(reach) < l.0);
+                        right[from..]
+                            .iter()
+                            .take_while(|r| r.0 <= l.1.saturating_add(reach))
+                            .find(|r| r.1 < l.0 || r.0 > l.1)
+                            .map(|r| (l.0.min(r.0), l.1.max(r.1)))
+                    })
+                    .collect();
+                (!spans.is_empty()).then_some((slot, spans))
+            })
+            .collect()
+    }
+
+    fn score(&self, occurrences: Occurrences, stats: (f64, f64)) -> Hits {
+        let df = occurrences.len();
+        occurrences
+            .into_iter()
+            .map(|(slot, spans)| {
+                let weight = self.weight(spans.len(), df, self.length(slot), stats);
+                (slot, (weight, spans.len()))
+            })
+            .collect()
+    }
+
+    /// Every document for which `keep` holds, unscored.
+    fn docs_where(&self, keep: impl Fn(u32, &Doc) -> bool) -> Hits {
+        self.live_docs()
+            .filter(|(slot, doc)| keep(*slot, doc))
+            .map(|(slot, _)| (slot, (0.0, 0)))
+            .collect()
+    }
+
+    fn eval(&self, expr: &Expr, metadata: &Metadata, stats: (f64, f64)) -> Hits {
+        match expr {
+            Expr::Text(text) => self.score(self.occurrences(text), stats),
+            Expr::Near(a, b, distance) => self.score(self.near(a, b, *distance), stats),
+            Expr::Filter(filter) => self.docs_where(|_, doc| metadata.matches(doc, filter)),
+            Expr::Not(inner) => {
+                let excluded = self.eval(inner, metadata, stats);
+                self.docs_where(|slot, _| !excluded.contains_key(&slot))
+            }
+            Expr::And(parts) => {
+                let mut parts = parts.iter();
+                let Some(first) = parts.next() else {
+                    return Hits::new();
+                };
+                let mut combined = self.eval(first, metadata, stats);
+                for part in parts {
+                    let hits = self.eval(part, metadata, stats);
+                    combined.retain(|slot, _| hits.contains_key(slot));
+                    for (slot, hit) in combined.iter_mut() {
+                        let (score, count) = hits[slot];
+                        hit.0 += score;
+                        hit.1 += count;
+                    }
+                }
+                combined
+            }
+            Expr::Or(parts) => {
+                let mut combined = Hits::new();
+                for part in parts {
+                    for (slot, (score, count)) in self.eval(part, metadata, stats) {
+                        let hit = combined.entry(slot).or_default();
+                        hit.0 += score;
+                        hit.1 += count;
+                    }
+                }
+                combined
+            }
+        }
+    }
+
+    /// Manuscript nodes of any type matching `expr`. Filters look at each
+    /// node's own metadata, and text can only match chapters, the only
+    /// nodes with any.
+    fn select_nodes<'p>(&self, expr: &Expr, metadata: &Metadata<'p>) -> HashSet<&'p str> {
+        let nodes = &metadata.project.structure.nodes;
+        let chapters_with = |occurrences: Occurrences| -> HashSet<&'p str> {
+            occurrences
+                .into_keys()
+                .filter_map(|slot| {
+                    let doc = self.docs[slot as usize].as_ref().filter(|doc| doc.kind == DocKind::Chapter)?;
+                    nodes.get_key_value(&doc.id).map(|(id, _)| id.as_str())
+                })
+                .collect()
+        };
+        match expr {
+            Expr::Text(text) => chapters_with(self.occurrences(text)),
+            Expr::Near(a, b, distance) => chapters_with(self.near(a, b, *distance)),
+            Expr::Filter(filter) => nodes
+                .iter()
+                .filter(|(_, node)| metadata.node_matches(node, filter))
+                .map(|(id, _)| id.as_str())
+                .collect(),
+            Expr::Not(inner) => {
+                let excluded = self.select_nodes(inner, metadata);
+                nodes.keys().map(String::as_str).filter(|id| !excluded.contains(id)).collect()
+            }
+            Expr::And(parts) => {
+                let mut parts = parts.iter().map(|part| self.select_nodes(part, metadata));
+                let Some(mut kept) = parts.next() else {
+                    return HashSet::new();
+                };
+                for part in parts {
+                    kept.retain(|id| part.contains(id));
+                }
+                kept
+            }
+            Expr::Or(parts) => parts.iter().flat_map(|part| self.select_nodes(part, metadata)).collect(),
+        }
+    }
+
+    /// Documents matching `query`, best first. See [`super::query`] for the
+    /// syntax. Trashed chapters only turn up when the query asks for them
+    /// with `in:trash` or `status:trash`.
+    pub fn query(&self, project: &Project, query: &str, limit: usize) -> Result<Vec<IndexHit>, ProjectError> {
+        let Some(expr) = query::parse(query)? else {
+            return Ok(Vec::new());
+        };
+        let (doc_count, total_length) = self.live_docs().fold((0.0_f64, 0.0_f64), |(n, total), (_, doc)| {
+            (n + 1.0, total + doc.length as f64)
+        });
+        let stats = (doc_count, total_length / doc_count.max(1.0));
+        let metadata = Metadata::new(project);
+        let wants_trash = expr.wants_trash();
+
+        let mut results: Vec<IndexHit> = self
+            .eval(&expr, &metadata, stats)
+            .into_iter()
+            .filter_map(|(slot, (score, matches))| {
+                let doc = self.docs[slot as usize].as_ref()?;
+                if !wants_trash && metadata.trashed(doc) {
+                    return None;
+                }
+                Some(IndexHit {
+                    kind: doc.kind,
+                    id: doc.id.clone(),
+                    title: doc.title.clone(),
+                    category: doc.category.clone(),
+                    score,
+                    matches,
+                })
+            })
+            .collect();
+        results.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.title.cmp(&b.title)));
+        results.truncate(limit);
+        Ok(results)
+    }
+}
+
+/// Metadata the query filters look at, which lives in the manuscript tree
+/// rather than the index.
+struct Metadata<'p> {
+    project: &'p Project,
+    /// Child node id to parent node id.
+    parents: HashMap<&'p str, &'p str>,
+}
+
+/// Whether two metadata values are the same, ignoring case and accents.
+fn same(a: &str, b: &str) -> bool {
+    fold_str(a.trim(), true, true) == fold_str(b.trim(), true, true)
+}
+
+impl<'p> Metadata<'p> {
+    fn new(project: &'p Project) -> Self {
+        let parents = project
+            .structure
+            .nodes
+            .iter()
+            .flat_map(|(id, node)| node.children.iter().map(move |child| (child.as_str(), id.as_str())))
+            .collect();
+        Metadata { project, parents }
+    }
+
+    fn node(&self, doc: &Doc) -> Option<&'p ManuscriptNode> {
+        match doc.kind {
+            DocKind::Chapter => self.project.structure.nodes.get(&doc.id),
+            DocKind::Note => None,
+        }
+    }
+
+    fn trashed(&self, doc: &Doc) -> bool {
+        self.node(doc).is_some_and(|node| node.status == ChapterStatus::Trash)
+    }
+
+    fn matches(&self, doc: &Doc, filter: &Filter) -> bool {
+        match self.node(doc) {
+            Some(node) => self.node_matches(node, filter),
+            None => match filter {
+                Filter::Tag(tag) => doc.tags.iter().any(|t| same(t, tag)),
+                Filter::Type(kind) => kind == "note",
+                Filter::Category(category) => doc.category.as_deref().is_some_and(|c| same(c, category)),
+                Filter::Scope(scope) => *scope == Scope::Notes,
+                _ => false,
+            },
+        }
+    }
+
+    /// Whether a manuscript node passes `filter`.
+    fn node_matches(&self, node: &ManuscriptNode, filter: &Filter) -> bool {
+        match filter {
+            Filter::Pov(pov) => node.pov.as_deref().is_some_and(|p| same(p, pov)),
+            Filter::Mood(mood) => node.mood.as_deref().is_some_and(|m| same(m, mood)),
+            Filter::Status(status) => &node.status == status,
+            Filter::Tag(tag) => node.tags.iter().any(|t| same(t, tag)),
+            Filter::Type(kind) => {
+                let actual = match node.node_type {
+                    NodeType::Book => "book",
+                    NodeType::Part => "part",
+                    NodeType::Chapter => "chapter",
+                    NodeType::Scene => "scene",
+                };
+                actual == kind
+            }
+            Filter::Part(part) => {
+                let mut current = Some(node.id.as_str());
+                while let Some(id) = current {
+                    let title = self.project.structure.nodes.get(id).map_or("", |n| n.title.as_str());
+                    if id == part || same(title, part) {
+                        return true;
+                    }
+                    current = self.parents.get(id).copied();
+                }
+                false
+            }
+            Filter::Category(_) | Filter::Scope(Scope::Notes) => false,
+            Filter::Scope(Scope::Manuscript) => node.status != ChapterStatus::Trash,
+            Filter::Scope(Scope::Trash) => node.status == ChapterStatus::Trash,
+        }
+    }
+}
+
+/// Search chapters and notes through the index, updating it first. See
+/// [`super::query`] for the query syntax.
+pub fn search_index(project: &Project, query: &str, limit: usize) -> Result<Vec<IndexHit>, ProjectError> {
+    SearchIndex::with_loaded(project, |index| {
+        let changed = index.refresh(project)?;
+        Ok((changed, index.query(project, query, limit)?))
+    })
+}
+
+/// Ids of the manuscript nodes matching `query`, in reading order, with
+/// the index updated first. Unlike [`search_index`] this covers parts and
+/// scenes as well as chapters, but never notes or the root node. Trashed
+/// nodes only turn up when the query asks for them.
+pub(super) fn matching_nodes(project: &Project, query: &str) -> Result<Vec<String>, ProjectError> {
+    let Some(expr) = query::parse(query)? else {
+        return Ok(Vec::new());
+    };
+    let selected: HashSet<String> = SearchIndex::with_loaded(project, |index| {
+        let changed = index.refresh(project)?;
+        let metadata = Metadata::new(project);
+        let selected = index.select_nodes(&expr, &metadata).into_iter().map(str::to_string).collect();
+        Ok((changed, selected))
+    })?;
+
+    fn walk(structure: &ManuscriptStructure, id: &str, order: &mut Vec<String>) {
+        if let Some(node) = structure.nodes.get(id) {
+            order.push(id.to_string());
+            for child in &node.children {
+                walk(structure, child, order);
+            }
+        }
+    }
+    let structure = &project.structure;
+    let mut order = Vec::new();
+    walk(structure, &structure.root, &mut order);
+    let wants_trash = expr.wants_trash();
+    Ok(order
+        .into_iter()
+        .filter(|id| *id != structure.root && selected.contains(id))
+        .filter(|id| wants_trash || structure.nodes[id].status != ChapterStatus::Trash)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project_with(chapters: &[(&str, &str)]) -> (tempfile::TempDir, Project, Vec<ChapterId>) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "Index", "Author").unwrap();
+        let mut ids = Vec::new();
+        for (title, content) in chapters {
+            let chapter = project.add_chapter(title, None).unwrap();
+            let id = ChapterId::parse(&chapter.id).unwrap();
+            project.update_chapter_content(&id, content).unwrap();
+            ids.push(id);
+        }
+        (dir, project, ids)
+    }
+
+    fn titles(project: &Project, query: &str) -> Vec<String> {
+        search_index(project, query, 10)
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.title)
+            .collect()
+    }
+
+    #[test]
+    fn ranks_words_and_finds_prefixes_phrases_and_near() {
+        let (_dir, project, _) = project_with(&[
+            ("Market", "A dragon flew over the red door."),
+            ("Harbour", "The dragon slept. The dragon woke. The dragon ate."),
+        ]);
+        assert_eq!(titles(&project, "dragon"), vec!["Harbour", "Market"]);
+        assert_eq!(titles(&project, "DRAG*").len(), 2);
+        assert_eq!(titles(&project, "\"red door\""), vec!["Market"]);
+        assert!(titles(&project, "\"door red\"").is_empty());
+        // Four words sit between "dragon" and "door".
+        assert_eq!(titles(&project, "door NEAR/4 dragon"), vec!["Market"]);
+        assert!(titles(&project, "dragon NEAR/3 door").is_empty());
+        assert_eq!(titles(&project, "dragon NEAR/4294967295 door"), vec!["Market"]);
+    }
+
+    #[test]
+    fn edits_are_picked_up_with_or_without_the_save_hook() {
+        let (_dir, mut project, ids) = project_with(&[("One", "Nothing to see.")]);
+        assert!(titles(&project, "lighthouse").is_empty());
+
+        project.update_chapter_content(&ids[0], "A lighthouse on the cliff.").unwrap();
+        assert_eq!(titles(&project, "lighthouse"), vec!["One"]);
+
+        // Written behind the index's back, so only the file stamp changes.
+        let mut chapter = project.load_chapter(&ids[0]).unwrap();
+        chapter.update_content("A windmill on the hill, and nothing else at all.");
+        project.write_chapter(&chapter).unwrap();
+        assert!(titles(&project, "lighthouse").is_empty());
+        assert_eq!(titles(&project, "windmill"), vec!["One"]);
+    }
+
+    #[test]
+    fn an_outdated_index_file_is_rebuilt() {
+        let (_dir, project, _) = project_with(&[("One", "The orchard in spring.")]);
+        assert_eq!(titles(&project, "orchard"), vec!["One"]);
+
+        let path = index_path(&project);
+        let mut raw: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        raw["version"] = (INDEX_VERSION - 1).into();
+        raw["postings"] = serde_json::json!({});
+        fs::write(&path, serde_json::to_vec(&raw).unwrap()).unwrap();
+
+        assert_eq!(titles(&project, "orchard"), vec!["One"]);
+        let rebuilt: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(rebuilt["version"], INDEX_VERSION);
+    }
+}
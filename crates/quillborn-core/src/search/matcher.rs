@@ -0,0 +1,329 @@
+//! Matching that follows Unicode rather than bytes.
+//!
+//! Literal queries are compared after canonical decomposition and, unless
+//! the search is case-sensitive, full case folding, so `STRASSE` finds
+//! `Straße` and a precomposed `é` finds `e` + U+0301. With accent-insensitive
+//! matching the combining marks are dropped as well. Each line is
+//! transformed the same way as the query, and matches are mapped back to
+//! the original text; a match that only covers part of a character's
+//! expansion (`i` against the `i̇` that `İ` folds to) doesn't count.
+//!
+//! Regular expressions run on the line itself, with the `regex` crate's
+//! simple case folding; only accent stripping is applied to both sides.
+//! Fuzzy matching compares word by word; see [`super::fuzzy`].
+
+use caseless::Caseless;
+use regex::{Regex, RegexBuilder};
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::fuzzy::FuzzyWord;
+use super::{SearchMode, SearchOptions};
+use crate::manuscript::project::ProjectError;
+
+/// A match within a line, as byte ranges into the original line. `groups`
+/// holds the capture groups in regex mode, `None` where a group did not
+/// take part.
+pub(super) struct LineMatch {
+    pub start: usize,
+    pub end: usize,
+    pub groups: Vec<Option<(usize, usize)>>,
+}
+
+/// A line after folding, remembering where each original character went.
+struct Folded {
+    text: String,
+    /// For each original character: its byte range in the line, then the
+    /// byte range of what it became in `text`.
+    chars: Vec<(usize, usize, usize, usize)>,
+}
+
+impl Folded {
+    /// The original range for a range of the folded text, or `None` when
+    /// either end falls inside a character's expansion.
+    fn original(&self, start: usize, end: usize, line_len: usize) -> Option<(usize, usize)> {
+        let at_start = self.chars.partition_point(|c| c.2 < start);
+        let first = self.chars[at_start..]
+            .iter()
+            .take_while(|c| c.2 == start)
+            .find(|c| c.3 > c.2)
+            .or_else(|| self.chars.get(at_start).filter(|c| c.2 == start));
+        let orig_start = match first {
+            Some(c) => c.0,
+            None if start == self.text.len() => line_len,
+            None => return None,
+        };
+        if end == start {
+            return Some((orig_start, orig_start));
+        }
+        // The last character ending here, so marks stripped after a base
+        // letter stay with it.
+        let at_end = self.chars.partition_point(|c| c.3 <= end);
+        let last = at_end.checked_sub(1).map(|i| self.chars[i]).filter(|c| c.3 == end)?;
+        Some((orig_start, last.1))
+    }
+}
+
+/// What a [`Matcher`] runs over each line.
+enum Search {
+    Regex(Regex),
+    Fuzzy(FuzzyWord),
+}
+
+pub(super) struct Matcher {
+    search: Search,
+    /// Whether lines go through [`fold`] before matching.
+    fold_lines: bool,
+    fold_case: bool,
+    strip_marks: bool,
+    whole_word: bool,
+}
+
+/// Decompose, optionally case fold and optionally drop combining marks,
+/// calling `emit` for each resulting character.
+fn fold_char(c: char, fold_case: bool, strip_marks: bool, emit: &mut impl FnMut(char)) {
+    let mut push = |g: char| {
+        if !(strip_marks && is_combining_mark(g)) {
+            emit(g);
+        }
+    };
+    decompose_canonical(c, |d| {
+        if fold_case {
+            for f in std::iter::once(d).default_case_fold() {
+                decompose_canonical(f, &mut push);
+            }
+        } else {
+            push(d);
+        }
+    });
+}
+
+fn fold(line: &str, fold_case: bool, strip_marks: bool) -> Folded {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = Vec::with_capacity(line.len());
+    for (i, c) in line.char_indices() {
+        let folded_start = text.len();
+        fold_char(c, fold_case, strip_marks, &mut |g| text.push(g));
+        chars.push((i, i + c.len_utf8(), folded_start, text.len()));
+    }
+    Folded { text, chars }
+}
+
+pub(super) fn fold_str(text: &str, fold_case: bool, strip_marks: bool) -> String {
+    fold(text, fold_case, strip_marks).text
+}
+
+/// Where a regex fails to parse, as a byte range of the pattern the user
+/// typed. `None` when the pattern itself is fine and only the compiled
+/// regex was refused, e.g. for being too big.
+fn syntax_error_span(pattern: &str) -> Option<(usize, usize)> {
+    let error = regex_syntax::Parser::new().parse(pattern).err()?;
+    let span = match &error {
+        regex_syntax::Error::Parse(e) => e.span(),
+        regex_syntax::Error::Translate(e) => e.span(),
+        _ => return None,
+    };
+    Some((span.start.offset, span.end.offset))
+}
+
+impl Matcher {
+    pub fn new(query: &str, options: &SearchOptions) -> Result<Self, ProjectError> {
+        let strip_marks = options.accent_insensitive;
+        let (pattern, fold_lines, fold_case, case_insensitive) = match options.mode {
+            SearchMode::Fuzzy => {
+                return Ok(Matcher {
+                    search: Search::Fuzzy(FuzzyWord::new(query)?),
+                    fold_lines: false,
+                    fold_case: false,
+                    strip_marks: false,
+                    whole_word: false,
+                });
+            }
+            SearchMode::Literal => {
+                let fold_case = !options.case_sensitive;
+                (regex::escape(&fold_str(query, fold_case, strip_marks)), true, fold_case, false)
+            }
+            SearchMode::Regex => {
+                let pattern = if strip_marks { fold_str(query, false, true) } else { query.to_string() };
+                (pattern, strip_marks, false, !options.case_sensitive)
+            }
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|e| ProjectError::InvalidSearchPattern {
+                pattern: query.to_string(),
+                message: e.to_string(),
+                span: syntax_error_span(query),
+            })?;
+        Ok(Matcher {
+            search: Search::Regex(regex),
+            fold_lines,
+            fold_case,
+            strip_marks,
+            whole_word: options.whole_word,
+        })
+    }
+
+    /// Every match in one line, in order.
+    pub fn find_line(&self, line: &str) -> Vec<LineMatch> {
+        let regex = match &self.search {
+            Search::Regex(regex) => regex,
+            Search::Fuzzy(fuzzy) => return fuzzy.find_line(line),
+        };
+        let folded = self.fold_lines.then(|| fold(line, self.fold_case, self.strip_marks));
+        let haystack = folded.as_ref().map_or(line, |f| f.text.as_str());
+        let to_original = |start: usize, end: usize| match &folded {
+            Some(folded) => folded.original(start, end, line.len()),
+            None => Some((start, end)),
+        };
+        let boundaries: Vec<usize> = if self.whole_word {
+            line.split_word_bound_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(line.len()))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let mut matches = Vec::new();
+        for captures in regex.captures_iter(haystack) {
+            let whole = captures.get(0).expect("capture 0 is always present");
+            let Some((start, end)) = to_original(whole.start(), whole.end()) else {
+                continue;
+            };
+            if self.whole_word && !(boundaries.contains(&start) && boundaries.contains(&end)) {
+                continue;
+            }
+            let groups = captures
+                .iter()
+                .map(|group| group.and_then(|g| to_original(g.start(), g.end())))
+                .collect();
+            matches.push(LineMatch { start, end, groups });
+        }
+        matches
+    }
+
+    /// Fill `$1`, `${1}`, `$name` and `${name}` in a replacement from a
+    /// match's groups. `$$` is a `$`; unknown groups are left empty.
+    pub fn expand(&self, template: &str, line: &str, found: &LineMatch) -> String {
+        let group_text = |name: &str| {
+            let index = name.parse::<usize>().ok().or_else(|| match &self.search {
+                Search::Regex(regex) => regex.capture_names().position(|n| n == Some(name)),
+                Search::Fuzzy(_) => None,
+            });
+            index
+                .and_then(|i| found.groups.get(i).copied().flatten())
+                .map_or("", |(start, end)| &line[start..end])
+        };
+
+        let mut output = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(at) = rest.find('$') {
+            output.push_str(&rest[..at]);
+            rest = &rest[at + 1..];
+            if let Some(after) = rest.strip_prefix('$') {
+                output.push('$');
+                rest = after;
+            } else if let Some((name, after)) = rest.strip_prefix('{').and_then(|r| r.split_once('}')) {
+                output.push_str(group_text(name));
+                rest = after;
+            } else {
+                // `$1st` means group 1 followed by "st", as in most editors.
+                let numeric = rest.starts_with(|c: char| c.is_ascii_digit());
+                let len = rest
+                    .find(|c: char| {
+                        if numeric {
+                            !c.is_ascii_digit()
+                        } else {
+                            !(c.is_ascii_alphanumeric() || c == '_')
+                        }
+                    })
+                    .unwrap_or(rest.len());
+                if len == 0 {
+                    output.push('$');
+                } else {
+                    output.push_str(group_text(&rest[..len]));
+                }
+                rest = &rest[len..];
+            }
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::MatchSpan;
+
+    fn spans(query: &str, line: &str, options: &SearchOptions) -> Vec<MatchSpan> {
+        Matcher::new(query, options)
+            .unwrap()
+            .find_line(line)
+            .iter()
+            .map(|m| MatchSpan::new(line, m.start, m.end))
+            .collect()
+    }
+
+    fn ranges(spans: &[MatchSpan]) -> Vec<[usize; 6]> {
+        spans
+            .iter()
+            .map(|s| [s.start, s.end, s.start_char, s.end_char, s.start_utf16, s.end_utf16])
+            .collect()
+    }
+
+    #[test]
+    fn sharp_s_and_double_s_find_each_other() {
+        let options = SearchOptions::default();
+        let line = "In der Straße.";
+        assert_eq!(ranges(&spans("STRASSE", line, &options)), vec![[7, 14, 7, 13, 7, 13]]);
+        assert_eq!(ranges(&spans("straße", "THE STRASSE", &options)), vec![[4, 11, 4, 11, 4, 11]]);
+
+        let case_sensitive = SearchOptions { case_sensitive: true, ..SearchOptions::default() };
+        assert!(spans("STRASSE", line, &case_sensitive).is_empty());
+    }
+
+    #[test]
+    fn accent_folded_spans_cover_the_original_characters() {
+        let options = SearchOptions { accent_insensitive: true, ..SearchOptions::default() };
+        // The emoji is four bytes, two UTF-16 units and one character; the
+        // second "café" carries a combining acute that belongs to the match.
+        let line = "😀 café and cafe\u{301}.";
+        assert_eq!(
+            ranges(&spans("cafe", line, &options)),
+            vec![[5, 10, 2, 6, 3, 7], [15, 21, 11, 16, 12, 17]]
+        );
+    }
+
+    #[test]
+    fn precomposed_and_decomposed_accents_match_without_folding_them_away() {
+        let options = SearchOptions::default();
+        assert_eq!(spans("é", "cafe\u{301}", &options).len(), 1);
+        assert_eq!(spans("e\u{301}", "café", &options).len(), 1);
+        assert!(spans("é", "cafe", &options).is_empty());
+    }
+
+    #[test]
+    fn part_of_a_characters_expansion_is_not_a_match() {
+        // İ folds to i + U+0307, so a bare "i" would only cover half of it.
+        assert!(spans("i", "İ", &SearchOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn whole_word_skips_matches_inside_words() {
+        let options = SearchOptions { whole_word: true, ..SearchOptions::default() };
+        let found = spans("cat", "Cat concatenate cat.", &options);
+        assert_eq!(found.iter().map(|s| s.start).collect::<Vec<_>>(), vec![0, 16]);
+    }
+
+    #[test]
+    fn regex_errors_carry_the_offset_in_the_pattern() {
+        let options = SearchOptions { mode: SearchMode::Regex, ..SearchOptions::default() };
+        let Err(ProjectError::InvalidSearchPattern { span, .. }) = Matcher::new("ok(", &options) else {
+            panic!("expected an invalid pattern");
+        };
+        assert_eq!(span.map(|(start, _)| start), Some(2));
+    }
+}
@@ -0,0 +1,244 @@
+mod collections;
+mod fuzzy;
+mod index;
+mod matcher;
+mod query;
+mod replace;
+mod sources;
+mod stream;
+
+pub use collections::{SmartCollection, SmartCollections};
+pub use fuzzy::{fuzzy_search, FuzzyResults, FuzzyTerm};
+pub use index::{search_index, DocKind, IndexHit, SearchIndex};
+pub(crate) use index::{stamp, Stamp};
+pub use replace::{apply_replace, preview_replace, ChapterWordCount, ProposedReplacement, ReplaceOutcome};
+pub use sources::{search_project, SourceGroup, SourceKind, SourceMatch, SourceResult};
+pub use stream::{ManuscriptSearch, SearchFinished, SearchResultEvent, SearchSummary, FINISHED_EVENT, RESULT_EVENT};
+
+use serde::{Deserialize, Serialize};
+
+use crate::manuscript::chapter::Chapter;
+use crate::manuscript::ids::ChapterId;
+use crate::manuscript::project::{Project, ProjectError};
+use matcher::{LineMatch, Matcher};
+
+/// Characters of context kept on either side of a match in a [`Snippet`].
+pub const SNIPPET_CONTEXT: usize = 80;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchResult {
+    pub chapter_id: String,
+    pub chapter_title: String,
+    pub matches: Vec<SearchMatch>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchMatch {
+    pub line_number: usize,
+    #[serde(flatten)]
+    pub span: MatchSpan,
+    pub snippet: Snippet,
+}
+
+/// A match with the text around it on its line, cut to
+/// [`SNIPPET_CONTEXT`] characters each side. Cuts fall between words where
+/// possible and are marked with `…`.
+#[derive(Debug, Serialize, Clone)]
+pub struct Snippet {
+    pub before: String,
+    pub matched: String,
+    pub after: String,
+}
+
+impl Snippet {
+    fn new(line: &str, start: usize, end: usize) -> Self {
+        Snippet {
+            before: context_before(&line[..start]),
+            matched: line[start..end].to_string(),
+            after: context_after(&line[end..]),
+        }
+    }
+}
+
+fn context_before(text: &str) -> String {
+    let Some((cut, _)) = text.char_indices().rev().nth(SNIPPET_CONTEXT - 1).filter(|(cut, _)| *cut > 0) else {
+        return text.to_string();
+    };
+    let mut kept = &text[cut..];
+    if !text[..cut].ends_with(char::is_whitespace) {
+        if let Some(space) = kept.find(char::is_whitespace) {
+            kept = &kept[space..];
+        }
+    }
+    format!("…{}", kept.trim_start())
+}
+
+fn context_after(text: &str) -> String {
+    let Some((cut, _)) = text.char_indices().nth(SNIPPET_CONTEXT) else {
+        return text.to_string();
+    };
+    let mut kept = &text[..cut];
+    if !text[cut..].starts_with(char::is_whitespace) {
+        if let Some(space) = kept.rfind(char::is_whitespace) {
+            kept = &kept[..space];
+        }
+    }
+    format!("{}…", kept.trim_end())
+}
+
+/// Where a match sits within its line, in the units each consumer needs:
+/// bytes for Rust, UTF-16 code units for JavaScript strings and editors,
+/// and characters (Unicode scalar values).
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct MatchSpan {
+    pub start: usize,
+    pub end: usize,
+    pub start_utf16: usize,
+    pub end_utf16: usize,
+    pub start_char: usize,
+    pub end_char: usize,
+}
+
+impl MatchSpan {
+    pub(crate) fn new(line: &str, start: usize, end: usize) -> Self {
+        let (before, matched) = (&line[..start], &line[start..end]);
+        let start_utf16 = before.encode_utf16().count();
+        let start_char = before.chars().count();
+        MatchSpan {
+            start,
+            end,
+            start_utf16,
+            end_utf16: start_utf16 + matched.encode_utf16().count(),
+            start_char,
+            end_char: start_char + matched.chars().count(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    /// The query is plain text.
+    #[default]
+    Literal,
+    /// The query is a regular expression in Rust `regex` syntax, matched
+    /// line by line, so `^` and `$` anchor to line boundaries.
+    Regex,
+    /// The query is a single word, matched by spelling or sound with a
+    /// few typos allowed. Case and accents never matter.
+    Fuzzy,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub mode: SearchMode,
+    /// Only count matches that start and end on a Unicode word boundary.
+    pub whole_word: bool,
+    /// Ignore accents and other combining marks, so `cafe` finds `café`.
+    pub accent_insensitive: bool,
+}
+
+/// One match inside a chapter's text. `line_start` is the byte offset of
+/// the line in the whole text; the match's own offsets are relative to the
+/// line.
+struct Found<'t> {
+    line_number: usize,
+    line: &'t str,
+    line_start: usize,
+    matched: LineMatch,
+}
+
+impl Found<'_> {
+    fn span(&self) -> MatchSpan {
+        MatchSpan::new(self.line, self.matched.start, self.matched.end)
+    }
+
+    fn to_match(&self) -> SearchMatch {
+        SearchMatch {
+            line_number: self.line_number,
+            span: self.span(),
+            snippet: Snippet::new(self.line, self.matched.start, self.matched.end),
+        }
+    }
+}
+
+/// Every match in `text`, line by line, ignoring a trailing `\r`.
+fn find_all<'t>(matcher: &Matcher, text: &'t str) -> Vec<Found<'t>> {
+    let mut found = Vec::new();
+    let mut line_start = 0;
+    for (line_idx, raw) in text.split('\n').enumerate() {
+        let line = raw.strip_suffix('\r').unwrap_or(raw);
+        for matched in matcher.find_line(line) {
+            found.push(Found {
+                line_number: line_idx + 1,
+                line,
+                line_start,
+                matched,
+            });
+        }
+        line_start += raw.len() + 1;
+    }
+    found
+}
+
+/// Each chapter that can be read, in reading order, with its title from
+/// the manuscript tree.
+fn chapters(project: &Project) -> impl Iterator<Item = (ChapterId, String, Chapter)> + '_ {
+    project.chapter_order().into_iter().filter_map(|id| {
+        let node = project.structure.nodes.get(id.as_str())?;
+        let chapter = project.load_chapter(&id).ok()?;
+        Some((id, node.title.clone(), chapter))
+    })
+}
+
+/// Find every occurrence of `query` in the manuscript, in reading order.
+pub fn search_manuscript(
+    project: &Project,
+    query: &str,
+    options: &SearchOptions,
+) -> Result<Vec<SearchResult>, ProjectError> {
+    let mut results = Vec::new();
+    ManuscriptSearch::new(query, options)?.run(project, || false, |result| results.push(result));
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_context_is_kept_whole() {
+        // Exactly SNIPPET_CONTEXT characters each side, all multi-byte.
+        let side = "é".repeat(SNIPPET_CONTEXT);
+        let line = format!("{}ñ{}", side, side);
+        let snippet = Snippet::new(&line, side.len(), side.len() + "ñ".len());
+        assert_eq!((snippet.before.as_str(), snippet.matched.as_str()), (side.as_str(), "ñ"));
+        assert_eq!(snippet.after, side);
+    }
+
+    #[test]
+    fn long_context_is_cut_between_words_with_ellipses() {
+        let words = "ñandú ".repeat(20);
+        let line = format!("{}lantern {}", words, words.trim_end());
+        let start = words.len();
+        let snippet = Snippet::new(&line, start, start + "lantern".len());
+
+        assert_eq!(snippet.matched, "lantern");
+        assert_eq!(snippet.before, format!("…{}", "ñandú ".repeat(13)));
+        assert_eq!(snippet.after, format!(" {}…", ["ñandú"; 13].join(" ")));
+        for side in [&snippet.before, &snippet.after] {
+            assert!(side.trim_matches('…').chars().count() <= SNIPPET_CONTEXT);
+        }
+    }
+
+    #[test]
+    fn a_context_without_spaces_is_cut_mid_word() {
+        let side = "ü".repeat(100);
+        let line = format!("{}x{}", side, side);
+        let snippet = Snippet::new(&line, side.len(), side.len() + 1);
+        assert_eq!(snippet.before, format!("…{}", "ü".repeat(SNIPPET_CONTEXT)));
+        assert_eq!(snippet.after, format!("{}…", "ü".repeat(SNIPPET_CONTEXT)));
+    }
+}
@@ -0,0 +1,419 @@
+//! The query language of the full-text index.
+//!
+//! ```text
+//! "red door" AND NOT kitchen pov:Mara status:draft part:"Part Two"
+//! ```
+//!
+//! - Words match anywhere, `word*` matches a prefix and `"quoted words"` a
+//!   phrase. A bare word that breaks into several words, like `well-known`,
+//!   is a phrase too.
+//! - Terms next to each other must all match; `AND` may be written out.
+//!   `OR` matches either side and binds looser than `AND`, so `a b OR c` is
+//!   `(a b) OR c`. `NOT x` or `-x` excludes, and parentheses group.
+//! - `a NEAR/n b` matches when at most `n` words separate `a` and `b`, in
+//!   either order; plain `NEAR` allows ten.
+//! - `field:value` filters by metadata, with `field:"two words"` for
+//!   values with spaces. Values ignore case and accents.
+//!
+//! Operators are only recognised in capitals; `and`, `or` and `not` are
+//! ordinary words.
+
+use std::iter::Peekable;
+
+use super::index::tokenize;
+use crate::manuscript::project::{ChapterStatus, ProjectError};
+
+/// Words allowed between the two sides of a bare `NEAR`.
+const DEFAULT_NEAR: u32 = 10;
+
+/// How deeply parentheses and `NOT`s may nest, so a hostile query can't
+/// exhaust the stack.
+const MAX_DEPTH: usize = 64;
+
+/// Text to look for in the index, already normalised.
+#[derive(Debug, Clone)]
+pub(super) enum Text {
+    Word(String),
+    Prefix(String),
+    Phrase(Vec<String>),
+}
+
+/// Where a query looks, from `in:`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Scope {
+    /// Chapters that aren't in the trash.
+    Manuscript,
+    Notes,
+    /// Chapters with the trash status.
+    Trash,
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Filter {
+    Pov(String),
+    Mood(String),
+    Status(ChapterStatus),
+    /// A note or manuscript node tag.
+    Tag(String),
+    /// A manuscript node type, or `note`.
+    Type(String),
+    /// Chapters inside the node with this title or id, or that node itself.
+    Part(String),
+    /// Notes in this category.
+    Category(String),
+    Scope(Scope),
+}
+
+impl Filter {
+    /// Whether this filter asks for trashed chapters, which are otherwise
+    /// left out of every search.
+    fn wants_trash(&self) -> bool {
+        matches!(self, Filter::Status(ChapterStatus::Trash) | Filter::Scope(Scope::Trash))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Expr {
+    Text(Text),
+    Near(Text, Text, u32),
+    Filter(Filter),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+impl Expr {
+    /// Whether any part of the query asks for trashed chapters.
+    pub fn wants_trash(&self) -> bool {
+        match self {
+            Expr::Filter(filter) => filter.wants_trash(),
+            Expr::Not(inner) => inner.wants_trash(),
+            Expr::And(parts) | Expr::Or(parts) => parts.iter().any(Expr::wants_trash),
+            Expr::Text(_) | Expr::Near(..) => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Near(u32),
+    Quoted(String),
+    Word(String),
+    Field(String, String),
+}
+
+const FIELDS: &[&str] = &["pov", "mood", "status", "tag", "type", "part", "category", "in"];
+
+fn invalid(query: &str, message: impl Into<String>) -> ProjectError {
+    ProjectError::InvalidSearchPattern {
+        pattern: query.to_string(),
+        message: message.into(),
+        span: None,
+    }
+}
+
+fn lex(query: &str) -> Result<Vec<Token>, ProjectError> {
+    let mut tokens = Vec::new();
+    let mut rest = query;
+    loop {
+        rest = rest.trim_start();
+        let Some(c) = rest.chars().next() else {
+            break;
+        };
+        match c {
+            '(' => {
+                tokens.push(Token::Open);
+                rest = &rest[1..];
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                rest = &rest[1..];
+            }
+            '"' => {
+                let (quoted, after) = rest[1..].split_once('"').unwrap_or((&rest[1..], ""));
+                tokens.push(Token::Quoted(quoted.to_string()));
+                rest = after;
+            }
+            '-' if rest[1..].starts_with(|c: char| !c.is_whitespace()) => {
+                tokens.push(Token::Not);
+                rest = &rest[1..];
+            }
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '"'))
+                    .unwrap_or(rest.len());
+                let word = &rest[..end];
+                rest = &rest[end..];
+                if let Some((name, value)) = word.split_once(':') {
+                    let quoted_value = value.is_empty() && rest.starts_with('"');
+                    let is_field = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphabetic());
+                    // `Note:` on its own is just a word.
+                    if is_field && (!value.is_empty() || quoted_value) {
+                        let name = name.to_ascii_lowercase();
+                        if !FIELDS.contains(&name.as_str()) {
+                            return Err(invalid(
+                                query,
+                                format!("unknown field `{}:`; use one of {}", name, FIELDS.join(", ")),
+                            ));
+                        }
+                        let value = if quoted_value {
+                            let (quoted, after) = rest[1..].split_once('"').unwrap_or((&rest[1..], ""));
+                            rest = after;
+                            quoted
+                        } else {
+                            value
+                        };
+                        if value.trim().is_empty() {
+                            return Err(invalid(query, format!("`{}:` needs a value", name)));
+                        }
+                        tokens.push(Token::Field(name, value.trim().to_string()));
+                        continue;
+                    }
+                }
+                tokens.push(match word {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    "NEAR" => Token::Near(DEFAULT_NEAR),
+                    _ => match word.strip_prefix("NEAR/").map(str::parse) {
+                        Some(Ok(distance)) => Token::Near(distance),
+                        Some(Err(_)) => return Err(invalid(query, format!("`{}` needs a number after the /", word))),
+                        None => Token::Word(word.to_string()),
+                    },
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn text(words: Vec<String>) -> Option<Text> {
+    match words.len() {
+        0 => None,
+        1 => words.into_iter().next().map(Text::Word),
+        _ => Some(Text::Phrase(words)),
+    }
+}
+
+fn filter(query: &str, name: &str, value: String) -> Result<Filter, ProjectError> {
+    let lower = value.to_lowercase();
+    let bad = |allowed: &str| invalid(query, format!("`{}:{}` should be one of {}", name, value, allowed));
+    Ok(match name {
+        "pov" => Filter::Pov(value),
+        "mood" => Filter::Mood(value),
+        "status" => Filter::Status(match lower.as_str() {
+            "draft" => ChapterStatus::Draft,
+            "revised" => ChapterStatus::Revised,
+            "final" => ChapterStatus::Final,
+            "trash" => ChapterStatus::Trash,
+            _ => return Err(bad("draft, revised, final, trash")),
+        }),
+        "tag" => Filter::Tag(value),
+        "type" => match lower.as_str() {
+            "book" | "part" | "chapter" | "scene" | "note" => Filter::Type(lower),
+            _ => return Err(bad("book, part, chapter, scene, note")),
+        },
+        "part" => Filter::Part(value),
+        "category" => Filter::Category(value),
+        _ => Filter::Scope(match lower.as_str() {
+            "manuscript" | "chapters" => Scope::Manuscript,
+            "notes" => Scope::Notes,
+            "trash" => Scope::Trash,
+            _ => return Err(bad("manuscript, notes, trash")),
+        }),
+    })
+}
+
+struct Parser<'q> {
+    query: &'q str,
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// Run `f` one nesting level deeper.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, ProjectError>) -> Result<T, ProjectError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(invalid(self.query, "too deeply nested"));
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn peek(&mut self) -> Option<&Token> {
+        self.tokens.peek()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.tokens.next()
+    }
+
+    fn or(&mut self) -> Result<Option<Expr>, ProjectError> {
+        let mut parts = Vec::new();
+        parts.extend(self.and()?);
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            match self.and()? {
+                Some(part) if !parts.is_empty() => parts.push(part),
+                _ => return Err(invalid(self.query, "OR needs something on both sides")),
+            }
+        }
+        Ok(match parts.len() {
+            0 => None,
+            1 => parts.pop(),
+            _ => Some(Expr::Or(parts)),
+        })
+    }
+
+    fn and(&mut self) -> Result<Option<Expr>, ProjectError> {
+        let mut parts = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(Token::Close) | Some(Token::Or) => break,
+                Some(Token::And) => {
+                    self.next();
+                }
+                _ => parts.extend(self.unary()?),
+            }
+        }
+        Ok(match parts.len() {
+            0 => None,
+            1 => parts.pop(),
+            _ => Some(Expr::And(parts)),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Option<Expr>, ProjectError> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            if matches!(self.peek(), None | Some(Token::Close | Token::Or | Token::And)) {
+                return Err(invalid(self.query, "NOT needs something to exclude"));
+            }
+            let inner = self.nested(Self::unary)?;
+            return Ok(inner.map(|inner| Expr::Not(Box::new(inner))));
+        }
+        let mut left = self.primary()?;
+        while let Some(&Token::Near(distance)) = self.peek() {
+            self.next();
+            let right = self.primary()?;
+            let (Some(Expr::Text(a)), Some(Expr::Text(b))) = (&left, &right) else {
+                return Err(invalid(self.query, "NEAR needs words or phrases on both sides"));
+            };
+            left = Some(Expr::Near(a.clone(), b.clone(), distance));
+        }
+        Ok(left)
+    }
+
+    fn primary(&mut self) -> Result<Option<Expr>, ProjectError> {
+        Ok(match self.next() {
+            Some(Token::Open) => {
+                let inner = self.nested(Self::or)?;
+                if self.next() != Some(Token::Close) {
+                    return Err(invalid(self.query, "missing )"));
+                }
+                inner
+            }
+            Some(Token::Quoted(phrase)) => text(tokenize(&phrase)).map(Expr::Text),
+            Some(Token::Word(word)) => match word.strip_suffix('*').map(tokenize) {
+                Some(mut words) if words.len() == 1 => Some(Expr::Text(Text::Prefix(words.remove(0)))),
+                Some(words) => text(words).map(Expr::Text),
+                None => text(tokenize(&word)).map(Expr::Text),
+            },
+            Some(Token::Field(name, value)) => Some(Expr::Filter(filter(self.query, &name, value)?)),
+            Some(Token::Close) => return Err(invalid(self.query, "unexpected )")),
+            Some(Token::And | Token::Or | Token::Not | Token::Near(_)) | None => {
+                return Err(invalid(self.query, "an operator is missing something on one side"));
+            }
+        })
+    }
+}
+
+/// Parse a query. `None` means it has nothing to look for, such as an
+/// empty query or one made only of punctuation.
+pub(super) fn parse(query: &str) -> Result<Option<Expr>, ProjectError> {
+    let mut parser = Parser {
+        query,
+        tokens: lex(query)?.into_iter().peekable(),
+        depth: 0,
+    };
+    let expr = parser.or()?;
+    if parser.peek().is_some() {
+        return Err(invalid(query, "unexpected )"));
+    }
+    Ok(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(query: &str) -> Expr {
+        parse(query).unwrap().expect("query should not be empty")
+    }
+
+    fn is_word(expr: &Expr, expected: &str) -> bool {
+        matches!(expr, Expr::Text(Text::Word(word)) if word == expected)
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or_and_not_tighter_than_and() {
+        let Expr::Or(sides) = parsed("a OR b AND NOT c") else {
+            panic!("expected OR at the top");
+        };
+        assert_eq!(sides.len(), 2);
+        assert!(is_word(&sides[0], "a"));
+        let Expr::And(parts) = &sides[1] else {
+            panic!("expected b AND NOT c, got {:?}", sides[1]);
+        };
+        assert!(is_word(&parts[0], "b"));
+        assert!(matches!(&parts[1], Expr::Not(inner) if is_word(inner, "c")));
+    }
+
+    #[test]
+    fn quoted_field_values_keep_their_spaces() {
+        let Expr::And(parts) = parsed("\"Red Door\" part:\"Part Two\"") else {
+            panic!("expected a phrase and a filter");
+        };
+        assert!(matches!(&parts[0], Expr::Text(Text::Phrase(words)) if words == &["red", "door"]));
+        assert!(matches!(&parts[1], Expr::Filter(Filter::Part(part)) if part == "Part Two"));
+    }
+
+    #[test]
+    fn near_takes_an_optional_distance() {
+        assert!(matches!(parsed("a NEAR/3 \"b c\""), Expr::Near(Text::Word(_), Text::Phrase(_), 3)));
+        assert!(matches!(parsed("a NEAR b"), Expr::Near(_, _, DEFAULT_NEAR)));
+        assert!(parse("a NEAR/x b").is_err());
+        assert!(parse("a NEAR pov:Mara").is_err());
+    }
+
+    #[test]
+    fn lowercase_operators_are_words() {
+        let Expr::And(parts) = parsed("a or b") else {
+            panic!("expected three words");
+        };
+        assert!(is_word(&parts[1], "or"));
+    }
+
+    #[test]
+    fn malformed_queries_are_rejected() {
+        for query in ["a OR", "NOT", "(a", "a)", "colour:red", "status:lost", "pov:\"\""] {
+            assert!(parse(query).is_err(), "{} should not parse", query);
+        }
+        assert!(parse("  ... ").unwrap().is_none());
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_not_a_crash() {
+        let parens = format!("{}a{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert!(parse(&parens).is_err());
+        assert!(parse(&"NOT ".repeat(100_000)).is_err());
+        assert!(parse(&format!("{}a{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH))).is_ok());
+    }
+}
@@ -0,0 +1,230 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use super::matcher::Matcher;
+use super::{chapters, find_all, MatchSpan, SearchMode, SearchOptions};
+use crate::manuscript::history::{chapter_key, Capture, History, OperationKind};
+use crate::manuscript::ids::ChapterId;
+use crate::manuscript::project::{Project, ProjectError};
+
+/// One change a replace would make, as returned by the dry run.
+#[derive(Debug, Serialize, Clone)]
+pub struct ProposedReplacement {
+    /// Pass back to [`apply_replace`] to select this change. It stays valid
+    /// for as long as the chapter isn't edited.
+    pub id: String,
+    pub chapter_id: String,
+    pub chapter_title: String,
+    pub line_number: usize,
+    pub line_content: String,
+    #[serde(flatten)]
+    pub span: MatchSpan,
+    pub matched: String,
+    /// The text that replaces the match, with capture groups filled in.
+    pub replacement: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ChapterWordCount {
+    pub chapter_id: String,
+    pub word_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplaceOutcome {
+    pub replaced: usize,
+    /// Selected ids that no longer match, because the text changed after
+    /// the dry run. They are left alone.
+    pub skipped: Vec<String>,
+    /// Snapshot taken before anything was written, if anything was.
+    pub snapshot: Option<String>,
+    /// New word counts of the chapters that changed.
+    pub chapters: Vec<ChapterWordCount>,
+}
+
+/// A proposed change plus where it sits in the chapter's whole text.
+struct Planned {
+    proposal: ProposedReplacement,
+    start: usize,
+    end: usize,
+}
+
+/// Work out every replacement, chapter by chapter, without writing.
+fn plan(
+    project: &Project,
+    matcher: &Matcher,
+    replacement: &str,
+    mode: SearchMode,
+) -> Vec<(ChapterId, String, Vec<Planned>)> {
+    let mut planned = Vec::new();
+    for (chapter_id, title, chapter) in chapters(project) {
+        let changes: Vec<Planned> = find_all(matcher, &chapter.content)
+            .into_iter()
+            .map(|found| {
+                let (start, end) = (found.matched.start, found.matched.end);
+                let replacement = match mode {
+                    SearchMode::Literal | SearchMode::Fuzzy => replacement.to_string(),
+                    SearchMode::Regex => matcher.expand(replacement, found.line, &found.matched),
+                };
+                Planned {
+                    start: found.line_start + start,
+                    end: found.line_start + end,
+                    proposal: ProposedReplacement {
+                        id: format!("{}:{}:{}", chapter.id, found.line_number, start),
+                        chapter_id: chapter.id.clone(),
+                        chapter_title: title.clone(),
+                        line_number: found.line_number,
+                        line_content: found.line.to_string(),
+                        span: found.span(),
+                        matched: found.line[start..end].to_string(),
+                        replacement,
+                    },
+                }
+            })
+            .collect();
+        if !changes.is_empty() {
+            planned.push((chapter_id, chapter.content, changes));
+        }
+    }
+    planned
+}
+
+/// Dry run of a project-wide replace: every change it would make, in
+/// reading order. In regex mode the replacement may use `$1`, `${1}`,
+/// `$name` or `${name}` for capture groups and `$$` for a literal `$`; in
+/// the other modes it is used as is.
+pub fn preview_replace(
+    project: &Project,
+    query: &str,
+    replacement: &str,
+    options: &SearchOptions,
+) -> Result<Vec<ProposedReplacement>, ProjectError> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let matcher = Matcher::new(query, options)?;
+    Ok(plan(project, &matcher, replacement, options.mode)
+        .into_iter()
+        .flat_map(|(_, _, changes)| changes.into_iter().map(|c| c.proposal))
+        .collect())
+}
+
+/// Apply the changes picked from [`preview_replace`] by id. The matches are
+/// found again first, so a selection only applies where the text still
+/// matches. A snapshot of the chapters about to change is taken before
+/// writing, and the whole replace is recorded as one undoable operation.
+pub fn apply_replace(
+    project: &mut Project,
+    query: &str,
+    replacement: &str,
+    options: &SearchOptions,
+    selected: &[String],
+) -> Result<ReplaceOutcome, ProjectError> {
+    let mut outcome = ReplaceOutcome {
+        replaced: 0,
+        skipped: Vec::new(),
+        snapshot: None,
+        chapters: Vec::new(),
+    };
+    if query.is_empty() {
+        outcome.skipped = selected.to_vec();
+        return Ok(outcome);
+    }
+    let matcher = Matcher::new(query, options)?;
+
+    let mut wanted: HashSet<&str> = selected.iter().map(String::as_str).collect();
+    let mut edits = Vec::new();
+    for (chapter_id, content, changes) in plan(project, &matcher, replacement, options.mode) {
+        let chosen: Vec<Planned> = changes
+            .into_iter()
+            .filter(|c| wanted.remove(c.proposal.id.as_str()))
+            .collect();
+        if !chosen.is_empty() {
+            edits.push((chapter_id, content, chosen));
+        }
+    }
+    outcome.skipped = selected
+        .iter()
+        .filter(|id| wanted.contains(id.as_str()))
+        .cloned()
+        .collect();
+    if edits.is_empty() {
+        return Ok(outcome);
+    }
+
+    let touched: Vec<ChapterId> = edits.iter().map(|(chapter_id, ..)| chapter_id.clone()).collect();
+    outcome.snapshot = Some(project.create_content_snapshot(Some("before-replace"), &touched)?);
+    let before = Capture::take(project, touched.iter().map(chapter_key));
+
+    for (chapter_id, mut content, chosen) in edits {
+        // Back to front, so earlier offsets stay valid.
+        for change in chosen.iter().rev() {
+            content.replace_range(change.start..change.end, &change.proposal.replacement);
+        }
+        let chapter = project.update_chapter_content(&chapter_id, &content)?;
+        outcome.replaced += chosen.len();
+        outcome.chapters.push(ChapterWordCount {
+            chapter_id: chapter.id,
+            word_count: chapter.word_count,
+        });
+    }
+
+    History::record(
+        project,
+        OperationKind::ReplaceText,
+        format!("Replace \"{}\" with \"{}\"", query, replacement),
+        before,
+    );
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regex() -> SearchOptions {
+        SearchOptions {
+            mode: SearchMode::Regex,
+            case_sensitive: true,
+            ..SearchOptions::default()
+        }
+    }
+
+    #[test]
+    fn a_replace_can_be_restored_from_its_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut project = Project::create(dir.path(), "Replace", "Author").unwrap();
+        let chapter = project.add_chapter("One", None).unwrap();
+        let chapter_id = ChapterId::parse(&chapter.id).unwrap();
+        let original = "The grey cat sat.\nA grey dawn broke over the hills.";
+        project.update_chapter_content(&chapter_id, original).unwrap();
+
+        let proposed = preview_replace(&project, r"grey (\w+)", "${1}-grey", &regex()).unwrap();
+        let ids: Vec<String> = proposed.iter().map(|p| p.id.clone()).collect();
+        let outcome = apply_replace(&mut project, r"grey (\w+)", "${1}-grey", &regex(), &ids).unwrap();
+        assert_eq!(outcome.replaced, 2);
+        assert_eq!(
+            project.load_chapter(&chapter_id).unwrap().content,
+            "The cat-grey sat.\nA dawn-grey broke over the hills."
+        );
+
+        let restored = project.restore_snapshot(&outcome.snapshot.unwrap()).unwrap();
+        assert_eq!(restored, vec![chapter.id.clone()]);
+        let chapter = project.load_chapter(&chapter_id).unwrap();
+        assert_eq!(chapter.content, original);
+        assert_eq!(project.structure.nodes[chapter_id.as_str()].word_count, chapter.word_count);
+    }
+
+    #[test]
+    fn invalid_regexes_point_at_the_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = Project::create(dir.path(), "Replace", "Author").unwrap();
+
+        let error = preview_replace(&project, "café (grey", "x", &regex()).unwrap_err();
+        let ProjectError::InvalidSearchPattern { span: Some((start, _)), .. } = &error else {
+            panic!("expected a span, got {:?}", error);
+        };
+        assert_eq!(*start, "café ".len());
+        assert_eq!(error.details()["span"]["start_char"], 5);
+    }
+}
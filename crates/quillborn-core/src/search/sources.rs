@@ -0,0 +1,322 @@
+use serde::Serialize;
+
+use super::matcher::Matcher;
+use super::{chapters, find_all, SearchMatch, SearchOptions};
+use crate::manuscript::error::read_json;
+use crate::manuscript::project::{Project, ProjectError};
+use crate::planning::characters::{self, CharacterSheet};
+use crate::planning::ghost_notes;
+use crate::planning::notes::{self, Note};
+use crate::planning::timeline::Timeline;
+
+/// The kinds of text a project holds, in the order their groups are
+/// returned.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    Chapter,
+    /// Notes in any category, including wiki and worldbuilding pages.
+    Note,
+    Character,
+    GhostNote,
+    TimelineEvent,
+}
+
+impl SourceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SourceKind::Chapter => "chapter",
+            SourceKind::Note => "note",
+            SourceKind::Character => "character",
+            SourceKind::GhostNote => "ghost_note",
+            SourceKind::TimelineEvent => "timeline_event",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceMatch {
+    /// Where to jump to: `<kind>/<id>`, then `/<field>` for character
+    /// sheets, then `#L<line>`.
+    pub link: String,
+    #[serde(flatten)]
+    pub matched: SearchMatch,
+}
+
+/// The matches in one entity, or in one field of a character sheet.
+#[derive(Debug, Serialize)]
+pub struct SourceResult {
+    pub kind: SourceKind,
+    pub id: String,
+    /// Title of the entity the text belongs to. Ghost notes have none of
+    /// their own and use their chapter's.
+    pub title: String,
+    /// The character sheet field that matched; `None` for everything else.
+    pub field: Option<String>,
+    /// Note category, or the chapter a ghost note is attached to.
+    pub parent: Option<String>,
+    pub matches: Vec<SourceMatch>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceGroup {
+    pub kind: SourceKind,
+    pub results: Vec<SourceResult>,
+}
+
+/// An entity being searched.
+struct Entity<'a> {
+    kind: SourceKind,
+    id: &'a str,
+    title: &'a str,
+    parent: Option<&'a str>,
+}
+
+impl Entity<'_> {
+    fn search(&self, matcher: &Matcher, field: Option<&str>, text: &str) -> Option<SourceResult> {
+        let mut base = format!("{}/{}", self.kind.as_str(), self.id);
+        if let Some(field) = field {
+            base.push('/');
+            base.push_str(field);
+        }
+        let matches: Vec<SourceMatch> = find_all(matcher, text)
+            .iter()
+            .map(|found| SourceMatch {
+                link: format!("{}#L{}", base, found.line_number),
+                matched: found.to_match(),
+            })
+            .collect();
+        (!matches.is_empty()).then(|| SourceResult {
+            kind: self.kind,
+            id: self.id.to_string(),
+            title: self.title.to_string(),
+            field: field.map(str::to_string),
+            parent: self.parent.map(str::to_string),
+            matches,
+        })
+    }
+}
+
+/// Find `query` in every piece of text in the project: chapters, notes,
+/// character sheets, ghost notes and timeline event descriptions. Results
+/// are grouped by kind, and groups without matches are left out. Like the
+/// index, files that no longer parse are skipped rather than failing the
+/// whole search.
+pub fn search_project(
+    project: &Project,
+    query: &str,
+    options: &SearchOptions,
+) -> Result<Vec<SourceGroup>, ProjectError> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let matcher = Matcher::new(query, options)?;
+    let mut groups = Vec::new();
+    let mut push = |kind: SourceKind, results: Vec<SourceResult>| {
+        if !results.is_empty() {
+            groups.push(SourceGroup { kind, results });
+        }
+    };
+
+    let results = chapters(project)
+        .filter_map(|(_, title, chapter)| {
+            let entity = Entity {
+                kind: SourceKind::Chapter,
+                id: &chapter.id,
+                title: &title,
+                parent: None,
+            };
+            entity.search(&matcher, None, &chapter.content)
+        })
+        .collect();
+    push(SourceKind::Chapter, results);
+
+    let mut notes: Vec<Note> = notes::note_files(project)?
+        .into_iter()
+        .filter_map(|(category, path)| Note::from_file(&path, &category).ok())
+        .collect();
+    notes.sort_by_key(|n| n.title.to_lowercase());
+    let results = notes
+        .iter()
+        .filter_map(|note| {
+            let entity = Entity {
+                kind: SourceKind::Note,
+                id: &note.id,
+                title: &note.title,
+                parent: Some(&note.category),
+            };
+            entity.search(&matcher, None, &note.content)
+        })
+        .collect();
+    push(SourceKind::Note, results);
+
+    let mut sheets: Vec<CharacterSheet> = characters::character_files(project)?
+        .iter()
+        .filter_map(|path| read_json(path).ok())
+        .collect();
+    sheets.sort_by_key(|c| c.name.to_lowercase());
+    let mut results = Vec::new();
+    for sheet in sheets {
+        let entity = Entity {
+            kind: SourceKind::Character,
+            id: &sheet.id,
+            title: &sheet.name,
+            parent: None,
+        };
+        let mut custom: Vec<(&String, &String)> = sheet.custom_fields.iter().collect();
+        custom.sort();
+        let fields = [
+            ("role", &sheet.role),
+            ("description", &sheet.description),
+            ("arc_summary", &sheet.arc_summary),
+            ("speech_patterns", &sheet.speech_patterns),
+        ]
+        .into_iter()
+        .chain(custom.into_iter().map(|(name, value)| (name.as_str(), value)));
+        results.extend(fields.filter_map(|(field, text)| entity.search(&matcher, Some(field), text)));
+    }
+    push(SourceKind::Character, results);
+
+    let results = ghost_notes::list_ghost_notes(project)?
+        .iter()
+        .filter_map(|note| {
+            let title = project
+                .structure
+                .nodes
+                .get(&note.chapter_id)
+                .map_or("Ghost note", |node| node.title.as_str());
+            let entity = Entity {
+                kind: SourceKind::GhostNote,
+                id: &note.id,
+                title,
+                parent: Some(&note.chapter_id),
+            };
+            entity.search(&matcher, None, &note.message)
+        })
+        .collect();
+    push(SourceKind::GhostNote, results);
+
+    let results = Timeline::load(project)
+        .unwrap_or_default()
+        .events
+        .iter()
+        .filter_map(|event| {
+            let entity = Entity {
+                kind: SourceKind::TimelineEvent,
+                id: &event.id,
+                title: &event.title,
+                parent: None,
+            };
+            entity.search(&matcher, None, &event.description)
+        })
+        .collect();
+    push(SourceKind::TimelineEvent, results);
+
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manuscript::ids::ChapterId;
+    use crate::planning::ghost_notes::{GhostNote, GhostNoteTrigger};
+    use std::fs;
+
+    fn project(dir: &std::path::Path) -> Project {
+        let mut project = Project::create(dir, "Sources", "Author").unwrap();
+        let chapter = project.add_chapter("One", None).unwrap();
+        let chapter_id = ChapterId::parse(&chapter.id).unwrap();
+        project
+            .update_chapter_content(&chapter_id, "Dusk.\nShe lit the lantern.")
+            .unwrap();
+
+        let note = notes::create_note(&project, "Props", "research").unwrap();
+        notes::update_note(&project, &note.id, "A brass lantern.", None).unwrap();
+
+        let mut sheet = characters::create_character(&project, "Mara").unwrap();
+        sheet.description = "Keeper of the lantern.".to_string();
+        sheet
+            .custom_fields
+            .insert("habit".to_string(), "Trims the lantern wick.".to_string());
+        characters::save_character(&project, &mut sheet).unwrap();
+
+        let ghost = GhostNote::new(
+            &chapter_id,
+            "Foreshadow the lantern.",
+            GhostNoteTrigger::Manual,
+        );
+        ghost_notes::save_ghost_note(&project, &ghost).unwrap();
+        project
+    }
+
+    #[test]
+    fn results_are_grouped_by_kind_with_deep_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = project(dir.path());
+
+        let groups = search_project(&project, "lantern", &SearchOptions::default()).unwrap();
+        let kinds: Vec<SourceKind> = groups.iter().map(|g| g.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                SourceKind::Chapter,
+                SourceKind::Note,
+                SourceKind::Character,
+                SourceKind::GhostNote
+            ]
+        );
+        for group in &groups {
+            assert!(group.results.iter().all(|r| r.kind == group.kind));
+        }
+
+        let chapter = &groups[0].results[0];
+        assert_eq!(chapter.title, "One");
+        assert_eq!(
+            chapter.matches[0].link,
+            format!("chapter/{}#L2", chapter.id)
+        );
+
+        let note = &groups[1].results[0];
+        assert_eq!(note.parent.as_deref(), Some("research"));
+        assert_eq!(note.matches[0].link, format!("note/{}#L1", note.id));
+
+        let fields: Vec<&str> = groups[2]
+            .results
+            .iter()
+            .filter_map(|r| r.field.as_deref())
+            .collect();
+        assert_eq!(fields, ["description", "habit"]);
+        let sheet = &groups[2].results[0];
+        assert_eq!(sheet.title, "Mara");
+        assert_eq!(
+            sheet.matches[0].link,
+            format!("character/{}/description#L1", sheet.id)
+        );
+
+        let ghost = &groups[3].results[0];
+        assert_eq!(ghost.title, "One");
+        assert_eq!(ghost.matches[0].link, format!("ghost_note/{}#L1", ghost.id));
+    }
+
+    #[test]
+    fn unreadable_files_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = project(dir.path());
+        let notes_dir = project.path.join("notes");
+        fs::write(
+            notes_dir.join("research").join("broken.md"),
+            b"lantern \xff\xfe",
+        )
+        .unwrap();
+        fs::write(
+            notes_dir.join("characters").join("broken.json"),
+            "{ \"lantern\": ",
+        )
+        .unwrap();
+        fs::write(project.path.join("timeline.json"), "not json, lantern").unwrap();
+
+        let groups = search_project(&project, "lantern", &SearchOptions::default()).unwrap();
+        let counts: Vec<usize> = groups.iter().map(|g| g.results.len()).collect();
+        assert_eq!(counts, [1, 1, 2, 1]);
+    }
+}
@@ -0,0 +1,156 @@
+use serde::Serialize;
+
+use super::matcher::Matcher;
+use super::{chapters, find_all, Found, SearchMatch, SearchOptions, SearchResult};
+use crate::manuscript::project::{Project, ProjectError};
+
+/// Name of the Tauri event carrying one chapter's results of a streamed
+/// search, as a [`SearchResultEvent`].
+pub const RESULT_EVENT: &str = "search-result";
+
+/// Name of the Tauri event sent once a streamed search has ended, whether
+/// it finished or was cancelled, as a [`SearchFinished`].
+pub const FINISHED_EVENT: &str = "search-finished";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchResultEvent {
+    pub search_id: u64,
+    pub result: SearchResult,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchFinished {
+    pub search_id: u64,
+    #[serde(flatten)]
+    pub summary: SearchSummary,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SearchSummary {
+    pub total_matches: usize,
+    /// Chapters with at least one match.
+    pub chapters: usize,
+    /// Whether the search stopped before reaching the last chapter.
+    pub cancelled: bool,
+}
+
+/// A manuscript search that is set up once and then run chapter by
+/// chapter, so results can be shown while later chapters are still being
+/// read.
+pub struct ManuscriptSearch {
+    /// `None` for an empty query, which matches nothing.
+    matcher: Option<Matcher>,
+}
+
+impl ManuscriptSearch {
+    /// Check the query up front, so an invalid regular expression is
+    /// reported before any results are.
+    pub fn new(query: &str, options: &SearchOptions) -> Result<Self, ProjectError> {
+        let matcher = if query.is_empty() { None } else { Some(Matcher::new(query, options)?) };
+        Ok(ManuscriptSearch { matcher })
+    }
+
+    /// Search the chapters in reading order, handing each one with matches
+    /// to `on_result` as soon as it is done. `cancelled` is checked between
+    /// chapters; once it returns true the search stops.
+    pub fn run(
+        &self,
+        project: &Project,
+        cancelled: impl Fn() -> bool,
+        mut on_result: impl FnMut(SearchResult),
+    ) -> SearchSummary {
+        let mut summary = SearchSummary::default();
+        let Some(matcher) = &self.matcher else {
+            return summary;
+        };
+        for (_, title, chapter) in chapters(project) {
+            if cancelled() {
+                summary.cancelled = true;
+                break;
+            }
+            let matches: Vec<SearchMatch> = find_all(matcher, &chapter.content).iter().map(Found::to_match).collect();
+            if matches.is_empty() {
+                continue;
+            }
+            summary.total_matches += matches.len();
+            summary.chapters += 1;
+            on_result(SearchResult {
+                chapter_id: chapter.id,
+                chapter_title: title,
+                matches,
+            });
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manuscript::ids::ChapterId;
+    use std::cell::RefCell;
+
+    /// Chapters "One" and "Two", then "Three" and "Four" inside "One", so
+    /// reading order differs from the order they were added in.
+    fn project(dir: &std::path::Path) -> Project {
+        let mut project = Project::create(dir, "Stream", "Author").unwrap();
+        let mut add = |title: &str, parent: Option<&ChapterId>, text: &str| {
+            let chapter = project.add_chapter(title, parent).unwrap();
+            let id = ChapterId::parse(&chapter.id).unwrap();
+            project.update_chapter_content(&id, text).unwrap();
+            id
+        };
+        let one = add("One", None, "A lantern.\nAnother lantern.");
+        add("Two", None, "The lantern went out.");
+        add("Three", Some(&one), "No light here.");
+        add("Four", Some(&one), "A lantern again.");
+        project
+    }
+
+    fn titles(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.chapter_title.as_str()).collect()
+    }
+
+    #[test]
+    fn results_arrive_in_reading_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = project(dir.path());
+        let search = ManuscriptSearch::new("lantern", &SearchOptions::default()).unwrap();
+        let mut results = Vec::new();
+        let summary = search.run(&project, || false, |result| results.push(result));
+
+        assert_eq!(titles(&results), ["One", "Four", "Two"]);
+        assert_eq!(results[0].matches.len(), 2);
+        assert_eq!((summary.total_matches, summary.chapters, summary.cancelled), (4, 3, false));
+    }
+
+    #[test]
+    fn cancelling_stops_between_chapters() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = project(dir.path());
+        let search = ManuscriptSearch::new("lantern", &SearchOptions::default()).unwrap();
+
+        let results = RefCell::new(Vec::new());
+        let summary = search.run(
+            &project,
+            || !results.borrow().is_empty(),
+            |result| results.borrow_mut().push(result),
+        );
+        assert_eq!(titles(&results.borrow()), ["One"]);
+        assert_eq!((summary.total_matches, summary.chapters, summary.cancelled), (2, 1, true));
+
+        let mut results = Vec::new();
+        let summary = search.run(&project, || true, |result| results.push(result));
+        assert!(results.is_empty());
+        assert!(summary.cancelled);
+    }
+
+    #[test]
+    fn an_empty_query_matches_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = project(dir.path());
+        let search = ManuscriptSearch::new("", &SearchOptions::default()).unwrap();
+        let summary = search.run(&project, || false, |_| panic!("no results expected"));
+        assert_eq!((summary.total_matches, summary.cancelled), (0, false));
+    }
+}
@@ -0,0 +1,397 @@
+//! Application settings, kept in `settings.toml` in the app config
+//! directory. A project can override any of them from a `[settings]` table
+//! in its `metadata.toml`, laid out the same way.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::manuscript::error::{read_toml, WithPath};
+use crate::manuscript::project::{Project, ProjectError};
+
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+/// Name of the Tauri event emitted with a [`SettingsChanged`] payload
+/// whenever the global settings or a project's overrides change.
+pub const CHANGED_EVENT: &str = "settings-changed";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    Classic,
+    Candlelight,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AmbientProfile {
+    Rain,
+    Fireplace,
+    Wind,
+    Ocean,
+    Forest,
+    #[default]
+    Silence,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum KeystrokeSound {
+    Mechanical,
+    Soft,
+    Pen,
+    #[default]
+    None,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+    Fr,
+    De,
+    Ja,
+    Zh,
+    Ar,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Appearance {
+    pub theme: Theme,
+    /// `#rrggbb`
+    pub accent: String,
+    pub grain: bool,
+}
+
+impl Default for Appearance {
+    fn default() -> Self {
+        Appearance {
+            theme: Theme::Classic,
+            accent: "#8B1A1A".to_string(),
+            grain: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Writing {
+    pub daily_target: u32,
+}
+
+impl Default for Writing {
+    fn default() -> Self {
+        Writing { daily_target: 1000 }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sound {
+    pub ambient: AmbientProfile,
+    pub keystroke: KeystrokeSound,
+    /// 0.0 to 1.0
+    pub volume: f64,
+}
+
+impl Default for Sound {
+    fn default() -> Self {
+        Sound {
+            ambient: AmbientProfile::Silence,
+            keystroke: KeystrokeSound::None,
+            volume: 0.3,
+        }
+    }
+}
+
+/// CSS `font-family` values for the three font slots.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Fonts {
+    pub writing: String,
+    pub display: String,
+    pub ui: String,
+}
+
+impl Default for Fonts {
+    fn default() -> Self {
+        Fonts {
+            writing: "'EB Garamond', 'Georgia', serif".to_string(),
+            display: "'EB Garamond', serif".to_string(),
+            ui: "'Inter', -apple-system, BlinkMacSystemFont, sans-serif".to_string(),
+        }
+    }
+}
+
+/// Theme editor variables that take a colour.
+const THEME_COLORS: &[&str] = &[
+    "accent",
+    "ink",
+    "bone",
+    "surface",
+    "surfaceRaised",
+    "ghost",
+    "boneDust",
+    "border",
+];
+const THEME_FONTS: &[&str] = &["fontDisplay", "fontCanvas", "fontUi", "fontMono"];
+/// Theme editor sliders and their ranges.
+const THEME_RANGES: &[(&str, f64, f64)] = &[("grainOpacity", 0.0, 0.15), ("vignetteOpacity", 0.0, 0.3)];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Settings {
+    pub locale: Locale,
+    pub custom_css: String,
+    pub appearance: Appearance,
+    pub writing: Writing,
+    pub sound: Sound,
+    pub fonts: Fonts,
+    /// Theme editor overrides keyed by variable, e.g. `surfaceRaised` or
+    /// `grainOpacity`. Variables left out use the theme's own value.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub theme_editor: BTreeMap<String, String>,
+}
+
+/// Payload of [`CHANGED_EVENT`].
+#[derive(Debug, Serialize, Clone)]
+pub struct SettingsChanged {
+    /// The effective settings: global ones, with the project's overrides
+    /// applied when `project_path` is set.
+    pub settings: Settings,
+    pub project_path: Option<String>,
+}
+
+fn invalid(field: &str, message: impl Into<String>) -> ProjectError {
+    ProjectError::InvalidSettings {
+        field: field.to_string(),
+        message: message.into(),
+    }
+}
+
+fn is_hex_color(value: &str) -> bool {
+    value.strip_prefix('#').is_some_and(|hex| {
+        (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// Font stacks end up in inline styles, so anything that could close the
+/// declaration is refused.
+fn check_font(field: &str, value: &str) -> Result<(), ProjectError> {
+    if value.trim().is_empty() || value.len() > 200 || value.contains([';', '{', '}', '<']) {
+        return Err(invalid(field, "must be a CSS font-family list"));
+    }
+    Ok(())
+}
+
+/// Merge `patch` into `base`. Objects merge key by key; a `null` removes the
+/// key so it falls back to its default; anything else replaces.
+fn merge(base: &mut Map<String, Value>, patch: &Map<String, Value>) {
+    for (key, value) in patch {
+        match (base.get_mut(key), value) {
+            (_, Value::Null) => {
+                base.remove(key);
+            }
+            (Some(Value::Object(base)), Value::Object(patch)) => merge(base, patch),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Drop tables that merging left empty, so they don't linger in files.
+fn prune(map: &mut Map<String, Value>) {
+    for value in map.values_mut() {
+        if let Value::Object(inner) = value {
+            prune(inner);
+        }
+    }
+    map.retain(|_, value| !matches!(value, Value::Object(inner) if inner.is_empty()));
+}
+
+fn take<T: DeserializeOwned + Default>(map: &mut Map<String, Value>, key: &str) -> Result<T, ProjectError> {
+    match map.remove(key) {
+        Some(value) => serde_json::from_value(value).map_err(|e| invalid(key, e.to_string())),
+        None => Ok(T::default()),
+    }
+}
+
+impl Settings {
+    /// Build settings from a JSON-shaped value, filling gaps with defaults
+    /// and naming the section at fault when something doesn't fit.
+    fn from_value(value: Value) -> Result<Self, ProjectError> {
+        let Value::Object(mut map) = value else {
+            return Err(invalid("settings", "expected a table"));
+        };
+        let settings = Settings {
+            locale: take(&mut map, "locale")?,
+            custom_css: take(&mut map, "custom_css")?,
+            appearance: take(&mut map, "appearance")?,
+            writing: take(&mut map, "writing")?,
+            sound: take(&mut map, "sound")?,
+            fonts: take(&mut map, "fonts")?,
+            theme_editor: take(&mut map, "theme_editor")?,
+        };
+        if let Some(key) = map.keys().next() {
+            return Err(invalid(key, "unknown setting"));
+        }
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn to_value(&self) -> Result<Map<String, Value>, ProjectError> {
+        match serde_json::to_value(self)? {
+            Value::Object(map) => Ok(map),
+            _ => unreachable!("settings serialize as a map"),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ProjectError> {
+        if !is_hex_color(&self.appearance.accent) {
+            return Err(invalid("appearance.accent", "must be a colour like #8B1A1A"));
+        }
+        if self.writing.daily_target == 0 || self.writing.daily_target > 100_000 {
+            return Err(invalid("writing.daily_target", "must be between 1 and 100000"));
+        }
+        if !(0.0..=1.0).contains(&self.sound.volume) {
+            return Err(invalid("sound.volume", "must be between 0 and 1"));
+        }
+        check_font("fonts.writing", &self.fonts.writing)?;
+        check_font("fonts.display", &self.fonts.display)?;
+        check_font("fonts.ui", &self.fonts.ui)?;
+        if self.custom_css.len() > 256 * 1024 {
+            return Err(invalid("custom_css", "is larger than 256 KB"));
+        }
+
+        for (key, value) in &self.theme_editor {
+            let field = format!("theme_editor.{}", key);
+            if THEME_COLORS.contains(&key.as_str()) {
+                if !is_hex_color(value) {
+                    return Err(invalid(&field, "must be a colour like #8B1A1A"));
+                }
+            } else if THEME_FONTS.contains(&key.as_str()) {
+                check_font(&field, value)?;
+            } else if let Some((_, min, max)) = THEME_RANGES.iter().find(|(name, ..)| name == key) {
+                if !value.parse::<f64>().is_ok_and(|v| (*min..=*max).contains(&v)) {
+                    return Err(invalid(&field, format!("must be a number from {} to {}", min, max)));
+                }
+            } else {
+                return Err(invalid(&field, "unknown theme variable"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Load `settings.toml` from the config directory, or the defaults if
+    /// there isn't one yet.
+    pub fn load(config_dir: &Path) -> Result<Self, ProjectError> {
+        let path = config_dir.join(SETTINGS_FILE);
+        if !path.exists() {
+            return Ok(Settings::default());
+        }
+        let table: toml::Table = read_toml(&path)?;
+        Settings::from_value(serde_json::to_value(table)?)
+    }
+
+    pub fn save(&self, config_dir: &Path) -> Result<(), ProjectError> {
+        self.validate()?;
+        fs::create_dir_all(config_dir).with_path(config_dir)?;
+        let path = config_dir.join(SETTINGS_FILE);
+        fs::write(&path, self.to_toml()?).with_path(&path)?;
+        Ok(())
+    }
+
+    /// The settings as a standalone TOML document, for sharing.
+    pub fn to_toml(&self) -> Result<String, ProjectError> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Read settings shared with [`Settings::to_toml`]. Missing entries take
+    /// their defaults.
+    pub fn from_toml(text: &str) -> Result<Self, ProjectError> {
+        let table: toml::Table = toml::from_str(text)?;
+        Settings::from_value(serde_json::to_value(table)?)
+    }
+
+    /// Apply a partial update shaped like the settings themselves, e.g.
+    /// `{"sound": {"volume": 0.5}}`. `null` resets an entry to its default.
+    pub fn patched(&self, patch: &Value) -> Result<Self, ProjectError> {
+        let Value::Object(patch) = patch else {
+            return Err(invalid("settings", "expected an object"));
+        };
+        let mut value = self.to_value()?;
+        merge(&mut value, patch);
+        Settings::from_value(Value::Object(value))
+    }
+
+    /// These settings with a project's overrides applied.
+    pub fn with_overrides(&self, overrides: &toml::Table) -> Result<Self, ProjectError> {
+        self.patched(&serde_json::to_value(overrides)?)
+    }
+
+    /// Carry over the values the frontend used to keep in `localStorage`
+    /// under `quillborn-*` keys. Entries that don't parse or validate are
+    /// skipped, leaving the default.
+    pub fn from_local_storage(entries: &HashMap<String, String>) -> Self {
+        let parse_json = |raw: &str| serde_json::from_str::<Value>(raw).ok();
+        let mut settings = Settings::default();
+        for (key, raw) in entries {
+            let patch = match key.as_str() {
+                "quillborn-theme" => json!({ "appearance": { "theme": raw } }),
+                "quillborn-accent" => json!({ "appearance": { "accent": raw } }),
+                "quillborn-grain" => json!({ "appearance": { "grain": raw != "false" } }),
+                "quillborn-daily-target" => match raw.parse::<u32>() {
+                    Ok(target) => json!({ "writing": { "daily_target": target } }),
+                    Err(_) => continue,
+                },
+                "quillborn-ambient" => json!({ "sound": { "ambient": raw } }),
+                "quillborn-keystroke" => json!({ "sound": { "keystroke": raw } }),
+                "quillborn-volume" => match raw.parse::<f64>() {
+                    Ok(volume) => json!({ "sound": { "volume": volume } }),
+                    Err(_) => continue,
+                },
+                "quillborn-locale" => json!({ "locale": raw }),
+                "quillborn-custom-css" => json!({ "custom_css": raw }),
+                "quillborn-fonts" => match parse_json(raw) {
+                    Some(fonts) => json!({ "fonts": fonts }),
+                    None => continue,
+                },
+                "quillborn-custom-theme" => match parse_json(raw) {
+                    Some(theme) => json!({ "theme_editor": theme }),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            if let Ok(patched) = settings.patched(&patch) {
+                settings = patched;
+            }
+        }
+        settings
+    }
+}
+
+/// Merge a partial update into a project's overrides and save the project.
+/// `null` removes an override. The result must still make valid settings.
+pub fn update_project_overrides(project: &mut Project, patch: &Value) -> Result<(), ProjectError> {
+    let Value::Object(patch) = patch else {
+        return Err(invalid("settings", "expected an object"));
+    };
+    let mut overrides = match serde_json::to_value(&project.metadata.settings)? {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    };
+    merge(&mut overrides, patch);
+    prune(&mut overrides);
+
+    let overrides: toml::Table = serde_json::from_value(Value::Object(overrides))?;
+    Settings::default().with_overrides(&overrides)?;
+    project.metadata.settings = overrides;
+    project.save()
+}
@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter};
+
+use quillborn_core::analysis::{self, ChapterDiagnostics, OverusedWord, StyleConfig};
+use quillborn_core::manuscript::ids::ChapterId;
+use quillborn_core::manuscript::project::{Project, ProjectError};
+
+/// Emit the fresh diagnostics for a chapter that was just saved. Style
+/// problems, including a broken `style.json`, never fail the save.
+pub fn publish_diagnostics(app: &AppHandle, project: &Project, chapter_id: &ChapterId) {
+    if let Ok(diagnostics) = analysis::lint_chapter(project, chapter_id) {
+        let _ = app.emit(analysis::DIAGNOSTICS_EVENT, &diagnostics);
+    }
+}
+
+#[tauri::command]
+pub fn lint_manuscript(project_path: String) -> Result<Vec<ChapterDiagnostics>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    analysis::lint_manuscript(&project)
+}
+
+#[tauri::command]
+pub fn lint_chapter(project_path: String, chapter_id: ChapterId) -> Result<ChapterDiagnostics, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    analysis::lint_chapter(&project, &chapter_id)
+}
+
+/// The words used most out of proportion to the bundled baseline, with
+/// every use for highlighting.
+#[tauri::command]
+pub fn overused_words(project_path: String, limit: Option<usize>) -> Result<Vec<OverusedWord>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    analysis::overused_words(&project, limit.unwrap_or(50))
+}
+
+#[tauri::command]
+pub fn get_style_config(project_path: String) -> Result<StyleConfig, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    StyleConfig::load(&project)
+}
+
+#[tauri::command]
+pub fn update_style_config(project_path: String, config: StyleConfig) -> Result<(), ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    config.save(&project)
+}
@@ -0,0 +1,54 @@
+use std::sync::Mutex;
+
+use tauri::State;
+
+use quillborn_core::manuscript::project::ProjectError;
+use quillborn_core::rpc::{RpcConfig, RpcServer};
+
+/// The automation API is off until the user turns it on for the session.
+#[derive(Default)]
+pub struct AutomationServer(Mutex<Option<(RpcServer, AutomationStatus)>>);
+
+#[derive(serde::Serialize, Clone)]
+pub struct AutomationStatus {
+    pub url: String,
+    pub token: String,
+    pub read_only: bool,
+}
+
+#[tauri::command]
+pub fn start_automation_server(
+    server: State<'_, AutomationServer>,
+    port: Option<u16>,
+    read_only: bool,
+) -> Result<AutomationStatus, ProjectError> {
+    let mut running = server.0.lock().unwrap_or_else(|e| e.into_inner());
+    // Restarting picks up the new settings and issues a new token.
+    running.take();
+
+    let config = RpcConfig {
+        port: port.unwrap_or(0),
+        token: RpcConfig::generate_token(),
+        read_only,
+    };
+    let token = config.token.clone();
+    let rpc = RpcServer::start(config)?;
+    let status = AutomationStatus {
+        url: format!("http://{}/", rpc.addr()),
+        token,
+        read_only,
+    };
+    *running = Some((rpc, status.clone()));
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn stop_automation_server(server: State<'_, AutomationServer>) {
+    server.0.lock().unwrap_or_else(|e| e.into_inner()).take();
+}
+
+#[tauri::command]
+pub fn automation_server_status(server: State<'_, AutomationServer>) -> Option<AutomationStatus> {
+    let running = server.0.lock().unwrap_or_else(|e| e.into_inner());
+    running.as_ref().map(|(_, status)| status.clone())
+}
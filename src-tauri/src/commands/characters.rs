@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+
+use quillborn_core::manuscript::ids::CharacterId;
+use quillborn_core::manuscript::project::{Project, ProjectError};
+use quillborn_core::planning::characters::{
+    self, CharacterGraph, CharacterSheet, Neighbour, PathStep, RelationshipEdge, RelationshipType,
+};
+use quillborn_core::planning::mentions::{self, CharacterMentionReport, Mention};
+
+#[tauri::command]
+pub fn list_characters(project_path: String) -> Result<Vec<CharacterSheet>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    characters::list_characters(&project)
+}
+
+#[tauri::command]
+pub fn get_character(
+    project_path: String,
+    character_id: CharacterId,
+) -> Result<CharacterSheet, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    characters::load_character(&project, &character_id)
+}
+
+#[tauri::command]
+pub fn create_character(project_path: String, name: String) -> Result<CharacterSheet, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    characters::create_character(&project, &name)
+}
+
+#[tauri::command]
+pub fn update_character(
+    project_path: String,
+    character: CharacterSheet,
+) -> Result<CharacterSheet, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut character = character;
+    characters::save_character(&project, &mut character)?;
+    Ok(character)
+}
+
+#[tauri::command]
+pub fn delete_character(project_path: String, character_id: CharacterId) -> Result<(), ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    characters::delete_character(&project, &character_id)
+}
+
+#[tauri::command]
+pub fn set_character_portrait(
+    project_path: String,
+    character_id: CharacterId,
+    source_path: String,
+) -> Result<CharacterSheet, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    characters::set_portrait(&project, &character_id, &PathBuf::from(&source_path))
+}
+
+#[tauri::command]
+pub fn character_neighbours(
+    project_path: String,
+    character_id: CharacterId,
+    relationship_types: Option<Vec<RelationshipType>>,
+) -> Result<Vec<Neighbour>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let graph = CharacterGraph::load(&project)?;
+    graph.neighbours(character_id.as_str(), relationship_types.as_deref())
+}
+
+#[tauri::command]
+pub fn character_relationship_path(
+    project_path: String,
+    from_id: CharacterId,
+    to_id: CharacterId,
+    relationship_types: Option<Vec<RelationshipType>>,
+) -> Result<Option<Vec<PathStep>>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let graph = CharacterGraph::load(&project)?;
+    graph.shortest_path(from_id.as_str(), to_id.as_str(), relationship_types.as_deref())
+}
+
+#[tauri::command]
+pub fn character_relationships_by_type(
+    project_path: String,
+    relationship_types: Vec<RelationshipType>,
+) -> Result<Vec<RelationshipEdge>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let graph = CharacterGraph::load(&project)?;
+    Ok(graph.edges_of_type(&relationship_types))
+}
+
+#[tauri::command]
+pub fn character_mentions(
+    project_path: String,
+    min_absence: Option<usize>,
+) -> Result<Vec<CharacterMentionReport>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    mentions::mention_reports(&project, min_absence.unwrap_or(mentions::DEFAULT_MIN_ABSENCE))
+}
+
+#[tauri::command]
+pub fn find_character_mentions(
+    project_path: String,
+    character_id: CharacterId,
+) -> Result<Vec<Mention>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    mentions::find_mentions(&project, &character_id)
+}
@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use quillborn_core::manuscript::project::{ManuscriptNode, Project, ProjectError};
+use quillborn_core::search::{SmartCollection, SmartCollections};
+
+#[tauri::command]
+pub fn list_collections(project_path: String) -> Result<Vec<SmartCollection>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    Ok(SmartCollections::load(&project)?.collections)
+}
+
+#[tauri::command]
+pub fn create_collection(
+    project_path: String,
+    name: String,
+    query: String,
+) -> Result<SmartCollection, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut collections = SmartCollections::load(&project)?;
+    let collection = SmartCollection::new(&name, &query)?;
+    collections.upsert(collection.clone())?;
+    collections.save(&project)?;
+    Ok(collection)
+}
+
+#[tauri::command]
+pub fn update_collection(project_path: String, collection: SmartCollection) -> Result<(), ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut collections = SmartCollections::load(&project)?;
+    collections.upsert(collection)?;
+    collections.save(&project)
+}
+
+#[tauri::command]
+pub fn delete_collection(project_path: String, collection_id: String) -> Result<(), ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut collections = SmartCollections::load(&project)?;
+    collections.remove(&collection_id)?;
+    collections.save(&project)
+}
+
+/// The nodes in a collection right now, in reading order.
+#[tauri::command]
+pub fn get_collection_members(
+    project_path: String,
+    collection_id: String,
+) -> Result<Vec<ManuscriptNode>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    SmartCollections::load(&project)?.get(&collection_id)?.members(&project)
+}
@@ -1,26 +1,107 @@
-use crate::manuscript::project::ProjectError;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tauri::{AppHandle, State};
+use tauri_plugin_dialog::DialogExt;
+
+use quillborn_core::manuscript::project::ProjectError;
+
+/// Directories the user picked in an export save dialog this session.
+/// Exports may write straight into these as well as the project's exports/.
+#[derive(Default)]
+pub struct ExportDirs(Mutex<Vec<PathBuf>>);
+
+impl ExportDirs {
+    pub fn allowed(&self) -> Vec<PathBuf> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn allow(&self, dir: PathBuf) {
+        let mut dirs = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+}
+
+/// Ask where to save an export and allow-list the chosen directory. Returns
+/// `None` when the dialog is cancelled. Async so the blocking dialog stays
+/// off the main thread.
+#[tauri::command]
+pub async fn choose_export_path(
+    app: AppHandle,
+    dirs: State<'_, ExportDirs>,
+    title: String,
+    file_name: String,
+    filter_name: String,
+    extensions: Vec<String>,
+) -> Result<Option<String>, ProjectError> {
+    let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+    let Some(chosen) = app
+        .dialog()
+        .file()
+        .set_title(title)
+        .set_file_name(file_name)
+        .add_filter(filter_name, &extensions)
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let Ok(path) = chosen.into_path() else {
+        return Ok(None);
+    };
+    if let Some(parent) = path.parent() {
+        dirs.allow(parent.to_path_buf());
+    }
+    Ok(Some(path.display().to_string()))
+}
 
 #[tauri::command]
-pub fn export_markdown(project_path: String, output_path: String) -> Result<String, ProjectError> {
-    crate::export::export_markdown(project_path, output_path)
+pub fn export_markdown(
+    dirs: State<'_, ExportDirs>,
+    project_path: String,
+    output_path: String,
+    collection: Option<String>,
+) -> Result<String, ProjectError> {
+    quillborn_core::export::export_markdown(project_path, output_path, collection, &dirs.allowed())
 }
 
 #[tauri::command]
-pub fn export_plain_text(project_path: String, output_path: String) -> Result<String, ProjectError> {
-    crate::export::export_plain_text(project_path, output_path)
+pub fn export_plain_text(
+    dirs: State<'_, ExportDirs>,
+    project_path: String,
+    output_path: String,
+    collection: Option<String>,
+) -> Result<String, ProjectError> {
+    quillborn_core::export::export_plain_text(project_path, output_path, collection, &dirs.allowed())
 }
 
 #[tauri::command]
-pub fn export_html(project_path: String, output_path: String) -> Result<String, ProjectError> {
-    crate::export::export_html(project_path, output_path)
+pub fn export_html(
+    dirs: State<'_, ExportDirs>,
+    project_path: String,
+    output_path: String,
+    collection: Option<String>,
+) -> Result<String, ProjectError> {
+    quillborn_core::export::export_html(project_path, output_path, collection, &dirs.allowed())
 }
 
 #[tauri::command]
-pub fn export_latex(project_path: String, output_path: String) -> Result<String, ProjectError> {
-    crate::export::export_latex(project_path, output_path)
+pub fn export_latex(
+    dirs: State<'_, ExportDirs>,
+    project_path: String,
+    output_path: String,
+    collection: Option<String>,
+) -> Result<String, ProjectError> {
+    quillborn_core::export::export_latex(project_path, output_path, collection, &dirs.allowed())
 }
 
 #[tauri::command]
-pub fn export_epub(project_path: String, output_path: String) -> Result<String, ProjectError> {
-    crate::export::export_epub(project_path, output_path)
+pub fn export_epub(
+    dirs: State<'_, ExportDirs>,
+    project_path: String,
+    output_path: String,
+    collection: Option<String>,
+) -> Result<String, ProjectError> {
+    quillborn_core::export::export_epub(project_path, output_path, collection, &dirs.allowed())
 }
@@ -1,4 +1,4 @@
-use crate::manuscript::project::ProjectError;
+use quillborn_core::manuscript::project::ProjectError;
 use std::path::PathBuf;
 
 #[derive(serde::Serialize)]
@@ -21,7 +21,7 @@ pub fn scan_fonts(project_path: Option<String>) -> Result<Vec<FontInfo>, Project
                 if is_font_file(&path) {
                     if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
                         fonts.push(FontInfo {
-                            name: name.replace('-', " ").replace('_', " "),
+                            name: name.replace(['-', '_'], " "),
                             path: path.display().to_string(),
                             source: "system".to_string(),
                         });
@@ -41,7 +41,7 @@ pub fn scan_fonts(project_path: Option<String>) -> Result<Vec<FontInfo>, Project
                     if is_font_file(&path) {
                         if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
                             fonts.push(FontInfo {
-                                name: name.replace('-', " ").replace('_', " "),
+                                name: name.replace(['-', '_'], " "),
                                 path: path.display().to_string(),
                                 source: "project".to_string(),
                             });
@@ -53,7 +53,7 @@ pub fn scan_fonts(project_path: Option<String>) -> Result<Vec<FontInfo>, Project
     }
 
     // Sort by name
-    fonts.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    fonts.sort_by_key(|a| a.name.to_lowercase());
     // Deduplicate by name
     fonts.dedup_by(|a, b| a.name.to_lowercase() == b.name.to_lowercase());
 
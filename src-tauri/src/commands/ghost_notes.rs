@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter};
+
+use quillborn_core::manuscript::ids::{ChapterId, GhostNoteId};
+use quillborn_core::manuscript::project::{Project, ProjectError};
+use quillborn_core::planning::ghost_notes::{self, GhostNote, GhostNoteTrigger};
+
+/// Evaluate ghost note triggers and emit an event for every note that was
+/// revealed, so notes fire even when no ghost note view is mounted. Runs
+/// after the change that prompted it is saved, so failures are ignored.
+pub fn reveal_ghost_notes(app: &AppHandle, project: &Project) {
+    if let Ok(revealed) = ghost_notes::evaluate_triggers(project) {
+        for note in revealed {
+            let _ = app.emit(ghost_notes::REVEALED_EVENT, &note);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_ghost_notes(
+    project_path: String,
+    chapter_id: Option<ChapterId>,
+) -> Result<Vec<GhostNote>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let notes = ghost_notes::list_ghost_notes(&project)?;
+    Ok(match chapter_id {
+        Some(chapter_id) => notes.into_iter().filter(|n| chapter_id == n.chapter_id).collect(),
+        None => notes,
+    })
+}
+
+#[tauri::command]
+pub fn create_ghost_note(
+    app: AppHandle,
+    project_path: String,
+    chapter_id: ChapterId,
+    message: String,
+    trigger: GhostNoteTrigger,
+) -> Result<GhostNote, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    if !project.structure.nodes.contains_key(chapter_id.as_str()) {
+        return Err(ProjectError::ChapterNotFound(chapter_id.to_string()));
+    }
+    let note = GhostNote::new(&chapter_id, &message, trigger);
+    ghost_notes::save_ghost_note(&project, &note)?;
+
+    // The trigger may already be satisfied
+    reveal_ghost_notes(&app, &project);
+    ghost_notes::load_ghost_note(&project, &GhostNoteId::parse(&note.id)?)
+}
+
+#[tauri::command]
+pub fn dismiss_ghost_note(project_path: String, note_id: GhostNoteId) -> Result<GhostNote, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut note = ghost_notes::load_ghost_note(&project, &note_id)?;
+    note.dismissed = true;
+    ghost_notes::save_ghost_note(&project, &note)?;
+    Ok(note)
+}
+
+#[tauri::command]
+pub fn delete_ghost_note(project_path: String, note_id: GhostNoteId) -> Result<(), ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    ghost_notes::delete_ghost_note(&project, &note_id)
+}
+
+#[tauri::command]
+pub fn record_chapter_visit(
+    app: AppHandle,
+    project_path: String,
+    chapter_id: ChapterId,
+) -> Result<u32, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let visits = ghost_notes::record_visit(&project, &chapter_id)?;
+    reveal_ghost_notes(&app, &project);
+    Ok(visits)
+}
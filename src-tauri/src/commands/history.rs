@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use quillborn_core::manuscript::history::{History, HistoryState, OperationSummary};
+use quillborn_core::manuscript::project::{Project, ProjectError, ProjectState};
+
+#[derive(serde::Serialize)]
+pub struct HistoryStep {
+    /// The operation that was undone or redone, or `None` if the stack was empty.
+    pub operation: Option<OperationSummary>,
+    pub state: ProjectState,
+    pub history: HistoryState,
+}
+
+fn step(project: &Project, operation: Option<OperationSummary>) -> Result<HistoryStep, ProjectError> {
+    Ok(HistoryStep {
+        operation,
+        state: ProjectState::from(project),
+        history: History::load(project)?.state(),
+    })
+}
+
+#[tauri::command]
+pub fn get_history(project_path: String) -> Result<HistoryState, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    Ok(History::load(&project)?.state())
+}
+
+#[tauri::command]
+pub fn undo(project_path: String) -> Result<HistoryStep, ProjectError> {
+    let mut project = Project::open(&PathBuf::from(&project_path))?;
+    let operation = History::undo(&mut project)?;
+    step(&project, operation)
+}
+
+#[tauri::command]
+pub fn redo(project_path: String) -> Result<HistoryStep, ProjectError> {
+    let mut project = Project::open(&PathBuf::from(&project_path))?;
+    let operation = History::redo(&mut project)?;
+    step(&project, operation)
+}
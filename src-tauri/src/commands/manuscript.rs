@@ -1,16 +1,11 @@
-use std::fs;
 use std::path::PathBuf;
+use tauri::AppHandle;
 
-use crate::manuscript::chapter::Chapter;
-use crate::manuscript::project::{Project, ProjectError};
-
-#[derive(serde::Serialize)]
-pub struct ProjectState {
-    pub path: String,
-    pub metadata: crate::manuscript::project::ProjectMetadata,
-    pub structure: crate::manuscript::project::ManuscriptStructure,
-    pub total_word_count: u64,
-}
+use super::analysis::publish_diagnostics;
+use super::ghost_notes::reveal_ghost_notes;
+use quillborn_core::manuscript::chapter::Chapter;
+use quillborn_core::manuscript::ids::ChapterId;
+use quillborn_core::manuscript::project::{ChapterStatus, Project, ProjectError, ProjectState};
 
 #[tauri::command]
 pub fn create_project(
@@ -19,27 +14,15 @@ pub fn create_project(
     author: String,
 ) -> Result<ProjectState, ProjectError> {
     let project = Project::create(&PathBuf::from(&dir), &title, &author)?;
-    let state = ProjectState {
-        path: project.path.display().to_string(),
-        metadata: project.metadata.clone(),
-        structure: project.structure.clone(),
-        total_word_count: project.total_word_count(),
-    };
-    Ok(state)
+    Ok(ProjectState::from(&project))
 }
 
 #[tauri::command]
-pub fn open_project(path: String) -> Result<ProjectState, ProjectError> {
+pub fn open_project(app: AppHandle, path: String) -> Result<ProjectState, ProjectError> {
     let project = Project::open(&PathBuf::from(&path))?;
+    reveal_ghost_notes(&app, &project);
 
-    // Load word counts from chapter files
-    let state = ProjectState {
-        path: project.path.display().to_string(),
-        metadata: project.metadata.clone(),
-        structure: project.structure.clone(),
-        total_word_count: project.total_word_count(),
-    };
-    Ok(state)
+    Ok(ProjectState::from(&project))
 }
 
 #[tauri::command]
@@ -53,48 +36,30 @@ pub fn save_project(path: String) -> Result<(), ProjectError> {
 pub fn create_chapter(
     project_path: String,
     title: String,
-    parent_id: Option<String>,
+    parent_id: Option<ChapterId>,
 ) -> Result<Chapter, ProjectError> {
     let mut project = Project::open(&PathBuf::from(&project_path))?;
-    let chapter = project.add_chapter(&title, parent_id.as_deref())?;
+    let chapter = project.add_chapter(&title, parent_id.as_ref())?;
     Ok(chapter)
 }
 
 #[tauri::command]
 pub fn update_chapter(
+    app: AppHandle,
     project_path: String,
-    chapter_id: String,
+    chapter_id: ChapterId,
     content: String,
 ) -> Result<u64, ProjectError> {
     let mut project = Project::open(&PathBuf::from(&project_path))?;
-    let chapter_path = project
-        .path
-        .join("chapters")
-        .join(format!("{}.md", chapter_id));
-
-    if !chapter_path.exists() {
-        return Err(ProjectError::ChapterNotFound(chapter_id));
-    }
-
-    let mut chapter = Chapter::from_file(&chapter_path)
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
-
-    chapter.update_content(&content);
-    let word_count = chapter.word_count;
-
-    fs::write(&chapter_path, chapter.to_markdown())?;
-
-    // Update word count in structure
-    if let Some(node) = project.structure.nodes.get_mut(&chapter_id) {
-        node.word_count = word_count;
-    }
-    project.save()?;
+    let word_count = project.update_chapter_content(&chapter_id, &content)?.word_count;
+    reveal_ghost_notes(&app, &project);
+    publish_diagnostics(&app, &project, &chapter_id);
 
     Ok(word_count)
 }
 
 #[tauri::command]
-pub fn delete_chapter(project_path: String, chapter_id: String) -> Result<(), ProjectError> {
+pub fn delete_chapter(project_path: String, chapter_id: ChapterId) -> Result<(), ProjectError> {
     let mut project = Project::open(&PathBuf::from(&project_path))?;
     project.delete_chapter(&chapter_id)?;
     Ok(())
@@ -103,7 +68,7 @@ pub fn delete_chapter(project_path: String, chapter_id: String) -> Result<(), Pr
 #[tauri::command]
 pub fn rename_chapter(
     project_path: String,
-    chapter_id: String,
+    chapter_id: ChapterId,
     new_title: String,
 ) -> Result<(), ProjectError> {
     let mut project = Project::open(&PathBuf::from(&project_path))?;
@@ -111,33 +76,55 @@ pub fn rename_chapter(
     Ok(())
 }
 
+#[tauri::command]
+pub fn set_chapter_status(
+    app: AppHandle,
+    project_path: String,
+    chapter_id: ChapterId,
+    status: ChapterStatus,
+) -> Result<(), ProjectError> {
+    let mut project = Project::open(&PathBuf::from(&project_path))?;
+    project.set_chapter_status(&chapter_id, status)?;
+    reveal_ghost_notes(&app, &project);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_node_tags(project_path: String, node_id: ChapterId, tags: Vec<String>) -> Result<(), ProjectError> {
+    let mut project = Project::open(&PathBuf::from(&project_path))?;
+    project.set_node_tags(&node_id, tags)
+}
+
 #[tauri::command]
 pub fn reorder_chapters(
     project_path: String,
-    new_order: Vec<String>,
-    parent_id: Option<String>,
+    new_order: Vec<ChapterId>,
+    parent_id: Option<ChapterId>,
 ) -> Result<(), ProjectError> {
     let mut project = Project::open(&PathBuf::from(&project_path))?;
-    project.reorder_chapters(new_order, parent_id.as_deref())?;
+    project.reorder_chapters(new_order, parent_id.as_ref())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn move_chapter(
+    project_path: String,
+    chapter_id: ChapterId,
+    new_parent_id: Option<ChapterId>,
+    position: usize,
+) -> Result<(), ProjectError> {
+    let mut project = Project::open(&PathBuf::from(&project_path))?;
+    project.move_chapter(&chapter_id, new_parent_id.as_ref(), position)?;
     Ok(())
 }
 
 #[tauri::command]
 pub fn get_chapter_content(
     project_path: String,
-    chapter_id: String,
+    chapter_id: ChapterId,
 ) -> Result<Chapter, ProjectError> {
-    let project_dir = PathBuf::from(&project_path);
-    let chapter_path = project_dir.join("chapters").join(format!("{}.md", chapter_id));
-
-    if !chapter_path.exists() {
-        return Err(ProjectError::ChapterNotFound(chapter_id));
-    }
-
-    let chapter = Chapter::from_file(&chapter_path)
-        .map_err(|e| ProjectError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?;
-
-    Ok(chapter)
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    project.load_chapter(&chapter_id)
 }
 
 #[tauri::command]
@@ -150,14 +137,17 @@ pub fn create_snapshot(
     Ok(filename)
 }
 
+/// Put back the chapter text kept in a snapshot, e.g. the one taken before
+/// a replace. Returns the new project state.
+#[tauri::command]
+pub fn restore_snapshot(project_path: String, filename: String) -> Result<ProjectState, ProjectError> {
+    let mut project = Project::open(&PathBuf::from(&project_path))?;
+    project.restore_snapshot(&filename)?;
+    Ok(ProjectState::from(&project))
+}
+
 #[tauri::command]
 pub fn get_project_state(project_path: String) -> Result<ProjectState, ProjectError> {
     let project = Project::open(&PathBuf::from(&project_path))?;
-    let state = ProjectState {
-        path: project.path.display().to_string(),
-        metadata: project.metadata.clone(),
-        structure: project.structure.clone(),
-        total_word_count: project.total_word_count(),
-    };
-    Ok(state)
+    Ok(ProjectState::from(&project))
 }
@@ -1,4 +1,16 @@
+pub mod analysis;
+pub mod automation;
+pub mod characters;
+pub mod collections;
 pub mod export;
 pub mod fonts;
+pub mod ghost_notes;
+pub mod history;
 pub mod manuscript;
+pub mod notes;
+pub mod planning;
+pub mod plugins;
 pub mod search;
+pub mod settings;
+pub mod timeline;
+pub mod wiki;
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use quillborn_core::manuscript::project::{Project, ProjectError};
+use quillborn_core::planning::notes::{self, Note};
+
+#[tauri::command]
+pub fn list_notes(project_path: String) -> Result<Vec<Note>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    notes::list_notes(&project)
+}
+
+#[tauri::command]
+pub fn get_note(project_path: String, note_id: String) -> Result<Note, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    notes::load_note(&project, &note_id)
+}
+
+#[tauri::command]
+pub fn create_note(
+    project_path: String,
+    title: String,
+    category: String,
+) -> Result<Note, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    notes::create_note(&project, &title, &category)
+}
+
+#[tauri::command]
+pub fn update_note(
+    project_path: String,
+    note_id: String,
+    content: String,
+    tags: Option<Vec<String>>,
+) -> Result<Note, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    notes::update_note(&project, &note_id, &content, tags)
+}
+
+#[tauri::command]
+pub fn rename_note(
+    project_path: String,
+    note_id: String,
+    new_title: String,
+) -> Result<Note, ProjectError> {
+    let mut project = Project::open(&PathBuf::from(&project_path))?;
+    notes::rename_note(&mut project, &note_id, &new_title)
+}
+
+#[tauri::command]
+pub fn delete_note(project_path: String, note_id: String) -> Result<(), ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    notes::delete_note(&project, &note_id)
+}
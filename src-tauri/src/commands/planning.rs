@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+use quillborn_core::manuscript::ids::ChapterId;
+use quillborn_core::manuscript::project::{Project, ProjectError};
+use quillborn_core::planning::corkboard::{Corkboard, CorkboardCard};
+use quillborn_core::planning::threads::{PlotThread, PlotThreads, ThreadReport};
+
+#[tauri::command]
+pub fn get_corkboard(project_path: String) -> Result<Corkboard, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    Corkboard::load(&project)
+}
+
+#[tauri::command]
+pub fn save_corkboard_card(
+    project_path: String,
+    card: CorkboardCard,
+) -> Result<Corkboard, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    if !project.structure.nodes.contains_key(&card.chapter_id) {
+        return Err(ProjectError::ChapterNotFound(card.chapter_id));
+    }
+    let mut corkboard = Corkboard::load(&project)?;
+    corkboard.upsert_card(card);
+    corkboard.save(&project)?;
+    Ok(corkboard)
+}
+
+#[tauri::command]
+pub fn delete_corkboard_card(
+    project_path: String,
+    card_id: String,
+) -> Result<Corkboard, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut corkboard = Corkboard::load(&project)?;
+    corkboard.remove_card(&card_id);
+    corkboard.save(&project)?;
+    Ok(corkboard)
+}
+
+#[tauri::command]
+pub fn list_plot_threads(project_path: String) -> Result<Vec<PlotThread>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    Ok(PlotThreads::load(&project)?.threads)
+}
+
+#[tauri::command]
+pub fn create_plot_thread(
+    project_path: String,
+    name: String,
+    color: String,
+) -> Result<PlotThread, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut threads = PlotThreads::load(&project)?;
+    let thread = PlotThread::new(&name, &color);
+    threads.upsert_thread(thread.clone());
+    threads.save(&project)?;
+    Ok(thread)
+}
+
+#[tauri::command]
+pub fn update_plot_thread(project_path: String, thread: PlotThread) -> Result<(), ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut threads = PlotThreads::load(&project)?;
+    threads.upsert_thread(thread);
+    threads.save(&project)
+}
+
+#[tauri::command]
+pub fn delete_plot_thread(project_path: String, thread_id: String) -> Result<(), ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut threads = PlotThreads::load(&project)?;
+    threads.remove_thread(&thread_id)?;
+    threads.save(&project)
+}
+
+#[tauri::command]
+pub fn assign_chapter_to_thread(
+    project_path: String,
+    thread_id: String,
+    chapter_id: ChapterId,
+) -> Result<(), ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut threads = PlotThreads::load(&project)?;
+    threads.assign_chapter(&project, &thread_id, &chapter_id)?;
+    threads.save(&project)
+}
+
+#[tauri::command]
+pub fn unassign_chapter_from_thread(
+    project_path: String,
+    thread_id: String,
+    chapter_id: ChapterId,
+) -> Result<(), ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut threads = PlotThreads::load(&project)?;
+    threads.unassign_chapter(&thread_id, chapter_id.as_str())?;
+    threads.save(&project)
+}
+
+#[tauri::command]
+pub fn plot_thread_reports(project_path: String) -> Result<Vec<ThreadReport>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    Ok(PlotThreads::load(&project)?.reports(&project))
+}
@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager, State};
+
+use quillborn_core::manuscript::project::{Project, ProjectError};
+use quillborn_core::plugins::{CheckResults, PluginHost};
+
+use super::export::ExportDirs;
+
+/// Plugins are installed per user, under `<app data>/plugins/<id>/`.
+fn load_plugins(app: &AppHandle) -> Result<PluginHost, ProjectError> {
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| ProjectError::AppDirUnavailable {
+            dir: "data",
+            message: e.to_string(),
+        })?;
+    PluginHost::load(&data_dir.join("plugins"))
+}
+
+#[tauri::command]
+pub fn list_plugins(app: AppHandle) -> Result<PluginHost, ProjectError> {
+    load_plugins(&app)
+}
+
+#[tauri::command]
+pub fn export_with_plugin(
+    app: AppHandle,
+    dirs: State<'_, ExportDirs>,
+    project_path: String,
+    format_id: String,
+    output_path: String,
+) -> Result<String, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    load_plugins(&app)?.export(&project, &format_id, &output_path, &dirs.allowed())
+}
+
+#[tauri::command]
+pub fn run_plugin_checks(app: AppHandle, project_path: String) -> Result<CheckResults, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    Ok(load_plugins(&app)?.run_checks(&project))
+}
+
+#[tauri::command]
+pub fn run_plugin_command(
+    app: AppHandle,
+    project_path: String,
+    plugin_id: String,
+    command_id: String,
+    args: Option<serde_json::Value>,
+) -> Result<serde_json::Value, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    load_plugins(&app)?.run_command(
+        &project,
+        &plugin_id,
+        &command_id,
+        args.unwrap_or(serde_json::Value::Null),
+    )
+}
@@ -1,76 +1,114 @@
-use crate::manuscript::project::{Project, ProjectError};
+use quillborn_core::manuscript::project::{Project, ProjectError};
+use quillborn_core::search::{
+    self, FuzzyResults, IndexHit, ManuscriptSearch, ProposedReplacement, ReplaceOutcome, SearchFinished, SearchOptions, SearchResult,
+    SearchResultEvent, SourceGroup,
+};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
 
-#[derive(serde::Serialize)]
-pub struct SearchResult {
-    pub chapter_id: String,
-    pub chapter_title: String,
-    pub matches: Vec<SearchMatch>,
-}
-
-#[derive(serde::Serialize)]
-pub struct SearchMatch {
-    pub line_number: usize,
-    pub line_content: String,
-    pub start: usize,
-    pub end: usize,
-}
+/// Id of the newest streamed search. Starting a search makes every older
+/// one stale, and a stale search stops at the next chapter.
+#[derive(Default)]
+pub struct ActiveSearch(Arc<AtomicU64>);
 
 #[tauri::command]
 pub fn search_manuscript(
     project_path: String,
     query: String,
-    case_sensitive: Option<bool>,
+    options: Option<SearchOptions>,
 ) -> Result<Vec<SearchResult>, ProjectError> {
     let project = Project::open(&PathBuf::from(&project_path))?;
-    let case_sensitive = case_sensitive.unwrap_or(false);
-    let mut results = Vec::new();
+    search::search_manuscript(&project, &query, &options.unwrap_or_default())
+}
 
-    for (id, node) in &project.structure.nodes {
-        if node.node_type != crate::manuscript::project::NodeType::Chapter {
-            continue;
-        }
-        let chapter_path = project.path.join("chapters").join(format!("{}.md", id));
-        if !chapter_path.exists() {
-            continue;
-        }
-        let content = std::fs::read_to_string(&chapter_path).unwrap_or_default();
-        // Skip frontmatter
-        let text = if content.starts_with("---") {
-            content.splitn(3, "---").nth(2).unwrap_or("").to_string()
-        } else {
-            content
-        };
+/// Search the manuscript in the background and stream the results: one
+/// `search-result` event per chapter with matches, in reading order, then a
+/// `search-finished` event with the totals. Returns the search id the
+/// events carry. Starting another search cancels this one.
+#[tauri::command]
+pub fn start_search(
+    app: AppHandle,
+    active: State<'_, ActiveSearch>,
+    project_path: String,
+    query: String,
+    options: Option<SearchOptions>,
+) -> Result<u64, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let search = ManuscriptSearch::new(&query, &options.unwrap_or_default())?;
+    let search_id = active.0.fetch_add(1, Ordering::SeqCst) + 1;
+    let newest = Arc::clone(&active.0);
 
-        let mut matches = Vec::new();
-        for (line_idx, line) in text.lines().enumerate() {
-            let (search_line, search_query) = if case_sensitive {
-                (line.to_string(), query.clone())
-            } else {
-                (line.to_lowercase(), query.to_lowercase())
-            };
+    std::thread::spawn(move || {
+        let summary = search.run(
+            &project,
+            || newest.load(Ordering::SeqCst) != search_id,
+            |result| {
+                let _ = app.emit(search::RESULT_EVENT, SearchResultEvent { search_id, result });
+            },
+        );
+        let _ = app.emit(search::FINISHED_EVENT, SearchFinished { search_id, summary });
+    });
+    Ok(search_id)
+}
 
-            let mut start = 0;
-            while let Some(pos) = search_line[start..].find(&search_query) {
-                let abs_pos = start + pos;
-                matches.push(SearchMatch {
-                    line_number: line_idx + 1,
-                    line_content: line.to_string(),
-                    start: abs_pos,
-                    end: abs_pos + query.len(),
-                });
-                start = abs_pos + 1;
-            }
-        }
+/// Stop a streamed search if it is still the newest one.
+#[tauri::command]
+pub fn cancel_search(active: State<'_, ActiveSearch>, search_id: u64) {
+    let _ = active
+        .0
+        .compare_exchange(search_id, search_id + 1, Ordering::SeqCst, Ordering::SeqCst);
+}
 
-        if !matches.is_empty() {
-            results.push(SearchResult {
-                chapter_id: id.clone(),
-                chapter_title: node.title.clone(),
-                matches,
-            });
-        }
-    }
+/// Search chapters, notes, character sheets, ghost notes and timeline
+/// events, grouped by kind.
+#[tauri::command]
+pub fn search_project(
+    project_path: String,
+    query: String,
+    options: Option<SearchOptions>,
+) -> Result<Vec<SourceGroup>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    search::search_project(&project, &query, &options.unwrap_or_default())
+}
 
-    Ok(results)
+/// Ranked search of chapters and notes through the project's index.
+#[tauri::command]
+pub fn search_index(project_path: String, query: String, limit: Option<usize>) -> Result<Vec<IndexHit>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    search::search_index(&project, &query, limit.unwrap_or(50))
+}
+
+/// Spellings of `word` used in the manuscript, near misses included, with
+/// their counts and occurrences.
+#[tauri::command]
+pub fn fuzzy_search(project_path: String, word: String) -> Result<FuzzyResults, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    search::fuzzy_search(&project, &word)
+}
+
+/// Dry run: list every change a replace would make without writing.
+#[tauri::command]
+pub fn preview_replace(
+    project_path: String,
+    query: String,
+    replacement: String,
+    options: Option<SearchOptions>,
+) -> Result<Vec<ProposedReplacement>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    search::preview_replace(&project, &query, &replacement, &options.unwrap_or_default())
+}
+
+/// Apply the changes from `preview_replace` whose ids are in `selected`.
+#[tauri::command]
+pub fn apply_replace(
+    project_path: String,
+    query: String,
+    replacement: String,
+    options: Option<SearchOptions>,
+    selected: Vec<String>,
+) -> Result<ReplaceOutcome, ProjectError> {
+    let mut project = Project::open(&PathBuf::from(&project_path))?;
+    search::apply_replace(&mut project, &query, &replacement, &options.unwrap_or_default(), &selected)
 }
@@ -0,0 +1,142 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use quillborn_core::manuscript::error::WithPath;
+use quillborn_core::manuscript::project::{Project, ProjectError};
+use quillborn_core::settings::{self, Settings, SettingsChanged};
+
+fn config_dir(app: &AppHandle) -> Result<PathBuf, ProjectError> {
+    app.path()
+        .app_config_dir()
+        .map_err(|e| ProjectError::AppDirUnavailable {
+            dir: "config",
+            message: e.to_string(),
+        })
+}
+
+/// Global settings, with the project's overrides on top when a project is
+/// given.
+fn effective(app: &AppHandle, project_path: Option<&str>) -> Result<Settings, ProjectError> {
+    let global = Settings::load(&config_dir(app)?)?;
+    match project_path {
+        Some(path) => global.with_overrides(&Project::open(&PathBuf::from(path))?.metadata.settings),
+        None => Ok(global),
+    }
+}
+
+fn save_and_notify(app: &AppHandle, settings: &Settings) -> Result<(), ProjectError> {
+    settings.save(&config_dir(app)?)?;
+    let _ = app.emit(
+        settings::CHANGED_EVENT,
+        SettingsChanged {
+            settings: settings.clone(),
+            project_path: None,
+        },
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_settings(app: AppHandle, project_path: Option<String>) -> Result<Settings, ProjectError> {
+    effective(&app, project_path.as_deref())
+}
+
+#[tauri::command]
+pub fn update_settings(app: AppHandle, patch: Value) -> Result<Settings, ProjectError> {
+    let settings = Settings::load(&config_dir(&app)?)?.patched(&patch)?;
+    save_and_notify(&app, &settings)?;
+    Ok(settings)
+}
+
+#[tauri::command]
+pub fn reset_settings(app: AppHandle) -> Result<Settings, ProjectError> {
+    let settings = Settings::default();
+    save_and_notify(&app, &settings)?;
+    Ok(settings)
+}
+
+/// Only the values this project overrides.
+#[tauri::command]
+pub fn get_project_settings(project_path: String) -> Result<Value, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    Ok(serde_json::to_value(&project.metadata.settings)?)
+}
+
+#[tauri::command]
+pub fn update_project_settings(app: AppHandle, project_path: String, patch: Value) -> Result<Settings, ProjectError> {
+    let mut project = Project::open(&PathBuf::from(&project_path))?;
+    settings::update_project_overrides(&mut project, &patch)?;
+    let settings = effective(&app, Some(&project_path))?;
+    let _ = app.emit(
+        settings::CHANGED_EVENT,
+        SettingsChanged {
+            settings: settings.clone(),
+            project_path: Some(project_path),
+        },
+    );
+    Ok(settings)
+}
+
+/// Replace the global settings with a settings file the user picks. The
+/// file is chosen here rather than by the webview, so only a file the user
+/// picked can be read. Returns `None` when the dialog is cancelled. Async so
+/// the blocking dialog stays off the main thread.
+#[tauri::command]
+pub async fn import_settings(app: AppHandle) -> Result<Option<Settings>, ProjectError> {
+    let Some(chosen) = app
+        .dialog()
+        .file()
+        .set_title("Import settings")
+        .add_filter("Settings", &["toml"])
+        .blocking_pick_file()
+    else {
+        return Ok(None);
+    };
+    let Ok(path) = chosen.into_path() else {
+        return Ok(None);
+    };
+    let text = fs::read_to_string(&path).with_path(&path)?;
+    let settings = Settings::from_toml(&text)?;
+    save_and_notify(&app, &settings)?;
+    Ok(Some(settings))
+}
+
+/// Write the global settings to a file the user picks in a save dialog and
+/// return its path, or `None` when the dialog is cancelled.
+#[tauri::command]
+pub async fn export_settings(app: AppHandle) -> Result<Option<String>, ProjectError> {
+    let text = Settings::load(&config_dir(&app)?)?.to_toml()?;
+    let Some(chosen) = app
+        .dialog()
+        .file()
+        .set_title("Export settings")
+        .set_file_name(settings::SETTINGS_FILE)
+        .add_filter("Settings", &["toml"])
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let Ok(path) = chosen.into_path() else {
+        return Ok(None);
+    };
+    fs::write(&path, text).with_path(&path)?;
+    Ok(Some(path.display().to_string()))
+}
+
+/// One-time move of the old `quillborn-*` localStorage values into the
+/// settings file. Does nothing once a settings file exists.
+#[tauri::command]
+pub fn migrate_local_storage(app: AppHandle, entries: HashMap<String, String>) -> Result<Settings, ProjectError> {
+    let dir = config_dir(&app)?;
+    if dir.join(settings::SETTINGS_FILE).exists() {
+        return Settings::load(&dir);
+    }
+    let settings = Settings::from_local_storage(&entries);
+    save_and_notify(&app, &settings)?;
+    Ok(settings)
+}
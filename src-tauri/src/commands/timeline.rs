@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+use quillborn_core::manuscript::project::{Project, ProjectError};
+use quillborn_core::planning::timeline::{Timeline, TimelineEvent, TimelineIssue, TimelineTrack};
+
+#[tauri::command]
+pub fn get_timeline(project_path: String) -> Result<Timeline, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    Timeline::load(&project)
+}
+
+#[tauri::command]
+pub fn save_timeline_event(
+    project_path: String,
+    event: TimelineEvent,
+) -> Result<Timeline, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut timeline = Timeline::load(&project)?;
+    timeline.upsert_event(event);
+    timeline.save(&project)?;
+    Ok(timeline)
+}
+
+#[tauri::command]
+pub fn delete_timeline_event(
+    project_path: String,
+    event_id: String,
+) -> Result<Timeline, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut timeline = Timeline::load(&project)?;
+    timeline.remove_event(&event_id)?;
+    timeline.save(&project)?;
+    Ok(timeline)
+}
+
+#[tauri::command]
+pub fn save_timeline_tracks(
+    project_path: String,
+    tracks: Vec<TimelineTrack>,
+) -> Result<Timeline, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let mut timeline = Timeline::load(&project)?;
+    timeline.tracks = tracks;
+    timeline.save(&project)?;
+    Ok(timeline)
+}
+
+#[tauri::command]
+pub fn check_timeline(project_path: String) -> Result<Vec<TimelineIssue>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    Timeline::load(&project)?.check(&project)
+}
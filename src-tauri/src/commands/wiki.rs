@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+use quillborn_core::manuscript::project::{Project, ProjectError};
+use quillborn_core::planning::wiki::{IndexEntry, IndexedLink, LinkIndex};
+
+#[derive(serde::Serialize)]
+pub struct LinkIndexState {
+    pub entries: Vec<IndexEntry>,
+    pub links: Vec<IndexedLink>,
+    pub broken: Vec<IndexedLink>,
+}
+
+#[derive(serde::Serialize)]
+pub struct EntryLinks {
+    pub outgoing: Vec<IndexedLink>,
+    pub backlinks: Vec<IndexedLink>,
+}
+
+#[tauri::command]
+pub fn get_link_index(project_path: String) -> Result<LinkIndexState, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let index = LinkIndex::build(&project)?;
+    Ok(LinkIndexState {
+        broken: index.broken_links(),
+        entries: index.entries,
+        links: index.links,
+    })
+}
+
+#[tauri::command]
+pub fn get_entry_links(project_path: String, entry_id: String) -> Result<EntryLinks, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    let index = LinkIndex::build(&project)?;
+    Ok(EntryLinks {
+        outgoing: index.outgoing(&entry_id),
+        backlinks: index.backlinks(&entry_id),
+    })
+}
+
+#[tauri::command]
+pub fn get_broken_links(project_path: String) -> Result<Vec<IndexedLink>, ProjectError> {
+    let project = Project::open(&PathBuf::from(&project_path))?;
+    Ok(LinkIndex::build(&project)?.broken_links())
+}
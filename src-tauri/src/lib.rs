@@ -1,11 +1,12 @@
 mod commands;
-mod export;
-mod manuscript;
 
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
+        .manage(commands::automation::AutomationServer::default())
+        .manage(commands::search::ActiveSearch::default())
+        .manage(commands::export::ExportDirs::default())
         .invoke_handler(tauri::generate_handler![
             commands::manuscript::create_project,
             commands::manuscript::open_project,
@@ -14,17 +15,97 @@ pub fn run() {
             commands::manuscript::update_chapter,
             commands::manuscript::delete_chapter,
             commands::manuscript::rename_chapter,
+            commands::manuscript::set_chapter_status,
+            commands::manuscript::set_node_tags,
             commands::manuscript::reorder_chapters,
+            commands::manuscript::move_chapter,
             commands::manuscript::get_chapter_content,
             commands::manuscript::create_snapshot,
+            commands::manuscript::restore_snapshot,
             commands::manuscript::get_project_state,
+            commands::history::get_history,
+            commands::history::undo,
+            commands::history::redo,
+            commands::plugins::list_plugins,
+            commands::plugins::export_with_plugin,
+            commands::plugins::run_plugin_checks,
+            commands::plugins::run_plugin_command,
+            commands::automation::start_automation_server,
+            commands::automation::stop_automation_server,
+            commands::automation::automation_server_status,
+            commands::settings::get_settings,
+            commands::settings::update_settings,
+            commands::settings::reset_settings,
+            commands::settings::get_project_settings,
+            commands::settings::update_project_settings,
+            commands::settings::import_settings,
+            commands::settings::export_settings,
+            commands::settings::migrate_local_storage,
+            commands::export::choose_export_path,
             commands::export::export_markdown,
             commands::export::export_plain_text,
             commands::export::export_html,
             commands::export::export_latex,
             commands::export::export_epub,
             commands::search::search_manuscript,
+            commands::search::start_search,
+            commands::search::cancel_search,
+            commands::search::search_project,
+            commands::search::search_index,
+            commands::search::fuzzy_search,
+            commands::search::preview_replace,
+            commands::search::apply_replace,
+            commands::collections::list_collections,
+            commands::collections::create_collection,
+            commands::collections::update_collection,
+            commands::collections::delete_collection,
+            commands::collections::get_collection_members,
+            commands::analysis::lint_manuscript,
+            commands::analysis::lint_chapter,
+            commands::analysis::overused_words,
+            commands::analysis::get_style_config,
+            commands::analysis::update_style_config,
             commands::fonts::scan_fonts,
+            commands::characters::list_characters,
+            commands::characters::get_character,
+            commands::characters::create_character,
+            commands::characters::update_character,
+            commands::characters::delete_character,
+            commands::characters::set_character_portrait,
+            commands::characters::character_neighbours,
+            commands::characters::character_relationship_path,
+            commands::characters::character_relationships_by_type,
+            commands::characters::character_mentions,
+            commands::characters::find_character_mentions,
+            commands::notes::list_notes,
+            commands::notes::get_note,
+            commands::notes::create_note,
+            commands::notes::update_note,
+            commands::notes::rename_note,
+            commands::notes::delete_note,
+            commands::wiki::get_link_index,
+            commands::wiki::get_entry_links,
+            commands::wiki::get_broken_links,
+            commands::timeline::get_timeline,
+            commands::timeline::save_timeline_event,
+            commands::timeline::delete_timeline_event,
+            commands::timeline::save_timeline_tracks,
+            commands::timeline::check_timeline,
+            commands::ghost_notes::list_ghost_notes,
+            commands::ghost_notes::create_ghost_note,
+            commands::ghost_notes::dismiss_ghost_note,
+            commands::ghost_notes::delete_ghost_note,
+            commands::ghost_notes::record_chapter_visit,
+            commands::planning::get_corkboard,
+            commands::planning::save_corkboard_card,
+            commands::planning::delete_corkboard_card,
+            commands::planning::list_plot_threads,
+            commands::planning::create_plot_thread,
+            commands::planning::update_plot_thread,
+            commands::planning::delete_plot_thread,
+            commands::planning::assign_chapter_to_thread,
+            commands::planning::unassign_chapter_from_thread,
+            commands::planning::plot_thread_reports,
         ])
         .run(tauri::generate_context!())
         .expect("error while running Quillborn");
@@ -166,12 +166,12 @@ const App: Component = () => {
     const ext = format === "markdown" ? "md" : "txt";
     const cmd = format === "markdown" ? "export_markdown" : "export_plain_text";
     try {
-      const { save } = await import("@tauri-apps/plugin-dialog");
       const { invoke } = await import("@tauri-apps/api/core");
-      const path = await save({
+      const path = await invoke<string | null>("choose_export_path", {
         title: `Export ${format === "markdown" ? "Markdown" : "Plain Text"}`,
-        defaultPath: `${store.project.metadata.title}.${ext}`,
-        filters: [{ name: format === "markdown" ? "Markdown" : "Plain Text", extensions: [ext] }],
+        fileName: `${store.project.metadata.title}.${ext}`,
+        filterName: format === "markdown" ? "Markdown" : "Plain Text",
+        extensions: [ext],
       });
       if (path) {
         setBindingRitual({ title: store.project.metadata.title, format: ext });
@@ -185,13 +185,13 @@ const App: Component = () => {
   const handleExportFormat = async (cmd: string, ext: string) => {
     if (!store.project) return;
     try {
-      const { save } = await import("@tauri-apps/plugin-dialog");
       const { invoke } = await import("@tauri-apps/api/core");
       const filterName = ext.toUpperCase();
-      const path = await save({
+      const path = await invoke<string | null>("choose_export_path", {
         title: `Export ${filterName}`,
-        defaultPath: `${store.project.metadata.title}.${ext}`,
-        filters: [{ name: filterName, extensions: [ext] }],
+        fileName: `${store.project.metadata.title}.${ext}`,
+        filterName,
+        extensions: [ext],
       });
       if (path) {
         setBindingRitual({ title: store.project.metadata.title, format: ext });
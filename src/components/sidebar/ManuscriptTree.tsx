@@ -1,6 +1,7 @@
 import { Component, For, Show, createSignal, createMemo } from "solid-js";
 import { manuscriptStore, ManuscriptNode } from "@/stores/manuscript";
 import { TreeNode } from "./TreeNode";
+import { SmartCollections } from "./SmartCollections";
 import { Button } from "@/components/ui/Button";
 
 export const ManuscriptTree: Component = () => {
@@ -100,6 +101,8 @@ export const ManuscriptTree: Component = () => {
               <p class="manuscript-tree__empty-hint">Create your first chapter to begin writing.</p>
             </div>
           </Show>
+
+          <SmartCollections />
         </div>
 
         <div class="manuscript-tree__footer">
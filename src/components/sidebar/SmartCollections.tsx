@@ -0,0 +1,206 @@
+import { Component, For, Show, createEffect, createSignal, on } from "solid-js";
+import { manuscriptStore } from "@/stores/manuscript";
+import { collectionsStore, SmartCollection } from "@/stores/collections";
+import { TreeNode } from "./TreeNode";
+
+export const SmartCollections: Component = () => {
+  const { store } = manuscriptStore;
+  const { collections, members, load, refresh, refreshOpen, create, remove } = collectionsStore;
+  const [open, setOpen] = createSignal<Record<string, boolean>>({});
+  const [adding, setAdding] = createSignal(false);
+  const [name, setName] = createSignal("");
+  const [query, setQuery] = createSignal("");
+  const [error, setError] = createSignal<string | null>(null);
+
+  createEffect(on(() => store.project?.path, (path) => {
+    if (path) load(path).catch((e) => console.error("Failed to load collections:", e));
+  }));
+
+  // Membership follows the manuscript, so re-evaluate after any change to it.
+  createEffect(on(() => store.project?.structure, () => {
+    if (store.project) refreshOpen(store.project.path).catch(() => {});
+  }, { defer: true }));
+
+  const toggle = async (collection: SmartCollection) => {
+    const opening = !open()[collection.id];
+    setOpen((prev) => ({ ...prev, [collection.id]: opening }));
+    if (opening && store.project) await refresh(store.project.path, collection.id);
+  };
+
+  const save = async () => {
+    if (!store.project || !name().trim() || !query().trim()) return;
+    try {
+      await create(store.project.path, name().trim(), query().trim());
+      setName("");
+      setQuery("");
+      setAdding(false);
+      setError(null);
+    } catch (e) {
+      setError((e as { message?: string }).message ?? String(e));
+    }
+  };
+
+  const exportCollection = async (collection: SmartCollection) => {
+    if (!store.project) return;
+    try {
+      const { invoke } = await import("@tauri-apps/api/core");
+      const path = await invoke<string | null>("choose_export_path", {
+        title: `Export ${collection.name}`,
+        fileName: `${collection.name}.md`,
+        filterName: "Markdown",
+        extensions: ["md"],
+      });
+      if (path) {
+        await invoke("export_markdown", {
+          projectPath: store.project.path,
+          outputPath: path,
+          collection: collection.id,
+        });
+      }
+    } catch (e) {
+      console.error("Export failed:", e);
+    }
+  };
+
+  return (
+    <section class="smart-collections">
+      <div class="smart-collections__header">
+        <span class="smart-collections__title">Collections</span>
+        <button class="smart-collections__icon-btn" title="New collection" onClick={() => setAdding(!adding())}>
+          +
+        </button>
+      </div>
+
+      <Show when={adding()}>
+        <form class="smart-collections__form" onSubmit={(e) => { e.preventDefault(); void save(); }}>
+          <input
+            class="smart-collections__input"
+            placeholder="Name"
+            value={name()}
+            onInput={(e) => setName(e.currentTarget.value)}
+          />
+          <input
+            class="smart-collections__input"
+            placeholder="pov:Mara status:draft"
+            value={query()}
+            onInput={(e) => setQuery(e.currentTarget.value)}
+          />
+          <Show when={error()}>
+            <p class="smart-collections__error">{error()}</p>
+          </Show>
+          <button class="smart-collections__save" type="submit">Save</button>
+        </form>
+      </Show>
+
+      <For each={collections()}>
+        {(collection) => (
+          <div class="smart-collections__item">
+            <div class="smart-collections__row">
+              <button class="smart-collections__name" title={collection.query} onClick={() => toggle(collection)}>
+                {collection.name}
+                <Show when={members[collection.id]}>
+                  <span class="smart-collections__count">{members[collection.id].length}</span>
+                </Show>
+              </button>
+              <button class="smart-collections__icon-btn" title="Export as Markdown" onClick={() => exportCollection(collection)}>
+                ↓
+              </button>
+              <button
+                class="smart-collections__icon-btn"
+                title="Delete collection"
+                onClick={() => store.project && remove(store.project.path, collection.id)}
+              >
+                ×
+              </button>
+            </div>
+            <Show when={open()[collection.id]}>
+              <For each={members[collection.id] ?? []}>
+                {(node) => (
+                  <TreeNode node={node} depth={1} allNodes={store.project?.structure.nodes || {}} />
+                )}
+              </For>
+            </Show>
+          </div>
+        )}
+      </For>
+
+      <style>{`
+        .smart-collections {
+          border-top: 1px solid var(--color-border-subtle);
+          padding: var(--space-xs) 0;
+        }
+
+        .smart-collections__header,
+        .smart-collections__row {
+          display: flex;
+          align-items: center;
+          gap: var(--space-xs);
+          padding: 0 var(--space-md);
+        }
+
+        .smart-collections__title {
+          flex: 1;
+          font-size: var(--font-size-xs);
+          text-transform: uppercase;
+          letter-spacing: 0.05em;
+          color: var(--color-ghost);
+        }
+
+        .smart-collections__name {
+          flex: 1;
+          display: flex;
+          justify-content: space-between;
+          padding: var(--space-xs) 0;
+          font-size: var(--font-size-sm);
+          color: var(--color-ink);
+          text-align: left;
+        }
+
+        .smart-collections__count {
+          color: var(--color-ghost);
+          font-size: var(--font-size-xs);
+        }
+
+        .smart-collections__icon-btn {
+          color: var(--color-ghost);
+          font-size: var(--font-size-sm);
+          padding: 0 var(--space-xs);
+          border-radius: var(--radius-sm);
+        }
+
+        .smart-collections__icon-btn:hover {
+          color: var(--color-ink);
+          background: var(--color-bone-dust);
+        }
+
+        .smart-collections__form {
+          display: flex;
+          flex-direction: column;
+          gap: var(--space-xs);
+          padding: var(--space-xs) var(--space-md);
+        }
+
+        .smart-collections__input {
+          font-size: var(--font-size-sm);
+          color: var(--color-ink);
+          background: var(--color-bone);
+          border: none;
+          border-radius: var(--radius-sm);
+          padding: var(--space-xs) var(--space-sm);
+          outline: none;
+        }
+
+        .smart-collections__error {
+          font-size: var(--font-size-xs);
+          color: var(--color-status-trash);
+        }
+
+        .smart-collections__save {
+          align-self: flex-end;
+          font-size: var(--font-size-sm);
+          color: var(--color-ink);
+        }
+      `}</style>
+    </section>
+  );
+};
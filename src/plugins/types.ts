@@ -18,3 +18,25 @@ export interface PluginRegistry {
   toggle: (pluginId: string) => void;
   getPlugin: (pluginId: string) => QuillbornPlugin | undefined;
 }
+
+/** A sandboxed WASM plugin as reported by the backend's `list_plugins`. */
+export interface BackendPlugin {
+  id: string;
+  name: string;
+  version: string;
+  api_version: number;
+  description: string;
+  author: string;
+  permissions: (
+    | "read_chapters"
+    | "notes"
+    | "register_export"
+    | "register_check"
+    | "register_command"
+  )[];
+  contributions: {
+    exports: { id: string; name: string; extension: string }[];
+    checks: { id: string; name: string; description: string }[];
+    commands: { id: string; label: string }[];
+  };
+}
@@ -0,0 +1,54 @@
+import { createSignal, createRoot } from "solid-js";
+import { createStore, produce } from "solid-js/store";
+import { invoke } from "@tauri-apps/api/core";
+import type { ManuscriptNode } from "./manuscript";
+
+/** Mirrors `quillborn_core::search::SmartCollection`. */
+export interface SmartCollection {
+  id: string;
+  name: string;
+  /** A full-text index query, e.g. `pov:Mara status:draft`. */
+  query: string;
+}
+
+function createCollectionsStore() {
+  const [collections, setCollections] = createSignal<SmartCollection[]>([]);
+  // Members are only known for collections that have been opened.
+  const [members, setMembers] = createStore<Record<string, ManuscriptNode[]>>({});
+
+  const load = async (projectPath: string) => {
+    setCollections(await invoke<SmartCollection[]>("list_collections", { projectPath }));
+  };
+
+  /** Evaluate a collection's query against the manuscript as it is now. */
+  const refresh = async (projectPath: string, collectionId: string) => {
+    const nodes = await invoke<ManuscriptNode[]>("get_collection_members", { projectPath, collectionId });
+    setMembers(collectionId, nodes);
+  };
+
+  /** Re-evaluate every collection that has been opened. */
+  const refreshOpen = (projectPath: string) =>
+    Promise.all(Object.keys(members).map((id) => refresh(projectPath, id)));
+
+  const create = async (projectPath: string, name: string, query: string) => {
+    const collection = await invoke<SmartCollection>("create_collection", { projectPath, name, query });
+    setCollections((prev) => [...prev, collection]);
+    return collection;
+  };
+
+  const update = async (projectPath: string, collection: SmartCollection) => {
+    await invoke("update_collection", { projectPath, collection });
+    setCollections((prev) => prev.map((c) => (c.id === collection.id ? collection : c)));
+    await refresh(projectPath, collection.id);
+  };
+
+  const remove = async (projectPath: string, collectionId: string) => {
+    await invoke("delete_collection", { projectPath, collectionId });
+    setCollections((prev) => prev.filter((c) => c.id !== collectionId));
+    setMembers(produce((all) => { delete all[collectionId]; }));
+  };
+
+  return { collections, members, load, refresh, refreshOpen, create, update, remove };
+}
+
+export const collectionsStore = createRoot(createCollectionsStore);
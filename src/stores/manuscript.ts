@@ -9,6 +9,7 @@ export interface ManuscriptNode {
   status: "draft" | "revised" | "final" | "trash";
   mood?: string;
   pov?: string;
+  tags?: string[];
   word_count: number;
 }
 
@@ -28,6 +29,13 @@ export interface ManuscriptStructure {
   order: string[];
 }
 
+/** Shape of every error returned by a backend command. */
+export interface BackendError {
+  code: string;
+  message: string;
+  details: Record<string, unknown> | null;
+}
+
 export interface ProjectState {
   path: string;
   metadata: ProjectMetadata;
@@ -0,0 +1,97 @@
+import { createSignal, createRoot } from "solid-js";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+export interface SearchOptions {
+  case_sensitive?: boolean;
+  mode?: "literal" | "regex" | "fuzzy";
+  whole_word?: boolean;
+  accent_insensitive?: boolean;
+}
+
+/** Mirrors `quillborn_core::search::SearchMatch`. */
+export interface SearchMatch {
+  line_number: number;
+  start: number;
+  end: number;
+  start_utf16: number;
+  end_utf16: number;
+  start_char: number;
+  end_char: number;
+  snippet: { before: string; matched: string; after: string };
+}
+
+export interface SearchResult {
+  chapter_id: string;
+  chapter_title: string;
+  matches: SearchMatch[];
+}
+
+interface SearchSummary {
+  total_matches: number;
+  chapters: number;
+  cancelled: boolean;
+}
+
+function createSearchStore() {
+  const [results, setResults] = createSignal<SearchResult[]>([]);
+  const [summary, setSummary] = createSignal<SearchSummary | null>(null);
+  const [searching, setSearching] = createSignal(false);
+  let current: number | null = null;
+  let requests = 0;
+  // Events can beat `start_search`'s reply; hold them until the id is known.
+  let early: { searchId: number; apply: () => void }[] = [];
+
+  const accept = (searchId: number, apply: () => void) => {
+    if (searchId === current) apply();
+    else if (current === null && searching()) early.push({ searchId, apply });
+  };
+
+  const init = async () => {
+    await listen<{ search_id: number; result: SearchResult }>("search-result", (event) => {
+      const { search_id, result } = event.payload;
+      accept(search_id, () => setResults((prev) => [...prev, result]));
+    });
+    await listen<SearchSummary & { search_id: number }>("search-finished", (event) => {
+      const { search_id, ...totals } = event.payload;
+      accept(search_id, () => {
+        setSummary(totals);
+        setSearching(false);
+      });
+    });
+  };
+
+  const cancel = () => {
+    requests++;
+    if (current !== null) void invoke("cancel_search", { searchId: current });
+    current = null;
+    early = [];
+    setSearching(false);
+  };
+
+  /** Start a search, cancelling any search still running. */
+  const search = async (projectPath: string, query: string, options?: SearchOptions) => {
+    cancel();
+    setResults([]);
+    setSummary(null);
+    if (!query) return;
+
+    const request = requests;
+    setSearching(true);
+    try {
+      const searchId = await invoke<number>("start_search", { projectPath, query, options });
+      // A newer search has started meanwhile; the backend stops this one.
+      if (request !== requests) return;
+      current = searchId;
+      early.filter((e) => e.searchId === searchId).forEach((e) => e.apply());
+      early = [];
+    } catch (error) {
+      if (request === requests) setSearching(false);
+      throw error;
+    }
+  };
+
+  return { results, summary, searching, init, search, cancel };
+}
+
+export const searchStore = createRoot(createSearchStore);
@@ -0,0 +1,86 @@
+import { createSignal, createRoot } from "solid-js";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import type { ThemeName } from "./theme";
+
+/** Mirrors `quillborn_core::settings::Settings`. */
+export interface Settings {
+  locale: "en" | "es" | "fr" | "de" | "ja" | "zh" | "ar";
+  custom_css: string;
+  appearance: { theme: ThemeName; accent: string; grain: boolean };
+  writing: { daily_target: number };
+  sound: {
+    ambient: "rain" | "fireplace" | "wind" | "ocean" | "forest" | "silence";
+    keystroke: "mechanical" | "soft" | "pen" | "none";
+    volume: number;
+  };
+  fonts: { writing: string; display: string; ui: string };
+  theme_editor?: Record<string, string>;
+}
+
+/** A partial update; `null` resets an entry to its default. */
+export type SettingsPatch = {
+  [K in keyof Settings]?: Settings[K] extends object
+    ? { [P in keyof Settings[K]]?: Settings[K][P] | null } | null
+    : Settings[K] | null;
+};
+
+const LEGACY_KEYS = [
+  "quillborn-theme",
+  "quillborn-accent",
+  "quillborn-grain",
+  "quillborn-daily-target",
+  "quillborn-ambient",
+  "quillborn-keystroke",
+  "quillborn-volume",
+  "quillborn-locale",
+  "quillborn-custom-css",
+  "quillborn-fonts",
+  "quillborn-custom-theme",
+];
+
+function createSettingsStore() {
+  const [settings, setSettings] = createSignal<Settings | null>(null);
+  const [projectPath, setProjectPath] = createSignal<string | null>(null);
+
+  /** Load settings, carrying over old localStorage values the first time. */
+  const init = async () => {
+    const entries: Record<string, string> = {};
+    for (const key of LEGACY_KEYS) {
+      const value = localStorage.getItem(key);
+      if (value !== null) entries[key] = value;
+    }
+    setSettings(await invoke<Settings>("migrate_local_storage", { entries }));
+
+    await listen<{ settings: Settings; project_path: string | null }>(
+      "settings-changed",
+      (event) => {
+        const { project_path } = event.payload;
+        if (project_path === projectPath()) {
+          setSettings(event.payload.settings);
+        } else if (project_path === null) {
+          // Global change while a project is open: its overrides still apply.
+          void useProject(projectPath());
+        }
+      }
+    );
+  };
+
+  /** Switch to a project's effective settings, or back to the global ones. */
+  const useProject = async (path: string | null) => {
+    setProjectPath(path);
+    setSettings(await invoke<Settings>("get_settings", { projectPath: path }));
+  };
+
+  const update = (patch: SettingsPatch) => invoke<Settings>("update_settings", { patch });
+
+  const updateProject = (patch: SettingsPatch) => {
+    const path = projectPath();
+    if (!path) return update(patch);
+    return invoke<Settings>("update_project_settings", { projectPath: path, patch });
+  };
+
+  return { settings, projectPath, init, useProject, update, updateProject };
+}
+
+export const settingsStore = createRoot(createSettingsStore);
@@ -0,0 +1,117 @@
+import { createSignal, createRoot } from "solid-js";
+import { createStore, reconcile } from "solid-js/store";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+export type RuleId =
+  | "adverb"
+  | "filter_word"
+  | "passive_voice"
+  | "weasel_word"
+  | "repeated_opener"
+  | "long_sentence"
+  | "cliche"
+  | "echo";
+
+export type Severity = "info" | "warning" | "error";
+
+/** Mirrors `quillborn_core::analysis::Location`. */
+export interface Location {
+  line_number: number;
+  start: number;
+  end: number;
+  start_utf16: number;
+  end_utf16: number;
+  start_char: number;
+  end_char: number;
+}
+
+/** Mirrors `quillborn_core::analysis::Diagnostic`. */
+export interface Diagnostic extends Location {
+  rule: RuleId;
+  severity: Severity;
+  text: string;
+  message: string;
+  /** For echoes, the earlier use of the word. */
+  related?: Location;
+}
+
+export interface ChapterDiagnostics {
+  chapter_id: string;
+  chapter_title: string;
+  diagnostics: Diagnostic[];
+}
+
+/** Mirrors `quillborn_core::analysis::StyleConfig`, kept in `style.json`. */
+export interface StyleConfig {
+  disabled: RuleId[];
+  allow: Partial<Record<RuleId, string[]>>;
+  severity: Partial<Record<RuleId, Severity>>;
+  max_sentence_words: number;
+  max_repeated_openers: number;
+  echo_window: number;
+}
+
+/** Mirrors `quillborn_core::analysis::OverusedWord`. */
+export interface OverusedWord {
+  lemma: string;
+  count: number;
+  per_million: number;
+  baseline_per_million: number;
+  ratio: number;
+  locations: (Location & { chapter_id: string; text: string })[];
+}
+
+function createStyleStore() {
+  // Diagnostics by chapter id.
+  const [diagnostics, setDiagnostics] = createStore<Record<string, Diagnostic[]>>({});
+  const [config, setConfig] = createSignal<StyleConfig | null>(null);
+  const [overused, setOverused] = createSignal<OverusedWord[]>([]);
+
+  /** Take the diagnostics the backend sends after each chapter save. */
+  const init = async () => {
+    await listen<ChapterDiagnostics>("style-diagnostics", (event) => {
+      setDiagnostics(event.payload.chapter_id, event.payload.diagnostics);
+    });
+  };
+
+  const lintManuscript = async (projectPath: string) => {
+    const chapters = await invoke<ChapterDiagnostics[]>("lint_manuscript", { projectPath });
+    setDiagnostics(reconcile(Object.fromEntries(chapters.map((c) => [c.chapter_id, c.diagnostics]))));
+  };
+
+  const lintChapter = async (projectPath: string, chapterId: string) => {
+    const chapter = await invoke<ChapterDiagnostics>("lint_chapter", { projectPath, chapterId });
+    setDiagnostics(chapterId, chapter.diagnostics);
+  };
+
+  /** Book-wide pet words, most overused first. */
+  const loadOverused = async (projectPath: string, limit?: number) => {
+    setOverused(await invoke<OverusedWord[]>("overused_words", { projectPath, limit }));
+  };
+
+  const loadConfig = async (projectPath: string) => {
+    setConfig(await invoke<StyleConfig>("get_style_config", { projectPath }));
+  };
+
+  /** Save the project's rules and relint everything against them. */
+  const updateConfig = async (projectPath: string, next: StyleConfig) => {
+    await invoke("update_style_config", { projectPath, config: next });
+    setConfig(next);
+    await lintManuscript(projectPath);
+  };
+
+  return {
+    diagnostics,
+    config,
+    overused,
+    init,
+    lintManuscript,
+    lintChapter,
+    loadOverused,
+    loadConfig,
+    updateConfig,
+  };
+}
+
+export const styleStore = createRoot(createStyleStore);